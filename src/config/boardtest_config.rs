@@ -0,0 +1,70 @@
+//! Boardtest（远程板卡农场）连接配置
+//!
+//! boardtest 后端通过一个可配置的 HTTP/REST 任务接口执行命令：
+//! 提交命令 -> 轮询任务状态 -> 获取 stdout/stderr/退出码。
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BoardtestConfig {
+    /// 任务接口的基础地址，例如 "http://farm.example.com/api"
+    pub base_url: String,
+    /// 目标板卡标识，会随提交请求一起发送
+    #[serde(default)]
+    pub board: Option<String>,
+    /// 访问令牌（以 `Authorization: Bearer <token>` 发送）
+    #[serde(default)]
+    pub token: Option<String>,
+    /// 从该环境变量读取访问令牌（优先级低于 token）
+    #[serde(default)]
+    pub token_env: Option<String>,
+    /// 提交任务的路径（POST），默认 "/jobs"
+    #[serde(default)]
+    pub submit_path: Option<String>,
+    /// 查询任务状态的路径（GET），`{id}` 会被替换为任务ID，默认 "/jobs/{id}"
+    #[serde(default)]
+    pub status_path: Option<String>,
+    /// 获取任务结果的路径（GET）；不设置时直接从状态响应中读取结果
+    #[serde(default)]
+    pub result_path: Option<String>,
+    /// 取消任务的路径（DELETE），超时时调用；不设置则不取消
+    #[serde(default)]
+    pub cancel_path: Option<String>,
+    /// setup 时用于健康检查的路径（GET）；不设置则跳过
+    #[serde(default)]
+    pub health_path: Option<String>,
+    /// 轮询间隔（毫秒），默认 1000
+    #[serde(default)]
+    pub poll_interval_ms: Option<u64>,
+    /// 响应字段名映射
+    #[serde(default)]
+    pub fields: BoardtestFields,
+    /// 表示任务已成功结束的状态值，默认 ["finished", "completed", "done", "success"]
+    #[serde(default)]
+    pub done_statuses: Option<Vec<String>>,
+    /// 表示任务异常结束（未得到命令结果）的状态值，默认 ["error", "failed", "cancelled", "canceled"]
+    #[serde(default)]
+    pub failed_statuses: Option<Vec<String>>,
+}
+
+/// 任务接口 JSON 响应中各字段的名称
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BoardtestFields {
+    pub id: String,
+    pub status: String,
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: String,
+}
+
+impl Default for BoardtestFields {
+    fn default() -> Self {
+        Self {
+            id: "id".to_string(),
+            status: "status".to_string(),
+            stdout: "stdout".to_string(),
+            stderr: "stderr".to_string(),
+            exit_code: "exit_code".to_string(),
+        }
+    }
+}
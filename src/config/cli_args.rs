@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
 use std::path::PathBuf;
 
 // Lintestor - 执行和管理嵌入在Markdown文件中的测试
@@ -7,54 +7,87 @@ use std::path::PathBuf;
     name = "lintestor",
     version,
     about = "Execute and manage tests embedded in Markdown files",
-    override_usage = "lintestor [OPTIONS] { --test | --parse-only }\n       lintestor --test [TEST_OPTIONS]\n       lintestor --parse-only [PARSE_OPTIONS]",
-    after_help = "EXECUTION MODES:\n  --test                 Execute test templates\n  --parse-only           Parse templates without execution\n\nENVIRONMENT TYPES:\n  --local                Execute in local environment\n  --remote               Execute on remote target via SSH\n  --qemu                 Execute in QEMU virtual machine\n  --serial               Execute via serial connection\n\nFILTER OPTIONS:\n  --unit <NAME>          Filter tests by unit name\n  --tag <TAG>            Filter tests by tag\n  --target <FILE>        Use specific target configuration\n\nEXAMPLES:\n  lintestor --test --template T.test.md\n  lintestor --test --test-dir tests/ --local\n  lintestor --test --remote --target prod.toml --unit integration\n  lintestor --parse-only --template test.md\n  lintestor --test --qemu --continue-on-error --timeout 600"
+    args_conflicts_with_subcommands = true,
+    after_help = "ENVIRONMENT TYPES (run):\n  --local                Execute in local environment\n  --remote               Execute on remote target via SSH\n  --qemu                 Execute in QEMU virtual machine\n  --serial               Execute via serial connection\n\nFILTER OPTIONS:\n  --unit <NAME>          Filter tests by unit name\n  --tag <TAG>            Filter tests by tag\n  --target <FILE>        Use specific target configuration\n\nEXAMPLES:\n  lintestor run --template T.test.md\n  lintestor run --test-dir tests/ --local\n  lintestor run --remote --target prod.toml --unit integration\n  lintestor parse --template test.md\n  lintestor list --steps --unit curl\n  lintestor validate --test-dir tests/\n  lintestor run --qemu --continue-on-error --timeout 600\n  lintestor run --jobs 4\n  lintestor run --format json,junit,html\n  lintestor run --format tap\n  lintestor report --compare-with last\n\nThe flat form `lintestor --test [OPTIONS]` / `lintestor --parse-only [OPTIONS]` is deprecated\nbut still accepted; it behaves like `lintestor run` / `lintestor parse`."
 )]
-pub struct CliArgs {
-    // Run tests - Execute commands in test templates
-    // 运行测试 - 执行测试模板中的命令
-    #[clap(short = 't', long = "test", help = "Execute test templates")]
-    pub test: bool,
-
-    // Parse only - Parse test templates without executing commands
-    // 仅解析 - 只解析测试模板但不执行命令
-    #[clap(
-        short = 'p',
-        long = "parse-only",
-        help = "Parse templates without execution"
-    )]
-    pub parse_only: bool,
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
 
     // Verbose mode - Show more log information
     // 详细模式 - 显示更多日志信息
-    #[clap(short = 'v', long = "verbose", help = "Enable verbose logging")]
+    #[clap(
+        short = 'v',
+        long = "verbose",
+        global = true,
+        conflicts_with = "quiet",
+        help = "Enable verbose logging"
+    )]
     pub verbose: bool,
 
     // Quiet mode - Suppress prompts and progress information
     // 安静模式 - 不显示提示和进度信息
-    #[clap(short = 'q', long = "quiet", help = "Suppress non-essential output")]
+    #[clap(
+        short = 'q',
+        long = "quiet",
+        global = true,
+        help = "Suppress non-essential output"
+    )]
     pub quiet: bool,
 
-    // Local test mode - Execute tests in local environment
-    // 本地测试模式 - 在本地环境中执行测试
-    #[clap(long = "local", help = "Execute in local environment")]
-    pub local: bool,
+    // Deprecated: run tests, replaced by `lintestor run`
+    // 已弃用：运行测试，由 `lintestor run` 代替
+    #[clap(short = 't', long = "test", hide = true, conflicts_with = "parse_only")]
+    pub test: bool,
 
-    // Remote test mode - Execute tests in remote environment
-    // 远程测试模式 - 在远程环境中执行测试
-    #[clap(long = "remote", help = "Execute on remote target via SSH")]
-    pub remote: bool,
+    // Deprecated: parse templates only, replaced by `lintestor parse`
+    // 已弃用：仅解析模板，由 `lintestor parse` 代替
+    #[clap(short = 'p', long = "parse-only", hide = true)]
+    pub parse_only: bool,
 
-    // QEMU test mode - Execute tests in QEMU environment
-    // QEMU测试模式 - 在QEMU环境中执行测试
-    #[clap(long = "qemu", help = "Execute in QEMU virtual machine")]
-    pub qemu: bool,
+    // Options of the deprecated flat form, same as `lintestor run`
+    // 已弃用的扁平形式的选项，与 `lintestor run` 相同
+    #[clap(
+        flatten,
+        next_help_heading = "Deprecated flat options (use `lintestor run`)"
+    )]
+    pub legacy: RunArgs,
+}
 
-    // Serial test mode - Execute tests via serial connection
-    // 串口测试模式 - 通过串口执行测试
-    #[clap(long = "serial", help = "Execute via serial connection")]
-    pub serial: bool,
+/// Subcommands
+/// 子命令
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    // Execute test templates
+    // 执行测试模板
+    #[clap(about = "Execute test templates")]
+    Run(RunArgs),
+
+    // Parse templates without execution
+    // 只解析模板，不执行
+    #[clap(about = "Parse templates without execution")]
+    Parse(TemplateArgs),
+
+    // List the templates (and optionally steps) that would run
+    // 列出将要执行的模板（以及步骤）
+    #[clap(about = "List templates, their targets and optionally their steps")]
+    List(ListArgs),
 
+    // Check templates for problems without executing them
+    // 检查模板中的问题，不执行
+    #[clap(about = "Check templates for problems and report them with file:line locations")]
+    Validate(TemplateArgs),
+
+    // Show recorded runs and compare them
+    // 查看和对比历史运行
+    #[clap(about = "Show recorded runs from the run history and compare them")]
+    Report(ReportArgs),
+}
+
+/// Template selection shared by all subcommands
+/// 各子命令共用的模板选择参数
+#[derive(Args, Debug, Clone, Default)]
+pub struct TemplateArgs {
     // Test template path - Specify path to a single test template
     // 测试模板路径 - 指定单一测试模板的路径
     #[clap(long = "template", help = "Path to test template file")]
@@ -69,20 +102,6 @@ pub struct CliArgs {
     )]
     pub test_dir: Option<PathBuf>,
 
-    // Reports directory - Specify directory for test reports
-    // 报告目录 - 指定存放测试报告的目录
-    #[clap(long = "reports-dir", help = "Output directory for test reports")]
-    pub reports_dir: Option<PathBuf>,
-
-    // Aggregate report output - Specify output file path for aggregate report
-    // 聚合报告输出 - 指定聚合报告的输出文件路径
-    #[clap(
-        long = "output",
-        short = 'o',
-        help = "Output file for aggregate report"
-    )]
-    pub output: Option<PathBuf>,
-
     // Unit name - Filter tests by unit name
     // 单元名称 - 通过单元名称筛选测试
     #[clap(long = "unit", help = "Filter tests by unit name")]
@@ -97,6 +116,72 @@ pub struct CliArgs {
     // 目标配置文件 - 指定目标配置文件路径
     #[clap(long = "target", help = "Target configuration file")]
     pub target: Option<PathBuf>,
+}
+
+/// Options of `lintestor run`
+/// `lintestor run` 的选项
+#[derive(Args, Debug, Clone, Default)]
+pub struct RunArgs {
+    #[clap(flatten)]
+    pub templates: TemplateArgs,
+
+    // Local test mode - Execute tests in local environment
+    // 本地测试模式 - 在本地环境中执行测试
+    #[clap(
+        long = "local",
+        group = "environment",
+        help = "Execute in local environment"
+    )]
+    pub local: bool,
+
+    // Remote test mode - Execute tests in remote environment
+    // 远程测试模式 - 在远程环境中执行测试
+    #[clap(
+        long = "remote",
+        group = "environment",
+        help = "Execute on remote target via SSH"
+    )]
+    pub remote: bool,
+
+    // QEMU test mode - Execute tests in QEMU environment
+    // QEMU测试模式 - 在QEMU环境中执行测试
+    #[clap(
+        long = "qemu",
+        group = "environment",
+        help = "Execute in QEMU virtual machine"
+    )]
+    pub qemu: bool,
+
+    // Serial test mode - Execute tests via serial connection
+    // 串口测试模式 - 通过串口执行测试
+    #[clap(
+        long = "serial",
+        group = "environment",
+        help = "Execute via serial connection"
+    )]
+    pub serial: bool,
+
+    // Reports directory - Specify directory for test reports
+    // 报告目录 - 指定存放测试报告的目录
+    #[clap(long = "reports-dir", help = "Output directory for test reports")]
+    pub reports_dir: Option<PathBuf>,
+
+    // Aggregate report output - Specify output file path for aggregate report
+    // 聚合报告输出 - 指定聚合报告的输出文件路径
+    #[clap(
+        long = "output",
+        short = 'o',
+        help = "Output file for aggregate report"
+    )]
+    pub output: Option<PathBuf>,
+
+    // Compatibility matrix output - Specify output file path for the cross-target matrix
+    // 兼容性矩阵输出 - 指定跨目标兼容性矩阵的输出文件路径
+    #[clap(
+        long = "matrix-output",
+        help = "Output file for the compatibility matrix (Markdown; JSON is written next to it), default <reports-dir>/matrix.report.md"
+    )]
+    pub matrix_output: Option<PathBuf>,
 
     // Continue on error - Continue executing remaining tests even if some fail
     // 出错继续 - 即使测试失败也继续执行其余测试
@@ -143,6 +228,34 @@ pub struct CliArgs {
     )]
     pub maintain_session: Option<bool>,
 
+    // Number of target groups executed concurrently
+    // 并行执行的目标分组数量
+    #[clap(
+        short = 'j',
+        long = "jobs",
+        help = "Number of target groups to run concurrently [default: 1]"
+    )]
+    pub jobs: Option<usize>,
+
+    // Additional report formats written alongside the Markdown reports
+    // 额外的报告格式，与 Markdown 报告一起输出
+    #[clap(
+        long = "format",
+        value_enum,
+        value_delimiter = ',',
+        help = "Additional report formats, comma separated"
+    )]
+    pub format: Vec<ReportFormat>,
+
+    // Compare results with a recorded run ("last" for the previous run)
+    // 与历史记录中的某次运行对比结果（"last" 表示上一次运行）
+    #[clap(
+        long = "compare-with",
+        value_name = "RUN_ID|last",
+        help = "Compare results with a recorded run and show regressions in the summary"
+    )]
+    pub compare_with: Option<String>,
+
     // Keep template directory structure when outputting reports
     // 输出报告时保持模板的原始目录结构
     #[clap(
@@ -154,26 +267,134 @@ pub struct CliArgs {
     pub keep_template_directory_structure: bool,
 }
 
-impl CliArgs {
+/// Options of `lintestor report`
+/// `lintestor report` 的选项
+#[derive(Args, Debug, Clone)]
+pub struct ReportArgs {
+    // Test directory the runs were started with
+    // 运行时使用的测试目录
+    #[clap(
+        short = 'D',
+        long = "test-dir",
+        help = "Directory containing test templates"
+    )]
+    pub test_dir: Option<PathBuf>,
+
+    // Reports directory containing the run history (same default as `run`)
+    // 存放运行历史的报告目录（默认值与 `run` 相同）
+    #[clap(
+        long = "reports-dir",
+        help = "Reports directory containing history.jsonl [default: <test-dir>/reports]"
+    )]
+    pub reports_dir: Option<PathBuf>,
+
+    // Run to show; all recorded runs are listed when omitted
+    // 要查看的运行，省略时列出所有历史运行
+    #[clap(
+        long = "run",
+        value_name = "RUN_ID|last",
+        help = "Show the results of a recorded run"
+    )]
+    pub run: Option<String>,
+
+    // Run to compare with ("last" is the run before the shown one)
+    // 用于对比的运行（"last" 表示被查看运行的前一次运行）
+    #[clap(
+        long = "compare-with",
+        value_name = "RUN_ID|last",
+        help = "Compare the shown run (default: the latest) with another recorded run"
+    )]
+    pub compare_with: Option<String>,
+}
+
+/// Arguments of the `list` subcommand
+/// `list` 子命令参数
+#[derive(Args, Debug, Clone)]
+pub struct ListArgs {
+    #[clap(flatten)]
+    pub templates: TemplateArgs,
+
+    // Show each template's steps in execution order
+    // 按执行顺序显示每个模板的步骤
+    #[clap(
+        long = "steps",
+        help = "Show the steps of each template in execution order with their dependencies"
+    )]
+    pub steps: bool,
+
+    // Output format
+    // 输出格式
+    #[clap(
+        long = "format",
+        value_enum,
+        default_value = "table",
+        help = "Output format"
+    )]
+    pub format: ListFormat,
+}
+
+/// Output formats of the `list` subcommand
+/// `list` 子命令的输出格式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListFormat {
+    // Human readable tables
+    // 便于阅读的表格
+    Table,
+    // JSON for other tools
+    // 供其他工具读取的 JSON
+    Json,
+}
+
+/// Report output formats
+/// 报告输出格式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    // Markdown report (always generated)
+    // Markdown 报告（总是生成）
+    Markdown,
+    // JSON results for dashboards and other tools
+    // 供看板等工具读取的 JSON 结果
+    Json,
+    // JUnit XML for CI systems
+    // 供 CI 系统读取的 JUnit XML
+    Junit,
+    // Self-contained HTML report
+    // 内联样式的单文件 HTML 报告
+    Html,
+    // TAP stream on stdout, one line per step
+    // 输出到标准输出的 TAP 流，每个步骤一行
+    Tap,
+}
+
+impl Cli {
     /// Parse command line arguments
     /// 解析命令行参数
     pub fn parse_args() -> Self {
         Self::parse()
     }
 
-    /// Get environment type
-    /// 获取测试环境类型
-    pub fn get_environment_type(&self) -> Option<String> {
-        if self.serial {
-            Some("serial".to_string())
-        } else if self.local {
-            Some("local".to_string())
-        } else if self.remote {
-            Some("remote".to_string())
-        } else if self.qemu {
-            Some("qemu".to_string())
-        } else {
-            None
+    /// Resolve the subcommand to execute, mapping the deprecated flat flags
+    /// 解析要执行的子命令，已弃用的扁平参数会映射到对应的子命令
+    ///
+    /// Returns the command and, for the deprecated form, the replacement to suggest
+    /// 返回子命令；使用已弃用的形式时同时返回建议使用的新写法
+    pub fn resolve_command(self) -> (Command, Option<&'static str>) {
+        match self.command {
+            Some(command) => (command, None),
+            None if self.parse_only => (
+                Command::Parse(self.legacy.templates),
+                Some("--parse-only is deprecated, use `lintestor parse` instead"),
+            ),
+            None if self.test => (
+                Command::Run(self.legacy),
+                Some("--test is deprecated, use `lintestor run` instead"),
+            ),
+            None => Self::command()
+                .error(
+                    ErrorKind::MissingSubcommand,
+                    "a subcommand is required (for example `lintestor run`)",
+                )
+                .exit(),
         }
     }
 
@@ -188,7 +409,9 @@ impl CliArgs {
             "info"
         }
     }
+}
 
+impl TemplateArgs {
     /// Get filter conditions
     /// 获取筛选条件
     pub fn get_filters(&self) -> (Option<&str>, Option<&str>, Option<&str>) {
@@ -199,3 +422,109 @@ impl CliArgs {
         )
     }
 }
+
+impl RunArgs {
+    /// Get environment type
+    /// 获取测试环境类型（各环境参数互斥）
+    pub fn get_environment_type(&self) -> Option<String> {
+        if self.serial {
+            Some("serial".to_string())
+        } else if self.local {
+            Some("local".to_string())
+        } else if self.remote {
+            Some("remote".to_string())
+        } else if self.qemu {
+            Some("qemu".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Get number of parallel jobs
+    /// 获取并行任务数量（至少为1）
+    pub fn get_jobs(&self) -> usize {
+        self.jobs.unwrap_or(1).max(1)
+    }
+
+    /// Get report formats
+    /// 获取报告格式（Markdown 总是包含在内）
+    pub fn get_report_formats(&self) -> Vec<ReportFormat> {
+        let mut formats = vec![ReportFormat::Markdown];
+        for format in &self.format {
+            if !formats.contains(format) {
+                formats.push(*format);
+            }
+        }
+        formats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("lintestor").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_subcommands_and_deprecated_flags() {
+        let (command, deprecation) = parse(&["run", "-D", "tests", "--local", "-j", "2", "-v"])
+            .unwrap()
+            .resolve_command();
+        assert!(deprecation.is_none());
+        let Command::Run(args) = command else {
+            panic!("expected run command");
+        };
+        assert_eq!(args.templates.test_dir, Some(PathBuf::from("tests")));
+        assert_eq!(args.get_environment_type().as_deref(), Some("local"));
+        assert_eq!(args.get_jobs(), 2);
+        assert!(args.matrix_output.is_none());
+
+        let (command, _) = parse(&["run", "-o", "summary.md", "--matrix-output", "m.md"])
+            .unwrap()
+            .resolve_command();
+        let Command::Run(args) = command else {
+            panic!("expected run command");
+        };
+        assert_eq!(args.output, Some(PathBuf::from("summary.md")));
+        assert_eq!(args.matrix_output, Some(PathBuf::from("m.md")));
+
+        let (command, deprecation) = parse(&["--test", "--qemu", "--unit", "curl"])
+            .unwrap()
+            .resolve_command();
+        assert!(deprecation.unwrap().contains("lintestor run"));
+        let Command::Run(args) = command else {
+            panic!("expected run command");
+        };
+        assert_eq!(args.templates.unit.as_deref(), Some("curl"));
+        assert_eq!(args.get_environment_type().as_deref(), Some("qemu"));
+
+        let (command, deprecation) = parse(&["-p", "--template", "a.test.md"])
+            .unwrap()
+            .resolve_command();
+        assert!(deprecation.unwrap().contains("lintestor parse"));
+        assert!(matches!(command, Command::Parse(args) if args.template.is_some()));
+
+        // report 与 run 一样接受 -D，未指定 --reports-dir 时由测试目录决定
+        let (command, _) = parse(&["report", "-D", "tests", "--run", "last"])
+            .unwrap()
+            .resolve_command();
+        let Command::Report(args) = command else {
+            panic!("expected report command");
+        };
+        assert_eq!(args.test_dir, Some(PathBuf::from("tests")));
+        assert!(args.reports_dir.is_none());
+
+        // 互斥的参数
+        assert!(parse(&["run", "--local", "--remote"]).is_err());
+        assert!(parse(&["--test", "--parse-only"]).is_err());
+        assert!(parse(&["run", "-v", "-q"]).is_err());
+        assert!(parse(&["--local", "run"]).is_err());
+    }
+}
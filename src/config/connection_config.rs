@@ -5,12 +5,19 @@
 ///
 /// # Fields
 ///
-/// - `ip`: Astring representing the IP address.
-/// - `port`: An unsigned 16-bit integer representing the port number.
-/// - `username`: A string representing the username.
+/// - `ip`: A string representing the IP address, host name or a `Host` alias from `~/.ssh/config`.
+/// - `port`: An optional port number; falls back to `~/.ssh/config`, then 22.
+/// - `username`: An optional username; falls back to `~/.ssh/config`, then the local user.
 /// - `password`: An optional string representing the password.
 /// - `private_key_path`: An optional string representing the path to the private key file.
-/// - `jump_hosts`: An optional vector of strings representing the jump hosts to use.
+/// - `private_key_passphrase`: An optional passphrase for an encrypted private key.
+/// - `private_key_passphrase_env`: An optional environment variable to read the passphrase from.
+/// - `public_key_path`: An optional path to the matching public key file.
+/// - `auth_methods`: The order in which to try `agent`, `key` and `password`, defaults to that order.
+/// - `jump_hosts`: An optional list of jump hosts, each either a `[user@]host[:port]` string
+///   (resolved through `~/.ssh/config`) or a table with its own credentials (see [`JumpHostConfig`]).
+/// - `host_key_policy`: How to verify the server's host key: `strict`, `accept-new` (default) or `off`.
+/// - `known_hosts`: An optional path to the known_hosts file, defaults to `~/.ssh/known_hosts`.
 /// - `max_retries`: A integer representing the maximum number of connection retry attempts.
 /// - `timeout`: A integer representing the connection timeout in seconds.
 ///
@@ -19,23 +26,73 @@ use serde::Deserialize;
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConnectionConfig {
     pub ip: String,
-    pub port: u16,
-    pub username: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub username: Option<String>,
     pub password: Option<String>,
     pub private_key_path: Option<String>,
+    #[serde(default)]
+    pub private_key_passphrase: Option<String>,
+    #[serde(default)]
+    pub private_key_passphrase_env: Option<String>,
     pub public_key_path: Option<String>,
-    pub jump_hosts: Option<Vec<String>>,
+    #[serde(default)]
+    pub auth_methods: Option<Vec<String>>,
+    pub jump_hosts: Option<Vec<JumpHost>>,
+    #[serde(default)]
+    pub host_key_policy: Option<String>,
+    #[serde(default)]
+    pub known_hosts: Option<String>,
 }
 impl Default for ConnectionConfig {
     fn default() -> Self {
         Self {
             ip: "localhost".to_string(),
-            port: 22,
-            username: "root".to_string(),
+            port: Some(22),
+            username: Some("root".to_string()),
             password: None,
             private_key_path: None,
+            private_key_passphrase: None,
+            private_key_passphrase_env: None,
             public_key_path: None,
+            auth_methods: None,
             jump_hosts: None,
+            host_key_policy: None,
+            known_hosts: None,
         }
     }
 }
+
+/// A jump host entry in `jump_hosts`.
+///
+/// Either a `[user@]host[:port]` string, whose missing fields and key are taken from the
+/// user's SSH configuration, or a table with explicit connection details.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum JumpHost {
+    Spec(String),
+    Config(JumpHostConfig),
+}
+
+/// Connection details of a jump host given as a table.
+///
+/// Fields that are not set fall back to the user's SSH configuration for `ip`, like a string entry.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct JumpHostConfig {
+    pub ip: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    #[serde(default)]
+    pub private_key_passphrase: Option<String>,
+    #[serde(default)]
+    pub private_key_passphrase_env: Option<String>,
+    #[serde(default)]
+    pub public_key_path: Option<String>,
+}
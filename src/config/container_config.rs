@@ -0,0 +1,21 @@
+//! 容器连接配置
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContainerConfig {
+    /// 容器引擎可执行文件（"docker" 或 "podman"），默认 "docker"
+    #[serde(default)]
+    pub engine: Option<String>,
+    /// 要启动的镜像（与 container 二选一）
+    #[serde(default)]
+    pub image: Option<String>,
+    /// 已存在的容器名或ID（与 image 二选一），这种情况下不会删除容器
+    #[serde(default)]
+    pub container: Option<String>,
+    /// 传给 `run` 的额外参数，例如 ["--privileged", "-v", "/src:/src"]
+    #[serde(default)]
+    pub run_args: Option<Vec<String>>,
+    /// 在容器内执行命令所用的shell，默认 "sh"
+    #[serde(default)]
+    pub shell: Option<String>,
+}
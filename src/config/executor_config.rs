@@ -19,9 +19,11 @@ pub struct ExecutorConfig {
     pub maintain_session: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub continue_on_error: Option<bool>, // 其实就是 !interactive
-                                         // #[serde(default, skip_serializing_if = "Option::is_none", with = "humantime_serde")]
-                                         // pub connection_timeout: Option<Duration>,
-                                         // TODO: 目前连接超时直接用的命令超时时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_parallel_steps: Option<usize>, // 模板内并行执行的步骤数上限
+                                           // #[serde(default, skip_serializing_if = "Option::is_none", with = "humantime_serde")]
+                                           // pub connection_timeout: Option<Duration>,
+                                           // TODO: 目前连接超时直接用的命令超时时间
 }
 
 // fn default_connection_timeout() -> Duration {
@@ -36,6 +38,7 @@ impl Default for ExecutorConfig {
             retry_interval: Some(5),
             maintain_session: Some(true),
             continue_on_error: Some(false),
+            max_parallel_steps: Some(1),
             // connection_timeout: Some(default_connection_timeout()),
         }
     }
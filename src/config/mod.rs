@@ -1,5 +1,8 @@
+pub mod boardtest_config;
 pub mod cli_args;
 pub mod connection_config;
+pub mod container_config;
 pub mod executor_config;
+pub mod qemu_config;
 pub mod serial_config;
 pub mod target_config;
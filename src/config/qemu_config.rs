@@ -0,0 +1,63 @@
+//! QEMU 虚拟机启动配置
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QemuConfig {
+    /// QEMU 可执行文件，例如 "qemu-system-riscv64"
+    pub binary: String,
+    /// 机器类型（-machine），例如 "virt"
+    #[serde(default)]
+    pub machine: Option<String>,
+    /// CPU 型号（-cpu）
+    #[serde(default)]
+    pub cpu: Option<String>,
+    /// CPU 数量（-smp）
+    #[serde(default)]
+    pub smp: Option<u32>,
+    /// 内存大小（-m），例如 "2G"
+    #[serde(default)]
+    pub memory: Option<String>,
+    /// 固件（-bios）
+    #[serde(default)]
+    pub bios: Option<String>,
+    /// 内核镜像（-kernel）
+    #[serde(default)]
+    pub kernel: Option<String>,
+    /// initrd（-initrd）
+    #[serde(default)]
+    pub initrd: Option<String>,
+    /// 内核命令行（-append）
+    #[serde(default)]
+    pub append: Option<String>,
+    /// 磁盘镜像路径
+    #[serde(default)]
+    pub disk: Option<String>,
+    /// 磁盘镜像格式，默认 "qcow2"
+    #[serde(default)]
+    pub disk_format: Option<String>,
+    /// 网卡设备型号，默认 "virtio-net-pci"
+    #[serde(default)]
+    pub net_device: Option<String>,
+    /// TCP 端口转发列表，格式为 "主机端口:客户机端口"
+    /// 未设置且通过 SSH 访问时，自动转发 connection.port 到客户机的 22 端口
+    #[serde(default)]
+    pub port_forwards: Option<Vec<String>>,
+    /// 是否使用 -snapshot，使每次运行都从干净的磁盘启动，默认 false
+    #[serde(default)]
+    pub snapshot: Option<bool>,
+    /// 传给 QEMU 的额外参数
+    #[serde(default)]
+    pub extra_args: Option<Vec<String>>,
+    /// 访问方式："ssh"（默认，使用 [connection]）或 "serial"（使用 [serial] 的登录逻辑，串口由 QEMU 的 pty 提供）
+    #[serde(default)]
+    pub access: Option<String>,
+    /// 等待系统启动并可登录的超时时间（秒），默认 300
+    #[serde(default)]
+    pub boot_timeout: Option<u64>,
+    /// destroy 时在客户机内执行的关机命令；未设置时，非 snapshot 模式默认为 "poweroff"
+    #[serde(default)]
+    pub shutdown_command: Option<String>,
+    /// 等待 QEMU 在关机命令后自行退出的时间（秒），超时后强制结束，默认 30
+    #[serde(default)]
+    pub shutdown_timeout: Option<u64>,
+}
@@ -10,4 +10,7 @@ pub struct SerialConfig {
     pub user_prompt: Option<String>, // 输入用户名的pattern
     pub pass_prompt: Option<String>, // 输入密码的pattern
     pub shell_prompt: String,        // shell提示符pattern
+    /// 是否将标准错误与标准输出分开采集（通过远端临时文件），默认 true
+    #[serde(default)]
+    pub separate_stderr: Option<bool>,
 }
@@ -2,8 +2,11 @@
 
 use std::path::PathBuf;
 
+use crate::config::boardtest_config::BoardtestConfig;
 use crate::config::connection_config::ConnectionConfig;
+use crate::config::container_config::ContainerConfig;
 use crate::config::executor_config::ExecutorConfig;
+use crate::config::qemu_config::QemuConfig;
 use crate::config::serial_config::SerialConfig;
 use crate::utils;
 /// This struct is used to deserialize the configuration from a file using the `utils::read_toml_from_file` method.
@@ -13,6 +16,8 @@ use crate::utils;
 /// - `boardtest`: An instance of `BoardtestConfig` struct representing the boardtest configuration.
 /// - `skip_units`: An optional vector of strings representing the units to be skipped.
 /// - `serial`: An instance of `SerialConfig` struct representing the serial connection configuration (only required when testing_type is 'serial').
+/// - `qemu`: An instance of `QemuConfig` struct describing how to launch the VM (optional for 'qemu-based-remote'; without it the VM is assumed to be running already).
+/// - `container`: An instance of `ContainerConfig` struct representing the container configuration (only required when testing_type is 'container').
 use serde::Deserialize;
 
 #[allow(dead_code)]
@@ -39,9 +44,15 @@ fn is_not_serial(value: &String) -> bool {
     value != "serial"
 }
 
+#[allow(dead_code)]
+fn is_not_container(value: &String) -> bool {
+    // keep this function as it is, just for serde plz
+    value != "container"
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct TargetConfig {
-    pub testing_type: String, // 'locally' or 'remote' or 'qemu-based-remote' or 'boardtest' or 'serial'
+    pub testing_type: String, // 'locally' or 'remote' or 'qemu-based-remote' or 'boardtest' or 'serial' or 'container'
 
     name: String,        // target name
     description: String, // target description
@@ -50,10 +61,22 @@ pub struct TargetConfig {
     #[serde(default, skip_serializing_if = "is_not_remote")]
     pub connection: Option<ConnectionConfig>,
 
+    #[serde(rename = "boardtest")]
+    #[serde(default, skip_serializing_if = "is_not_boardtest")]
+    pub boardtest: Option<BoardtestConfig>,
+
     #[serde(rename = "serial")]
     #[serde(default, skip_serializing_if = "is_not_serial")]
     pub serial: Option<SerialConfig>,
 
+    #[serde(rename = "qemu")]
+    #[serde(default, skip_serializing_if = "is_not_qemu_based_remote")]
+    pub qemu: Option<QemuConfig>,
+
+    #[serde(rename = "container")]
+    #[serde(default, skip_serializing_if = "is_not_container")]
+    pub container: Option<ContainerConfig>,
+
     #[serde(default)]
     pub executor: ExecutorConfig, // 执行器配置(超时、重试等参数)
 
@@ -92,6 +115,22 @@ impl TargetConfig {
         &self.description
     }
 
+    /// 目标配置无法加载时使用的占位配置，名称和测试类型均为 `unknown`
+    pub fn unknown() -> Self {
+        Self {
+            testing_type: "unknown".to_string(),
+            name: "unknown".to_string(),
+            description: String::new(),
+            connection: None,
+            boardtest: None,
+            serial: None,
+            qemu: None,
+            container: None,
+            executor: ExecutorConfig::default(),
+            path: PathBuf::new(),
+        }
+    }
+
     /// 从文件中读取
     pub fn from_file(file_path: &str) -> std::result::Result<Self, Box<dyn std::error::Error>> {
         let path = PathBuf::from(file_path);
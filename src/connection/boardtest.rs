@@ -0,0 +1,318 @@
+//! Boardtest连接管理器
+//!
+//! 该模块通过 HTTP/REST 任务接口在远程板卡农场上执行命令。
+//! 每条命令作为一个任务提交，随后轮询任务状态直到结束，再读取 stdout/stderr/退出码。
+//! 接口路径和响应字段名都可以在 `[boardtest]` 配置节中调整。
+
+use anyhow::{Context, Result, bail};
+use log::{debug, warn};
+use serde_json::Value;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::boardtest_config::BoardtestConfig;
+use crate::connection::{CommandOutput, ConnectionManager};
+use crate::template::ExecutorOptions;
+
+/// Boardtest连接管理器
+pub struct BoardtestConnectionManager {
+    config: BoardtestConfig,
+    executor_options: ExecutorOptions,
+    agent: ureq::Agent,
+    token: Option<String>,
+}
+
+impl BoardtestConnectionManager {
+    /// 创建新的Boardtest连接管理器
+    pub fn new(config: BoardtestConfig, executor_options: ExecutorOptions) -> Result<Self> {
+        let token = match (&config.token, &config.token_env) {
+            (Some(token), _) => Some(token.clone()),
+            (None, Some(var)) => Some(std::env::var(var).with_context(|| {
+                format!("Boardtest token environment variable is not set: {var}")
+            })?), // Boardtest 令牌环境变量未设置
+            (None, None) => None,
+        };
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .build();
+        Ok(Self {
+            config,
+            executor_options,
+            agent,
+            token,
+        })
+    }
+
+    /// 拼接接口地址，并替换路径中的 `{id}`
+    fn url(&self, path: &str, job_id: Option<&str>) -> String {
+        let path = match job_id {
+            Some(id) => path.replace("{id}", id),
+            None => path.to_string(),
+        };
+        format!(
+            "{}/{}",
+            self.config.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    /// 发送请求并解析 JSON 响应
+    fn request(&self, method: &str, url: &str, body: Option<Value>) -> Result<Value> {
+        debug!("Boardtest request: {method} {url}"); // Boardtest 请求
+        let mut request = self.agent.request(method, url);
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {token}"));
+        }
+        let response = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                let text = response.into_string().unwrap_or_default();
+                bail!("Boardtest API returned HTTP {code} for {method} {url}: {text}"); // Boardtest 接口返回错误状态码
+            }
+            Err(e) => bail!("Boardtest API request {method} {url} failed: {e}"), // Boardtest 接口请求失败
+        };
+        let text = response
+            .into_string()
+            .with_context(|| format!("Unable to read Boardtest response from {url}"))?; // 无法读取 Boardtest 响应
+        if text.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&text)
+            .with_context(|| format!("Boardtest response from {url} is not valid JSON: {text}")) // Boardtest 响应不是合法 JSON
+    }
+
+    /// 从 JSON 对象中读取字段并转为字符串（兼容数字类型的ID）
+    fn field_as_string(value: &Value, field: &str) -> Option<String> {
+        match value.get(field)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    /// 将任务结果 JSON 转换为命令输出
+    fn parse_result(&self, value: &Value) -> Result<CommandOutput> {
+        let fields = &self.config.fields;
+        let exit_code = value
+            .get(&fields.exit_code)
+            .and_then(Value::as_i64)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Boardtest result is missing integer field '{}'",
+                    fields.exit_code
+                )
+            })?; // Boardtest 结果缺少退出码字段
+        Ok(CommandOutput {
+            stdout: Self::field_as_string(value, &fields.stdout).unwrap_or_default(),
+            stderr: Self::field_as_string(value, &fields.stderr).unwrap_or_default(),
+            exit_code: exit_code as i32,
+            reconnects: Vec::new(),
+        })
+    }
+
+    /// 超时后尽力取消任务
+    fn cancel(&self, job_id: &str) {
+        if let Some(cancel_path) = &self.config.cancel_path {
+            let url = self.url(cancel_path, Some(job_id));
+            if let Err(e) = self.request("DELETE", &url, None) {
+                warn!("Failed to cancel Boardtest job {job_id}: {e}"); // 取消 Boardtest 任务失败
+            }
+        }
+    }
+}
+
+impl ConnectionManager for BoardtestConnectionManager {
+    /// 检查任务接口是否可用
+    fn setup(&mut self) -> Result<()> {
+        if let Some(health_path) = &self.config.health_path {
+            let url = self.url(health_path, None);
+            self.request("GET", &url, None)
+                .with_context(|| "Boardtest health check failed")?; // Boardtest 健康检查失败
+        }
+        Ok(())
+    }
+
+    /// 提交命令并等待任务结束
+    fn execute_command(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<CommandOutput> {
+        let timeout = timeout.unwrap_or(Duration::from_secs(self.executor_options.command_timeout));
+        let fields = self.config.fields.clone();
+
+        let submit_url = self.url(self.config.submit_path.as_deref().unwrap_or("/jobs"), None);
+        let body = serde_json::json!({
+            "command": command,
+            "board": self.config.board,
+            "timeout": timeout.as_secs(),
+        });
+        let submitted = self.request("POST", &submit_url, Some(body))?;
+        let job_id = Self::field_as_string(&submitted, &fields.id).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Boardtest submit response is missing field '{}': {submitted}",
+                fields.id
+            )
+        })?; // Boardtest 提交响应缺少任务ID
+        debug!("Boardtest job {job_id} submitted: {command}"); // Boardtest 任务已提交
+
+        let done_statuses = self.config.done_statuses.clone().unwrap_or_else(|| {
+            ["finished", "completed", "done", "success"]
+                .map(String::from)
+                .to_vec()
+        });
+        let failed_statuses = self.config.failed_statuses.clone().unwrap_or_else(|| {
+            ["error", "failed", "cancelled", "canceled"]
+                .map(String::from)
+                .to_vec()
+        });
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms.unwrap_or(1000));
+        let status_url = self.url(
+            self.config.status_path.as_deref().unwrap_or("/jobs/{id}"),
+            Some(&job_id),
+        );
+
+        let start = Instant::now();
+        let final_status = loop {
+            let status_value = self.request("GET", &status_url, None)?;
+            let status = Self::field_as_string(&status_value, &fields.status).unwrap_or_default();
+            debug!("Boardtest job {job_id} status: {status}"); // Boardtest 任务状态
+            if done_statuses.contains(&status) {
+                break status_value;
+            }
+            if failed_statuses.contains(&status) {
+                bail!("Boardtest job {job_id} ended with status '{status}': {status_value}"); // Boardtest 任务异常结束
+            }
+            if start.elapsed() > timeout {
+                self.cancel(&job_id);
+                bail!(
+                    "Boardtest job {job_id} timed out after {} s (last status '{status}')",
+                    timeout.as_secs()
+                ); // Boardtest 任务超时
+            }
+            thread::sleep(poll_interval);
+        };
+
+        let result_value = match &self.config.result_path {
+            Some(result_path) => {
+                let url = self.url(result_path, Some(&job_id));
+                self.request("GET", &url, None)?
+            }
+            None => final_status,
+        };
+        let output = self.parse_result(&result_value)?;
+        debug!(
+            "Boardtest job {job_id} completed: exit_code={}",
+            output.exit_code
+        ); // Boardtest 任务完成
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// 启动一个本地的任务接口替身：提交后第一次查询返回 running，之后返回 finished
+    fn start_stand_in_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let log_clone = Arc::clone(&log);
+        thread::spawn(move || {
+            let mut polls = 0;
+            let mut command = String::new();
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let auth = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Authorization"))
+                    .map(|h| h.value.to_string())
+                    .unwrap_or_default();
+                log_clone.lock().unwrap().push(format!(
+                    "{} {} {auth}",
+                    request.method(),
+                    request.url()
+                ));
+                let response = match (request.method().as_str(), request.url()) {
+                    ("POST", "/api/jobs") => {
+                        let value: Value = serde_json::from_str(&body).unwrap();
+                        command = value["command"].as_str().unwrap().to_string();
+                        r#"{"id": 17}"#.to_string()
+                    }
+                    ("GET", "/api/jobs/17") => {
+                        polls += 1;
+                        if polls < 2 {
+                            r#"{"status": "running"}"#.to_string()
+                        } else {
+                            serde_json::json!({
+                                "status": "finished",
+                                "stdout": format!("ran: {command}\n"),
+                                "stderr": "warn\n",
+                                "exit_code": 4,
+                            })
+                            .to_string()
+                        }
+                    }
+                    _ => "{}".to_string(),
+                };
+                let _ = request.respond(tiny_http::Response::from_string(response));
+            }
+        });
+        (addr, log)
+    }
+
+    fn config_for(base_url: String) -> BoardtestConfig {
+        toml::from_str(&format!(
+            r#"
+base_url = "{base_url}/api"
+board = "visionfive2"
+token = "secret"
+poll_interval_ms = 10
+"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_execute_command_against_stand_in_server() {
+        let (addr, log) = start_stand_in_server();
+        let mut manager =
+            BoardtestConnectionManager::new(config_for(addr), ExecutorOptions::default()).unwrap();
+        manager.setup().unwrap();
+        let output = manager
+            .execute_command("uname -a", Some(Duration::from_secs(10)))
+            .unwrap();
+        assert_eq!(output.stdout, "ran: uname -a\n");
+        assert_eq!(output.stderr, "warn\n");
+        assert_eq!(output.exit_code, 4);
+
+        let log = log.lock().unwrap();
+        assert_eq!(log[0], "POST /api/jobs Bearer secret");
+        assert!(
+            log.iter()
+                .filter(|l| l.starts_with("GET /api/jobs/17"))
+                .count()
+                >= 2
+        );
+    }
+
+    #[test]
+    fn test_parse_result_requires_exit_code() {
+        let manager = BoardtestConnectionManager::new(
+            config_for("http://127.0.0.1:1".to_string()),
+            ExecutorOptions::default(),
+        )
+        .unwrap();
+        let value = serde_json::json!({"status": "finished", "stdout": "x"});
+        assert!(manager.parse_result(&value).is_err());
+    }
+}
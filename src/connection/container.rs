@@ -0,0 +1,230 @@
+//! 容器连接管理器
+//!
+//! 该模块实现了通过 docker/podman 在容器内执行命令的连接管理器。
+//! 指定 `image` 时会在 `setup()` 中启动一个新容器，并在 `destroy()` 中删除它；
+//! 指定 `container` 时直接复用已存在的容器，不会删除它。
+
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::config::container_config::ContainerConfig;
+use crate::connection::local::run_with_timeout;
+use crate::connection::{CommandOutput, ConnectionManager};
+use crate::template::ExecutorOptions;
+
+/// 容器连接管理器
+pub struct ContainerConnectionManager {
+    /// 容器引擎可执行文件
+    engine: String,
+    /// 容器内使用的shell
+    shell: String,
+    config: ContainerConfig,
+    executor_options: ExecutorOptions,
+    /// 当前使用的容器ID或名称
+    container_id: Option<String>,
+    /// 容器是否由本管理器启动（决定 destroy 时是否删除）
+    owned: bool,
+}
+
+impl ContainerConnectionManager {
+    /// 创建新的容器连接管理器
+    pub fn new(config: ContainerConfig, executor_options: ExecutorOptions) -> Result<Self> {
+        match (&config.image, &config.container) {
+            (None, None) => bail!("Container configuration requires either 'image' or 'container'"), // 容器配置需要 image 或 container
+            (Some(_), Some(_)) => {
+                bail!("Container configuration must not specify both 'image' and 'container'") // 不能同时指定 image 和 container
+            }
+            _ => {}
+        }
+        Ok(Self {
+            engine: config
+                .engine
+                .clone()
+                .unwrap_or_else(|| "docker".to_string()),
+            shell: config.shell.clone().unwrap_or_else(|| "sh".to_string()),
+            config,
+            executor_options,
+            container_id: None,
+            owned: false,
+        })
+    }
+
+    /// 调用容器引擎执行一条管理命令，失败时返回错误
+    fn run_engine(&self, args: &[String]) -> Result<String> {
+        let description = format!("{} {}", self.engine, args.join(" "));
+        debug!("Running container engine command: {description}"); // 执行容器引擎命令
+        let mut process = Command::new(&self.engine);
+        process.args(args);
+        let output = run_with_timeout(
+            process,
+            &description,
+            Some(Duration::from_secs(self.executor_options.command_timeout)),
+        )?;
+        if output.exit_code != 0 {
+            bail!(
+                "Container engine command failed ({}): {}",
+                output.exit_code,
+                output.stderr.trim()
+            ); // 容器引擎命令失败
+        }
+        Ok(output.stdout.trim().to_string())
+    }
+}
+
+impl ConnectionManager for ContainerConnectionManager {
+    /// 启动容器（或确认已存在的容器正在运行）
+    fn setup(&mut self) -> Result<()> {
+        if self.container_id.is_some() {
+            debug!("Container setup: reusing existing container session");
+            return Ok(());
+        }
+
+        if let Some(name) = &self.config.container {
+            let running = self
+                .run_engine(&[
+                    "inspect".to_string(),
+                    "-f".to_string(),
+                    "{{.State.Running}}".to_string(),
+                    name.clone(),
+                ])
+                .with_context(|| format!("Unable to inspect container: {name}"))?; // 无法检查容器
+            if running != "true" {
+                bail!("Container {name} is not running"); // 容器未运行
+            }
+            self.container_id = Some(name.clone());
+            self.owned = false;
+            return Ok(());
+        }
+
+        let image = self.config.image.clone().unwrap_or_default();
+        let mut args = vec![
+            "run".to_string(),
+            "-d".to_string(),
+            "--entrypoint".to_string(),
+            self.shell.clone(),
+        ];
+        args.extend(self.config.run_args.clone().unwrap_or_default());
+        args.push(image.clone());
+        // 用一个不会退出的循环让容器保持运行，不依赖镜像里是否有 `sleep infinity`
+        args.push("-c".to_string());
+        args.push("while :; do sleep 3600; done".to_string());
+
+        let container_id = self
+            .run_engine(&args)
+            .with_context(|| format!("Unable to start container from image: {image}"))?; // 无法从镜像启动容器
+        if container_id.is_empty() {
+            bail!("Container engine did not return a container ID for image {image}"); // 容器引擎没有返回容器ID
+        }
+        info!("Started container {container_id} from image {image}"); // 已从镜像启动容器
+        self.container_id = Some(container_id);
+        self.owned = true;
+        Ok(())
+    }
+
+    /// 在容器内执行命令
+    fn execute_command(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<CommandOutput> {
+        let container_id = self
+            .container_id
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Container not started, please setup first"))?; // 容器未启动，请先setup
+        debug!("Executing container command in {container_id}: {command}"); // 在容器中执行命令
+
+        let mut process = Command::new(&self.engine);
+        process
+            .arg("exec")
+            .arg(container_id)
+            .arg(&self.shell)
+            .arg("-c")
+            .arg(command);
+        run_with_timeout(process, command, timeout)
+    }
+
+    /// 删除由本管理器启动的容器
+    fn destroy(&mut self) -> Result<()> {
+        let owned = std::mem::take(&mut self.owned);
+        if let Some(container_id) = self.container_id.take()
+            && owned
+        {
+            debug!("Container destroy: removing container {container_id}"); // 删除容器
+            self.run_engine(&["rm".to_string(), "-f".to_string(), container_id.clone()])
+                .with_context(|| format!("Unable to remove container: {container_id}"))?; // 无法删除容器
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ContainerConnectionManager {
+    fn drop(&mut self) {
+        if let Err(e) = self.destroy() {
+            warn!("Failed to clean up container: {e}"); // 清理容器失败
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// 写一个假的容器引擎脚本：记录调用参数，`exec` 时在本地执行命令
+    fn fake_engine(dir: &std::path::Path) -> (String, std::path::PathBuf) {
+        let log = dir.join("engine.log");
+        let script = dir.join("fake-engine");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> {log}\ncase \"$1\" in\n  run) echo cid123 ;;\n  exec) shift 2; exec \"$@\" ;;\nesac\n",
+                log = log.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        (script.to_string_lossy().into_owned(), log)
+    }
+
+    #[test]
+    fn test_container_lifecycle_with_image() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (engine, log) = fake_engine(temp_dir.path());
+        let config = ContainerConfig {
+            engine: Some(engine),
+            image: Some("debian:stable".to_string()),
+            container: None,
+            run_args: None,
+            shell: None,
+        };
+        let mut manager =
+            ContainerConnectionManager::new(config, ExecutorOptions::default()).unwrap();
+        manager.setup().unwrap();
+        let output = manager
+            .execute_command("echo hi; exit 3", Some(Duration::from_secs(10)))
+            .unwrap();
+        assert_eq!(output.stdout.trim(), "hi");
+        assert_eq!(output.exit_code, 3);
+        manager.destroy().unwrap();
+
+        let log = fs::read_to_string(log).unwrap();
+        assert!(log.contains("run -d --entrypoint sh debian:stable"));
+        assert!(log.contains("exec cid123 sh -c echo hi; exit 3"));
+        assert!(log.contains("rm -f cid123"));
+    }
+
+    #[test]
+    fn test_container_config_requires_image_or_container() {
+        let config = ContainerConfig {
+            engine: None,
+            image: None,
+            container: None,
+            run_args: None,
+            shell: None,
+        };
+        assert!(ContainerConnectionManager::new(config, ExecutorOptions::default()).is_err());
+    }
+}
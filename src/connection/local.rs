@@ -30,58 +30,71 @@ impl ConnectionManager for LocalConnectionManager {
     ) -> Result<CommandOutput> {
         debug!("Executing local command: {command}"); // 执行本地命令: {command}
 
-        // 创建命令进程
-        let mut child = Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .with_context(|| format!("Unable to start command process: {command}"))?; // 无法启动命令进程: {command}
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        run_with_timeout(process, command, timeout)
+    }
+}
+
+/// 启动进程并等待其结束（带超时），收集标准输出、标准错误和退出码
+///
+/// 超时后进程会被杀死，退出码记为 -1。
+/// `description` 仅用于日志和错误信息。
+pub(crate) fn run_with_timeout(
+    mut process: Command,
+    description: &str,
+    timeout: Option<Duration>,
+) -> Result<CommandOutput> {
+    // 创建命令进程
+    let mut child = process
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Unable to start command process: {description}"))?; // 无法启动命令进程: {description}
 
-        let start_time = Instant::now();
-        let timeout_duration = timeout.unwrap_or(Duration::from_secs(60)); // 默认60秒超时
+    let start_time = Instant::now();
+    let timeout_duration = timeout.unwrap_or(Duration::from_secs(60)); // 默认60秒超时
 
-        // 检查是否超时
-        let mut timed_out = false;
-        while child.try_wait()?.is_none() {
-            if start_time.elapsed() > timeout_duration {
-                timed_out = true;
-                warn!("Command execution timeout: {command}"); // 命令执行超时: {command}
-                child.kill()?;
-                break;
-            }
-            thread::sleep(Duration::from_millis(100));
+    // 检查是否超时
+    let mut timed_out = false;
+    while child.try_wait()?.is_none() {
+        if start_time.elapsed() > timeout_duration {
+            timed_out = true;
+            warn!("Command execution timeout: {description}"); // 命令执行超时: {description}
+            child.kill()?;
+            break;
         }
+        thread::sleep(Duration::from_millis(100));
+    }
 
-        // 获取结果
-        let mut stdout = String::new();
-        let mut stderr = String::new();
+    // 获取结果
+    let mut stdout = String::new();
+    let mut stderr = String::new();
 
-        // 读取标准输出
-        if let Some(mut stdout_pipe) = child.stdout.take() {
-            stdout_pipe.read_to_string(&mut stdout)?;
-        }
+    // 读取标准输出
+    if let Some(mut stdout_pipe) = child.stdout.take() {
+        stdout_pipe.read_to_string(&mut stdout)?;
+    }
 
-        // 读取标准错误
-        if let Some(mut stderr_pipe) = child.stderr.take() {
-            stderr_pipe.read_to_string(&mut stderr)?;
-        }
+    // 读取标准错误
+    if let Some(mut stderr_pipe) = child.stderr.take() {
+        stderr_pipe.read_to_string(&mut stderr)?;
+    }
 
-        // 获取退出码
-        let exit_code = if timed_out {
-            -1 // 超时返回-1
-        } else {
-            child.wait()?.code().unwrap_or(-1)
-        };
+    // 获取退出码
+    let exit_code = if timed_out {
+        -1 // 超时返回-1
+    } else {
+        child.wait()?.code().unwrap_or(-1)
+    };
 
-        debug!("Command execution completed: exit_code={exit_code}"); // 命令执行完成: exit_code={exit_code}
+    debug!("Command execution completed: exit_code={exit_code}"); // 命令执行完成: exit_code={exit_code}
 
-        Ok(CommandOutput {
-            stdout,
-            stderr,
-            exit_code,
-        })
-    }
+    Ok(CommandOutput {
+        stdout,
+        stderr,
+        exit_code,
+        reconnects: Vec::new(),
+    })
 }
@@ -1,6 +1,6 @@
 //! 连接管理模块
 //!
-//! 该模块提供了不同类型连接（SSH、本地、QEMU等）的统一接口
+//! 该模块提供了不同类型连接（SSH、本地、QEMU、串口、容器等）的统一接口
 
 use crate::config::target_config::TargetConfig;
 use crate::template::ExecutorOptions;
@@ -16,10 +16,12 @@ pub struct CommandOutput {
     pub stderr: String,
     /// 退出码
     pub exit_code: i32,
+    /// 执行期间重建连接的记录（目前只有SSH会自动重连）
+    pub reconnects: Vec<String>,
 }
 
 /// 连接管理器特质
-pub trait ConnectionManager {
+pub trait ConnectionManager: Send {
     /// 执行命令并返回结果
     fn execute_command(
         &mut self,
@@ -51,6 +53,22 @@ pub trait ConnectionManager {
 pub struct ConnectionFactory;
 
 impl ConnectionFactory {
+    /// 判断目标是否可以同时打开多个互不干扰的会话（用于模板内步骤的并行执行）
+    pub fn supports_parallel_sessions(config: &TargetConfig) -> bool {
+        match config.testing_type.as_str() {
+            "remote" | "ssh" | "local" | "locally" | "boardtest" => true,
+            // 由本程序管理的虚拟机每个连接都会启动一台新虚拟机，只能使用一个会话
+            "qemu" | "qemu-based-remote" => config.qemu.is_none(),
+            // 从镜像启动时每个连接都是一个新容器，只有复用已有容器时才能共享状态
+            "container" | "docker" | "podman" => config
+                .container
+                .as_ref()
+                .is_some_and(|c| c.container.is_some()),
+            // 串口同一时间只能有一个会话
+            _ => false,
+        }
+    }
+
     /// 根据目标配置创建适当类型的连接管理器
     pub fn create_manager(
         config: &TargetConfig,
@@ -74,7 +92,16 @@ impl ConnectionFactory {
                 Ok(Box::new(LocalConnectionManager::new()))
             }
             "qemu" | "qemu-based-remote" => {
-                // 创建QEMU连接（实际上也是SSH）
+                // 配置了 [qemu] 时由本程序管理虚拟机的生命周期
+                if let Some(qemu) = &config.qemu {
+                    return Ok(Box::new(QemuConnectionManager::new(
+                        qemu.clone(),
+                        config.connection.clone(),
+                        config.serial.clone(),
+                        executor_options.clone(),
+                    )?));
+                }
+                // 否则假定虚拟机已在运行，直接通过SSH连接
                 let connection = match &config.connection {
                     Some(conn) => conn,
                     None => bail!("No connection configuration provided for QEMU"),
@@ -95,9 +122,29 @@ impl ConnectionFactory {
                     executor_options.clone(),
                 )?))
             }
+            "container" | "docker" | "podman" => {
+                let mut container = match &config.container {
+                    Some(c) => c.clone(),
+                    None => bail!("No container configuration provided for container mode"),
+                };
+                // testing_type 为 docker/podman 时，未显式指定引擎则使用对应的引擎
+                if container.engine.is_none() && config.testing_type != "container" {
+                    container.engine = Some(config.testing_type.clone());
+                }
+                Ok(Box::new(ContainerConnectionManager::new(
+                    container,
+                    executor_options.clone(),
+                )?))
+            }
             "boardtest" => {
-                // 这里应该实现BoardTest连接类型
-                bail!("Boardtest connection type not yet implemented for template system")
+                let boardtest = match &config.boardtest {
+                    Some(b) => b.clone(),
+                    None => bail!("No boardtest configuration provided for boardtest mode"),
+                };
+                Ok(Box::new(BoardtestConnectionManager::new(
+                    boardtest,
+                    executor_options.clone(),
+                )?))
             }
             _ => {
                 bail!("Unknown testing type: {}", config.testing_type)
@@ -112,8 +159,21 @@ pub use local::LocalConnectionManager;
 
 // 实现SSH连接管理器
 mod ssh;
+mod ssh_config;
 pub use ssh::SSHConnectionManager;
 
 // 实现串口连接管理器
 mod serial;
 pub use serial::SerialConnectionManager;
+
+// 实现QEMU连接管理器
+mod qemu;
+pub use qemu::QemuConnectionManager;
+
+// 实现容器连接管理器
+mod container;
+pub use container::ContainerConnectionManager;
+
+// 实现Boardtest连接管理器
+mod boardtest;
+pub use boardtest::BoardtestConnectionManager;
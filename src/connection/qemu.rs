@@ -0,0 +1,420 @@
+//! QEMU连接管理器
+//!
+//! 该模块负责QEMU虚拟机的生命周期：在 `setup()` 中启动虚拟机并等待其可登录，
+//! 之后所有命令都交给内部的 SSH（或串口）连接管理器执行，
+//! 在 `destroy()` 中关闭虚拟机。
+
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::connection_config::ConnectionConfig;
+use crate::config::qemu_config::QemuConfig;
+use crate::config::serial_config::SerialConfig;
+use crate::connection::{
+    CommandOutput, ConnectionManager, SSHConnectionManager, SerialConnectionManager,
+};
+use crate::template::ExecutorOptions;
+
+/// 虚拟机的访问方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QemuAccess {
+    Ssh,
+    Serial,
+}
+
+/// QEMU连接管理器
+pub struct QemuConnectionManager {
+    qemu_config: QemuConfig,
+    access: QemuAccess,
+    connection: Option<ConnectionConfig>,
+    serial: Option<SerialConfig>,
+    executor_options: ExecutorOptions,
+    /// QEMU 进程
+    child: Option<Child>,
+    /// QEMU 输出的逐行接收端（用于获取 pty 路径和启动失败原因）
+    qemu_output: Option<Receiver<String>>,
+    /// 虚拟机启动后实际执行命令的连接
+    inner: Option<Box<dyn ConnectionManager>>,
+}
+
+impl QemuConnectionManager {
+    /// 创建新的QEMU连接管理器（此时不会启动虚拟机）
+    pub fn new(
+        qemu_config: QemuConfig,
+        connection: Option<ConnectionConfig>,
+        serial: Option<SerialConfig>,
+        executor_options: ExecutorOptions,
+    ) -> Result<Self> {
+        let access = match qemu_config.access.as_deref().unwrap_or("ssh") {
+            "ssh" => QemuAccess::Ssh,
+            "serial" => QemuAccess::Serial,
+            other => bail!("Unknown QEMU access method: {other}, expected 'ssh' or 'serial'"), // 未知的QEMU访问方式
+        };
+        if access == QemuAccess::Ssh && connection.is_none() {
+            bail!("No connection configuration provided for QEMU SSH access"); // QEMU SSH 访问缺少 connection 配置
+        }
+        if access == QemuAccess::Serial && serial.is_none() {
+            bail!("No serial configuration provided for QEMU serial access"); // QEMU 串口访问缺少 serial 配置
+        }
+        Ok(Self {
+            qemu_config,
+            access,
+            connection,
+            serial,
+            executor_options,
+            child: None,
+            qemu_output: None,
+            inner: None,
+        })
+    }
+
+    /// 根据配置构造QEMU命令行参数
+    fn build_args(
+        config: &QemuConfig,
+        access: QemuAccess,
+        connection: Option<&ConnectionConfig>,
+    ) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        let mut push = |flag: &str, value: &Option<String>| {
+            if let Some(v) = value {
+                args.push(flag.to_string());
+                args.push(v.clone());
+            }
+        };
+        push("-machine", &config.machine);
+        push("-cpu", &config.cpu);
+        push("-smp", &config.smp.map(|n| n.to_string()));
+        push("-m", &config.memory);
+        push("-bios", &config.bios);
+        push("-kernel", &config.kernel);
+        push("-initrd", &config.initrd);
+        push("-append", &config.append);
+
+        if let Some(disk) = &config.disk {
+            let format = config.disk_format.as_deref().unwrap_or("qcow2");
+            args.push("-drive".to_string());
+            args.push(format!("file={disk},format={format},if=virtio"));
+        }
+
+        let forwards = match (&config.port_forwards, access, connection) {
+            (Some(forwards), _, _) => forwards.clone(),
+            (None, QemuAccess::Ssh, Some(conn)) => vec![format!("{}:22", conn.port.unwrap_or(22))],
+            _ => Vec::new(),
+        };
+        let mut netdev = "user,id=net0".to_string();
+        for forward in &forwards {
+            match forward.split_once(':') {
+                Some((host_port, guest_port)) => {
+                    netdev.push_str(&format!(",hostfwd=tcp::{host_port}-:{guest_port}"))
+                }
+                None => warn!("Ignoring malformed QEMU port forward: {forward}"), // 忽略格式错误的端口转发
+            }
+        }
+        args.push("-netdev".to_string());
+        args.push(netdev);
+        args.push("-device".to_string());
+        args.push(format!(
+            "{},netdev=net0",
+            config.net_device.as_deref().unwrap_or("virtio-net-pci")
+        ));
+
+        if config.snapshot.unwrap_or(false) {
+            args.push("-snapshot".to_string());
+        }
+
+        args.push("-display".to_string());
+        args.push("none".to_string());
+        args.push("-monitor".to_string());
+        args.push("none".to_string());
+        args.push("-serial".to_string());
+        args.push(
+            match access {
+                QemuAccess::Serial => "pty",
+                QemuAccess::Ssh => "null",
+            }
+            .to_string(),
+        );
+
+        args.extend(config.extra_args.clone().unwrap_or_default());
+        args
+    }
+
+    /// 从QEMU输出中解析 `-serial pty` 分配的伪终端路径
+    fn parse_pty_path(line: &str) -> Option<String> {
+        // 例如: "char device redirected to /dev/pts/3 (label serial0)"
+        let rest = line.split("redirected to ").nth(1)?;
+        let path = rest.split_whitespace().next()?;
+        path.starts_with("/dev/").then(|| path.to_string())
+    }
+
+    /// 启动QEMU进程
+    fn launch(&mut self) -> Result<()> {
+        let args = Self::build_args(&self.qemu_config, self.access, self.connection.as_ref());
+        info!(
+            "Launching QEMU: {} {}",
+            self.qemu_config.binary,
+            args.join(" ")
+        ); // 启动QEMU
+        let mut child = Command::new(&self.qemu_config.binary)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Unable to start QEMU: {}", self.qemu_config.binary))?; // 无法启动QEMU
+
+        // QEMU 在不同版本中会把 pty 信息打印到 stdout 或 stderr，两个都收集
+        let (tx, rx) = mpsc::channel();
+        let pipes: Vec<Box<dyn Read + Send>> = vec![
+            Box::new(child.stdout.take().unwrap()),
+            Box::new(child.stderr.take().unwrap()),
+        ];
+        for pipe in pipes {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                    debug!("QEMU: {line}");
+                    if tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        self.child = Some(child);
+        self.qemu_output = Some(rx);
+        Ok(())
+    }
+
+    /// 检查QEMU进程是否已经退出
+    fn ensure_running(&mut self) -> Result<()> {
+        if let Some(child) = self.child.as_mut()
+            && let Some(status) = child.try_wait()?
+        {
+            let output: Vec<String> = self
+                .qemu_output
+                .as_ref()
+                .map(|rx| rx.try_iter().collect())
+                .unwrap_or_default();
+            self.child = None;
+            bail!("QEMU exited unexpectedly ({status}): {}", output.join("\n")); // QEMU 意外退出
+        }
+        Ok(())
+    }
+
+    /// 等待虚拟机可以通过配置的访问方式登录
+    fn wait_for_guest(&mut self) -> Result<Box<dyn ConnectionManager>> {
+        let boot_timeout = Duration::from_secs(self.qemu_config.boot_timeout.unwrap_or(300));
+        let start = Instant::now();
+
+        // 串口模式先拿到 pty 路径
+        let mut serial_config = self.serial.clone();
+        if self.access == QemuAccess::Serial {
+            let mut pty_path = None;
+            while pty_path.is_none() {
+                self.ensure_running()?;
+                if start.elapsed() > boot_timeout {
+                    bail!("Timed out waiting for QEMU to allocate a serial pty"); // 等待QEMU分配串口pty超时
+                }
+                if let Some(rx) = self.qemu_output.as_ref()
+                    && let Ok(line) = rx.recv_timeout(Duration::from_millis(200))
+                {
+                    pty_path = Self::parse_pty_path(&line);
+                }
+            }
+            let pty_path = pty_path.unwrap_or_default();
+            debug!("QEMU serial console is at {pty_path}"); // QEMU串口位于
+            if let Some(serial) = serial_config.as_mut() {
+                serial.port = pty_path;
+            }
+        }
+
+        // 单次尝试的重试次数由这里控制，避免每次尝试内部再重试很久
+        let mut attempt_options = self.executor_options.clone();
+        attempt_options.retry_count = 0;
+
+        loop {
+            self.ensure_running()?;
+            let attempt: Result<Box<dyn ConnectionManager>> = match self.access {
+                QemuAccess::Ssh => {
+                    let connection = self.connection.as_ref().unwrap();
+                    SSHConnectionManager::new(connection, &attempt_options)
+                        .map(|m| Box::new(m) as Box<dyn ConnectionManager>)
+                }
+                QemuAccess::Serial => {
+                    let serial = serial_config.clone().unwrap();
+                    SerialConnectionManager::new(serial, attempt_options.clone()).and_then(
+                        |mut m| {
+                            m.setup()?;
+                            Ok(Box::new(m) as Box<dyn ConnectionManager>)
+                        },
+                    )
+                }
+            };
+            match attempt {
+                Ok(manager) => {
+                    info!("QEMU guest is ready after {} s", start.elapsed().as_secs()); // QEMU 客户机已就绪
+                    return Ok(manager);
+                }
+                Err(e) => {
+                    debug!("QEMU guest not ready yet: {e}"); // QEMU 客户机尚未就绪
+                    if start.elapsed() > boot_timeout {
+                        bail!(
+                            "Timed out after {} s waiting for QEMU guest to become reachable: {e}",
+                            boot_timeout.as_secs()
+                        ); // 等待QEMU客户机可访问超时
+                    }
+                    thread::sleep(Duration::from_secs(2));
+                }
+            }
+        }
+    }
+}
+
+impl ConnectionManager for QemuConnectionManager {
+    /// 启动虚拟机并等待登录
+    fn setup(&mut self) -> Result<()> {
+        if let Some(inner) = self.inner.as_mut() {
+            debug!("QEMU setup: reusing running VM");
+            return inner.setup();
+        }
+        self.launch()?;
+        match self.wait_for_guest() {
+            Ok(inner) => {
+                self.inner = Some(inner);
+                Ok(())
+            }
+            Err(e) => {
+                let _ = self.destroy();
+                Err(e)
+            }
+        }
+    }
+
+    /// 在虚拟机中执行命令
+    fn execute_command(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<CommandOutput> {
+        self.ensure_running()?;
+        let inner = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("QEMU VM not started, please setup first"))?; // QEMU 虚拟机未启动，请先setup
+        inner.execute_command(command, timeout)
+    }
+
+    /// 关闭虚拟机
+    fn destroy(&mut self) -> Result<()> {
+        let shutdown_command = self.qemu_config.shutdown_command.clone().or_else(|| {
+            (!self.qemu_config.snapshot.unwrap_or(false)).then(|| "poweroff".to_string())
+        });
+
+        if let Some(mut inner) = self.inner.take() {
+            if let Some(cmd) = &shutdown_command {
+                debug!("QEMU destroy: sending shutdown command '{cmd}'"); // 发送关机命令
+                // 关机时连接可能在命令返回前就断开，这里忽略错误
+                let _ = inner.execute_command(cmd, Some(Duration::from_secs(10)));
+            }
+            let _ = inner.destroy();
+        }
+
+        if let Some(mut child) = self.child.take() {
+            if shutdown_command.is_some() {
+                let wait = Duration::from_secs(self.qemu_config.shutdown_timeout.unwrap_or(30));
+                let start = Instant::now();
+                while start.elapsed() < wait {
+                    if child.try_wait()?.is_some() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(200));
+                }
+            }
+            if child.try_wait()?.is_none() {
+                debug!("QEMU destroy: killing QEMU process"); // 结束QEMU进程
+                child
+                    .kill()
+                    .with_context(|| "Failed to kill QEMU process")?; // 结束QEMU进程失败
+            }
+            let _ = child.wait();
+            info!("QEMU VM stopped"); // QEMU 虚拟机已关闭
+        }
+        self.qemu_output = None;
+        Ok(())
+    }
+}
+
+impl Drop for QemuConnectionManager {
+    fn drop(&mut self) {
+        if let Err(e) = self.destroy() {
+            warn!("Failed to shut down QEMU VM: {e}"); // 关闭QEMU虚拟机失败
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimal_config() -> QemuConfig {
+        toml::from_str(
+            r#"
+binary = "qemu-system-riscv64"
+machine = "virt"
+memory = "2G"
+smp = 4
+disk = "/images/debian.qcow2"
+snapshot = true
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_build_args_ssh_forwards_connection_port() {
+        let connection = ConnectionConfig {
+            port: Some(2222),
+            ..Default::default()
+        };
+        let args = QemuConnectionManager::build_args(
+            &minimal_config(),
+            QemuAccess::Ssh,
+            Some(&connection),
+        );
+        let joined = args.join(" ");
+        assert!(joined.contains("-machine virt"));
+        assert!(joined.contains("-smp 4"));
+        assert!(joined.contains("-m 2G"));
+        assert!(joined.contains("-drive file=/images/debian.qcow2,format=qcow2,if=virtio"));
+        assert!(joined.contains("-netdev user,id=net0,hostfwd=tcp::2222-:22"));
+        assert!(joined.contains("-snapshot"));
+        assert!(joined.contains("-serial null"));
+    }
+
+    #[test]
+    fn test_build_args_serial_uses_pty() {
+        let mut config = minimal_config();
+        config.port_forwards = Some(vec!["8080:80".to_string()]);
+        let args = QemuConnectionManager::build_args(&config, QemuAccess::Serial, None);
+        let joined = args.join(" ");
+        assert!(joined.contains("hostfwd=tcp::8080-:80"));
+        assert!(joined.contains("-serial pty"));
+    }
+
+    #[test]
+    fn test_parse_pty_path() {
+        assert_eq!(
+            QemuConnectionManager::parse_pty_path(
+                "char device redirected to /dev/pts/3 (label serial0)"
+            ),
+            Some("/dev/pts/3".to_string())
+        );
+        assert_eq!(QemuConnectionManager::parse_pty_path("qemu: warning"), None);
+    }
+}
@@ -2,9 +2,10 @@
 //!
 //! 该模块实现了通过串口执行Linux命令的连接管理器
 
-use anyhow::{Context, Result};
-use log::debug;
+use anyhow::{Context, Result, bail};
+use log::{debug, warn};
 use mio_serial::SerialPort;
+use regex::Regex;
 use std::io::Read;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -14,11 +15,16 @@ use crate::connection::{CommandOutput, ConnectionManager};
 
 use crate::template::ExecutorOptions;
 
+/// 哨兵标记前缀，命令输出通过 `<前缀><token>_<段名>__` 形式的行来分段
+const SENTINEL_PREFIX: &str = "__LINTESTOR_";
+
 /// 串口连接管理器
 pub struct SerialConnectionManager {
     config: SerialConfig,
     executor_options: ExecutorOptions,
     port: Option<Box<dyn SerialPort + Send>>, // 线程安全
+    /// 已执行命令计数，用于生成唯一的哨兵token
+    command_counter: u64,
 }
 
 impl SerialConnectionManager {
@@ -27,6 +33,98 @@ impl SerialConnectionManager {
             config,
             executor_options,
             port: None,
+            command_counter: 0,
+        })
+    }
+
+    /// 生成本次命令使用的唯一token
+    fn next_token(&mut self) -> String {
+        self.command_counter += 1;
+        let nanos = chrono::Local::now()
+            .timestamp_nanos_opt()
+            .unwrap_or_default();
+        format!(
+            "{}_{:x}_{}",
+            std::process::id(),
+            nanos,
+            self.command_counter
+        )
+    }
+
+    /// 用哨兵包装命令
+    ///
+    /// 远端shell依次打印 OUT 标记、命令的标准输出、ERR 标记、标准错误（可选）、
+    /// 最后打印带有 `$?` 的 END 标记。标记在命令行里被 `""` 拆开，
+    /// 所以终端回显的命令行本身不会被误认为标记。
+    fn wrap_command(command: &str, token: &str, separate_stderr: bool) -> String {
+        let marker = |section: &str| format!("{SENTINEL_PREFIX}\"\"{token}_{section}__");
+        if separate_stderr {
+            let err_file = format!("/tmp/.lintestor_{token}.err");
+            format!(
+                "echo {out}; {{ {command}\n}} 2>{err_file}; __lts_rc=$?; echo {err}; cat {err_file} 2>/dev/null; rm -f {err_file}; echo {end}:$__lts_rc",
+                out = marker("OUT"),
+                err = marker("ERR"),
+                end = marker("END"),
+            )
+        } else {
+            format!(
+                "echo {out}; {{ {command}\n}}; __lts_rc=$?; echo {end}:$__lts_rc",
+                out = marker("OUT"),
+                end = marker("END"),
+            )
+        }
+    }
+
+    /// 匹配带退出码的结束标记，要求行尾已经读到，避免把只读到一部分的退出码当作完整的退出码
+    fn end_sentinel_regex(token: &str) -> Result<Regex> {
+        Ok(Regex::new(&format!(
+            r"(?m)^{}_END__:(-?\d+)\r?\n",
+            regex::escape(&format!("{SENTINEL_PREFIX}{token}"))
+        ))?)
+    }
+
+    /// 从带哨兵的原始串口输出中解析出 stdout、stderr 和退出码
+    fn parse_wrapped_output(raw: &str, token: &str) -> Result<CommandOutput> {
+        let normalized = raw.replace("\r\n", "\n").replace('\r', "");
+        let out_marker = format!("{SENTINEL_PREFIX}{token}_OUT__");
+        let err_marker = format!("{SENTINEL_PREFIX}{token}_ERR__");
+        let end_re = Self::end_sentinel_regex(token)?;
+
+        // 标记必须单独成行，这样才能跳过回显的命令和提示符
+        let find_line = |marker: &str, from: usize| -> Option<(usize, usize)> {
+            let mut offset = from;
+            for line in normalized[from..].split_inclusive('\n') {
+                if line.trim_end() == marker {
+                    return Some((offset, offset + line.len()));
+                }
+                offset += line.len();
+            }
+            None
+        };
+
+        let (_, stdout_start) = find_line(&out_marker, 0)
+            .ok_or_else(|| anyhow::anyhow!("Start sentinel not found in serial output"))?;
+        let end_caps = end_re
+            .captures_at(&normalized, stdout_start)
+            .ok_or_else(|| anyhow::anyhow!("End sentinel not found in serial output"))?;
+        let end_match = end_caps.get(0).unwrap();
+        let exit_code = end_caps[1]
+            .parse::<i32>()
+            .with_context(|| format!("Invalid exit code in end sentinel: {}", &end_caps[1]))?;
+
+        let (stdout, stderr) = match find_line(&err_marker, stdout_start) {
+            Some((err_start, err_end)) if err_start < end_match.start() => (
+                &normalized[stdout_start..err_start],
+                &normalized[err_end..end_match.start()],
+            ),
+            _ => (&normalized[stdout_start..end_match.start()], ""),
+        };
+
+        Ok(CommandOutput {
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            exit_code,
+            reconnects: Vec::new(),
         })
     }
 
@@ -40,6 +138,28 @@ impl SerialConnectionManager {
         Ok(Box::new(stream))
     }
 
+    /// 等待正则匹配出现
+    fn wait_for_regex(port: &mut dyn Read, re: &Regex, timeout: Duration) -> Result<String> {
+        let start = Instant::now();
+        let mut buf = vec![0u8; 4096];
+        let mut output = String::new();
+        while start.elapsed() < timeout {
+            match port.read(&mut buf) {
+                Ok(n) if n > 0 => {
+                    output.push_str(&String::from_utf8_lossy(&buf[..n]));
+                    if re.is_match(&output) {
+                        return Ok(output);
+                    }
+                }
+                _ => thread::sleep(Duration::from_millis(50)),
+            }
+        }
+        Err(anyhow::anyhow!(
+            "Waiting for pattern timeout: {}",
+            re.as_str()
+        )) // 等待pattern超时
+    }
+
     /// 等待特定pattern出现
     fn wait_for_pattern(
         port: &mut dyn SerialPort,
@@ -120,7 +240,8 @@ impl ConnectionManager for SerialConnectionManager {
         timeout: Option<Duration>,
     ) -> Result<CommandOutput> {
         let timeout = timeout.unwrap_or(Duration::from_secs(self.executor_options.command_timeout));
-        let shell_prompt = &self.config.shell_prompt;
+        let separate_stderr = self.config.separate_stderr.unwrap_or(true);
+        let token = self.next_token();
         let port = self
             .port
             .as_mut()
@@ -133,24 +254,26 @@ impl ConnectionManager for SerialConnectionManager {
                 break;
             }
         }
-        // 发送命令
-        Self::send_line(&mut **port, command)?;
-        // 读取直到下一个shell提示符
-        let output = Self::wait_for_pattern(&mut **port, shell_prompt, timeout)?;
-        // 提取命令输出（去掉命令本身和提示符）
-        let mut lines: Vec<&str> = output.lines().collect();
-        if !lines.is_empty() && lines[0].trim() == command.trim() {
-            lines.remove(0);
-        }
-        if !lines.is_empty() && lines.last().unwrap().contains(shell_prompt) {
-            lines.pop();
-        }
-        let stdout = lines.join("\n");
-        Ok(CommandOutput {
-            stdout,
-            stderr: String::new(), // 串口无法区分
-            exit_code: 0,          // 串口一般无法获取退出码
-        })
+        // 发送包装后的命令
+        let wrapped = Self::wrap_command(command, &token, separate_stderr);
+        Self::send_line(&mut **port, &wrapped)?;
+        // 读取直到出现带退出码的结束标记
+        let end_re = Self::end_sentinel_regex(&token)?;
+        let output = match Self::wait_for_regex(&mut **port, &end_re, timeout) {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("Serial command timed out, sending Ctrl-C: {command}"); // 串口命令超时，发送 Ctrl-C
+                let _ = port.write_all(&[0x03]);
+                let _ = port.flush();
+                bail!("Serial command execution timeout: {e}"); // 串口命令执行超时
+            }
+        };
+        let result = Self::parse_wrapped_output(&output, &token)?;
+        debug!(
+            "Serial command execution completed: exit_code={}",
+            result.exit_code
+        ); // 串口命令执行完成: exit_code={}
+        Ok(result)
     }
 
     fn close(&mut self) -> Result<()> {
@@ -158,3 +281,72 @@ impl ConnectionManager for SerialConnectionManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wrapped_output_with_echo_and_prompt() {
+        let token = "42_abc_1";
+        let wrapped = SerialConnectionManager::wrap_command("ls /nope", token, true);
+        let raw = format!(
+            "{wrapped}\r\n> }} 2>/tmp/x\r\n__LINTESTOR_42_abc_1_OUT__\r\nline1\r\nline2\r\n__LINTESTOR_42_abc_1_ERR__\r\nls: /nope: No such file\r\n__LINTESTOR_42_abc_1_END__:2\r\nroot@board:~# "
+        );
+        let output = SerialConnectionManager::parse_wrapped_output(&raw, token).unwrap();
+        assert_eq!(output.stdout, "line1\nline2\n");
+        assert_eq!(output.stderr, "ls: /nope: No such file\n");
+        assert_eq!(output.exit_code, 2);
+    }
+
+    #[test]
+    fn test_parse_wrapped_output_without_stderr_section() {
+        let token = "7_ff_3";
+        let raw = "__LINTESTOR_7_ff_3_OUT__\nhello\n__LINTESTOR_7_ff_3_END__:0\n# ";
+        let output = SerialConnectionManager::parse_wrapped_output(raw, token).unwrap();
+        assert_eq!(output.stdout, "hello\n");
+        assert_eq!(output.stderr, "");
+        assert_eq!(output.exit_code, 0);
+    }
+
+    /// 按顺序返回给定数据块的读取端，数据读完后返回 0 字节
+    struct ChunkedReader(Vec<&'static str>);
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0).as_bytes();
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_wait_for_truncated_end_marker() {
+        let end_re = SerialConnectionManager::end_sentinel_regex("t").unwrap();
+        let truncated = "__LINTESTOR_t_OUT__\r\nmissing\r\n__LINTESTOR_t_END__:12";
+
+        // 退出码所在的行还没有读完时继续等待
+        let mut port = ChunkedReader(vec![truncated]);
+        assert!(
+            SerialConnectionManager::wait_for_regex(&mut port, &end_re, Duration::from_millis(300))
+                .is_err()
+        );
+
+        let mut port = ChunkedReader(vec![truncated, "7\r\nroot@board:~# "]);
+        let raw =
+            SerialConnectionManager::wait_for_regex(&mut port, &end_re, Duration::from_secs(5))
+                .unwrap();
+        let output = SerialConnectionManager::parse_wrapped_output(&raw, "t").unwrap();
+        assert_eq!(output.exit_code, 127);
+        assert_eq!(output.stdout, "missing\n");
+    }
+
+    #[test]
+    fn test_parse_wrapped_output_missing_end_marker() {
+        let raw = "__LINTESTOR_t_OUT__\npartial output";
+        assert!(SerialConnectionManager::parse_wrapped_output(raw, "t").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! OpenSSH 客户端配置解析
+//!
+//! 按 OpenSSH 的规则从 `~/.ssh/config` 和 `/etc/ssh/ssh_config` 中求出一个主机别名的
+//! HostName、User、Port、IdentityFile 和 ProxyJump：每个参数取第一个匹配到的值，IdentityFile 可以有多个。
+//! `Match` 块和 `Include` 指令不受支持，会被忽略。
+
+use log::debug;
+use std::path::PathBuf;
+
+/// 一个主机别名在 SSH 配置中的参数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SshHostConfig {
+    /// 实际连接的主机名（HostName），`%h` 已替换为别名
+    pub host_name: Option<String>,
+    /// 用户名（User）
+    pub user: Option<String>,
+    /// 端口（Port）
+    pub port: Option<u16>,
+    /// 私钥文件（IdentityFile），按出现顺序排列
+    pub identity_files: Vec<String>,
+    /// 跳板机（ProxyJump），逗号分隔，`none` 表示不使用跳板机
+    pub proxy_jump: Option<String>,
+}
+
+impl SshHostConfig {
+    /// 从用户和系统的 SSH 配置文件中查找主机别名，文件不存在时视为空
+    pub fn lookup(alias: &str) -> Self {
+        let mut files = Vec::new();
+        if let Some(home) = std::env::var_os("HOME") {
+            files.push(PathBuf::from(home).join(".ssh").join("config"));
+        }
+        files.push(PathBuf::from("/etc/ssh/ssh_config"));
+
+        let mut config = Self::default();
+        for path in files {
+            match std::fs::read_to_string(&path) {
+                Ok(content) => config.apply(&content, alias),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => debug!("Unable to read SSH config {}: {e}", path.display()), // 无法读取SSH配置文件
+            }
+        }
+        config
+    }
+
+    /// 应用一个配置文件中与别名匹配的参数，已设置的参数不会被覆盖
+    fn apply(&mut self, content: &str, alias: &str) {
+        // 第一个 Host 之前的参数对所有主机生效
+        let mut active = true;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((keyword, value)) = split_directive(line) else {
+                continue;
+            };
+            match keyword.to_ascii_lowercase().as_str() {
+                "host" => active = host_matches(value, alias),
+                "match" => active = false,
+                _ if !active => {}
+                "hostname" => {
+                    self.host_name
+                        .get_or_insert_with(|| value.replace("%h", alias));
+                }
+                "user" => {
+                    self.user.get_or_insert_with(|| value.to_string());
+                }
+                "port" => self.port = self.port.or_else(|| value.parse().ok()),
+                "identityfile" => self.identity_files.push(value.to_string()),
+                "proxyjump" => {
+                    self.proxy_jump.get_or_insert_with(|| value.to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// 把一行拆分为关键字和值，支持 `Keyword value` 和 `Keyword=value` 两种写法，并去掉值两侧的引号
+fn split_directive(line: &str) -> Option<(&str, &str)> {
+    let end = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let (keyword, rest) = line.split_at(end);
+    let value = rest.trim_start().strip_prefix('=').unwrap_or(rest).trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    (!value.is_empty()).then_some((keyword, value))
+}
+
+/// 判断别名是否匹配 `Host` 行的模式列表：任一否定模式（`!pattern`）匹配时不匹配，否则任一模式匹配即可
+fn host_matches(patterns: &str, alias: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split_whitespace() {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, alias) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(pattern, alias),
+        }
+    }
+    matched
+}
+
+/// 支持 `*` 和 `?` 的通配符匹配
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+# 全局参数
+IdentityFile ~/.ssh/id_global
+
+Host bastion jump-*
+    HostName %h.example.com
+    User jumper
+    Port 2200
+
+Host board
+    ProxyJump bastion,jump-eu
+
+Host *.internal !secret.internal
+    User internal
+
+Host *
+    User fallback
+    Port=22
+    IdentityFile "~/.ssh/id_default"
+
+Match host other
+    User matched
+"#;
+
+    fn lookup(alias: &str) -> SshHostConfig {
+        let mut config = SshHostConfig::default();
+        config.apply(CONFIG, alias);
+        config
+    }
+
+    #[test]
+    fn test_first_value_wins() {
+        let config = lookup("jump-eu");
+        assert_eq!(config.host_name.as_deref(), Some("jump-eu.example.com"));
+        assert_eq!(config.user.as_deref(), Some("jumper"));
+        assert_eq!(config.port, Some(2200));
+        assert_eq!(
+            config.identity_files,
+            vec!["~/.ssh/id_global", "~/.ssh/id_default"]
+        );
+    }
+
+    #[test]
+    fn test_proxy_jump() {
+        let config = lookup("board");
+        assert_eq!(config.proxy_jump.as_deref(), Some("bastion,jump-eu"));
+        assert_eq!(config.user.as_deref(), Some("fallback"));
+        assert_eq!(lookup("jump-eu").proxy_jump, None);
+    }
+
+    #[test]
+    fn test_negated_and_wildcard_patterns() {
+        assert_eq!(lookup("db.internal").user.as_deref(), Some("internal"));
+        assert_eq!(lookup("secret.internal").user.as_deref(), Some("fallback"));
+        let other = lookup("other");
+        assert_eq!(other.host_name, None);
+        assert_eq!(other.user.as_deref(), Some("fallback"));
+        assert_eq!(other.port, Some(22));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("board-?", "board-1"));
+        assert!(wildcard_match("*.example.*", "a.example.com"));
+        assert!(!wildcard_match("board-?", "board-12"));
+        assert!(!wildcard_match("*.com", "example.org"));
+    }
+}
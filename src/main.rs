@@ -5,14 +5,26 @@ mod pool;
 mod template;
 mod utils;
 
-use crate::config::cli_args::CliArgs;
+use crate::config::cli_args::{
+    Cli, Command, ListArgs, ListFormat, ReportArgs, ReportFormat, RunArgs, TemplateArgs,
+};
 use crate::config::target_config::TargetConfig;
-use crate::template::{StepStatus, TestTemplate};
-use crate::template::{TemplateFilter, discover_templates, filter_templates};
+use crate::template::history::{self, RunComparison, RunRecord};
+use crate::template::{
+    CompatibilityMatrix, JsonReport, StepStatus, TapStream, TemplateListing, TestTemplate,
+    write_summary_report,
+};
+use crate::template::{
+    Diagnostic, LoadedTemplates, TemplateFilter, discover_templates, load_templates,
+    validate_template,
+};
 use env_logger::Env;
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
 use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::{
     env,
     path::{Path, PathBuf},
@@ -22,42 +34,181 @@ use template::{BatchExecutor, BatchOptions, ExecutionResult, ExecutorOptions};
 /// The main function of the application.
 fn main() -> Result<(), Box<dyn Error>> {
     // 解析命令行参数
-    let cli_args = parse_args();
+    let cli = parse_args();
+    let log_level = cli.get_log_level().to_string();
+    let (command, deprecation) = cli.resolve_command();
 
     // 设置日志级别
-    env_logger::Builder::from_env(Env::default().default_filter_or(cli_args.get_log_level()))
-        .init();
+    let mut log_builder =
+        env_logger::Builder::from_env(Env::default().default_filter_or(log_level));
+    if matches!(&command, Command::Run(args) if args.get_jobs() > 1) {
+        // 并行执行时在每条日志前加上目标名称，便于区分交错的输出
+        log_builder.format(|buf, record| {
+            let level_style = buf.default_level_style(record.level());
+            let prefix = utils::log_prefix()
+                .map(|p| format!("[{p}] "))
+                .unwrap_or_default();
+            writeln!(
+                buf,
+                "[{} {level_style}{:<5}{level_style:#} {}] {prefix}{}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        });
+    }
+    log_builder.init();
+    if let Some(message) = deprecation {
+        warn!("{message}"); // 使用了已弃用的参数
+    }
 
-    // 获取工作目录
-    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    let test_dir = cli_args
-        .test_dir
-        .as_ref()
-        .map(|dir| cwd.join(dir))
-        .unwrap_or(match cli_args.template.as_ref() {
-            Some(template_file) => {
-                // 如果指定了单个模板文件，则使用该文件所在目录作为工作目录
-                template_file.parent().unwrap_or(&cwd).to_path_buf()
+    match command {
+        Command::Run(args) => {
+            let test_dir = resolve_test_dir(&args.templates);
+            // 检查是否有指定单个模板文件
+            if let Some(template_file) = args.templates.template.as_ref() {
+                run_single_template_test(template_file, &args, &test_dir)?;
+            } else {
+                run_template_tests(&args, &test_dir)?
             }
-            None => cwd.clone(), // 如果没有指定模板文件，则使用当前工作目录
-        });
+        }
+        Command::Parse(args) => parse_templates(&args, &resolve_test_dir(&args))?,
+        Command::List(args) => list_templates(&args, &resolve_test_dir(&args.templates))?,
+        Command::Validate(args) => validate_templates(&args, &resolve_test_dir(&args))?,
+        Command::Report(args) => show_report(&args)?,
+    }
+    Ok(())
+}
+
+/// 解析命令行参数
+/// 返回解析后的`Cli`对象
+fn parse_args() -> Cli {
+    Cli::parse_args()
+}
+
+/// 确定工作目录：优先使用 --test-dir，其次是 --template 所在目录，最后是当前目录
+fn resolve_test_dir(args: &TemplateArgs) -> PathBuf {
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let test_dir =
+        args.test_dir
+            .as_ref()
+            .map(|dir| cwd.join(dir))
+            .unwrap_or(match args.template.as_ref() {
+                Some(template_file) => {
+                    // 如果指定了单个模板文件，则使用该文件所在目录作为工作目录
+                    template_file.parent().unwrap_or(&cwd).to_path_buf()
+                }
+                None => cwd.clone(), // 如果没有指定模板文件，则使用当前工作目录
+            });
     debug!("Working directory: {}", test_dir.display());
+    test_dir
+}
 
-    // 检查是否有指定单个模板文件
-    if let Some(template_file) = cli_args.template.as_ref() {
-        // 应用环境类型设置--
-        run_single_template_test(template_file, &cli_args, &test_dir)?;
+/// 确定报告目录：未指定 `--reports-dir` 时为测试目录下的 `reports`
+///
+/// `run` 和 `report` 使用同样的规则，`report` 才能读到 `run` 写入的历史记录
+fn resolve_reports_dir(reports_dir: Option<&PathBuf>, test_dir: &Path) -> PathBuf {
+    reports_dir
+        .cloned()
+        .unwrap_or_else(|| test_dir.join("reports"))
+}
+
+/// 在测试目录及其 tests、templates 子目录中发现模板文件（去重）
+fn discover_template_paths(test_dir: &Path) -> Vec<PathBuf> {
+    info!("Discovering Markdown test templates...");
+
+    // Guess the template directories in "tests" and "templates" subdirectories
+    // Hardcoded LOL...
+    let template_dirs = vec![
+        test_dir.to_path_buf(),
+        test_dir.join("tests"),
+        test_dir.join("templates"),
+    ];
+    let mut all_template_paths = Vec::new();
+    for dir in &template_dirs {
+        if let Ok(mut paths) = discover_templates(dir, true) {
+            paths.retain(|path| !all_template_paths.contains(path));
+            all_template_paths.extend(paths);
+        }
+    }
+    info!(
+        "Found {} template files (deduplicated)",
+        all_template_paths.len()
+    );
+    if all_template_paths.is_empty() {
+        warn!("No template files found in the specified directories.");
+    }
+    all_template_paths
+}
+
+/// 由命令行筛选参数创建模板过滤器
+fn template_filter(args: &TemplateArgs) -> TemplateFilter {
+    let (unit_filter, tag_filter, target_metadata_filter) = args.get_filters();
+    TemplateFilter {
+        target: target_metadata_filter.map(|t| t.to_string()),
+        unit: unit_filter.map(|u| u.to_string()),
+        tags: tag_filter.map_or_else(Vec::new, |t| vec![t.to_string()]),
+    }
+}
+
+/// 加载 --template 指定的模板，或在测试目录中发现并按筛选条件过滤模板
+///
+/// 发现的模板中加载失败的记录在 `failures` 中。设置了筛选条件时无法判断加载失败的模板是否符合条件，
+/// 这些模板只给出警告，不计入 `failures`。
+fn load_selected_templates(
+    args: &TemplateArgs,
+    test_dir: &Path,
+) -> Result<LoadedTemplates, Box<dyn Error>> {
+    if let Some(template_file) = args.template.as_ref() {
+        return match TestTemplate::from_file(template_file, test_dir) {
+            Ok(template) => Ok(LoadedTemplates {
+                templates: vec![template],
+                failures: Vec::new(),
+            }),
+            Err(e) => Err(format!("Failed to load template from file: {e:#}").into()),
+        };
+    }
+
+    let all_template_paths = discover_template_paths(test_dir);
+    if all_template_paths.is_empty() {
+        return Ok(LoadedTemplates::default());
+    }
+
+    let filter = template_filter(args);
+    let mut loaded = load_templates(all_template_paths, &filter, test_dir);
+    if !filter.is_empty() && !loaded.failures.is_empty() {
+        warn!(
+            "Ignoring {} templates that failed to load, since they cannot be matched against the filter.",
+            loaded.failures.len()
+        ); // 加载失败的模板无法与筛选条件比较，忽略这些模板
+        loaded.failures.clear();
+    }
+    if loaded.templates.is_empty() {
+        warn!("No templates found matching the criteria after filtering.");
     } else {
-        // 创建模板过滤器，应用单元和标签过滤
-        run_template_tests(&cli_args, &test_dir)?
+        info!(
+            "Successfully loaded and filtered {} templates.",
+            loaded.templates.len()
+        );
     }
-    Ok(())
+    Ok(loaded)
 }
 
-/// 解析命令行参数
-/// 返回解析后的`CliArgs`对象
-fn parse_args() -> CliArgs {
-    CliArgs::parse_args()
+/// 只解析模板并显示模板信息，不执行
+fn parse_templates(args: &TemplateArgs, test_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let templates = load_selected_templates(args, test_dir)?.templates;
+    info!("Parse-only mode. Displaying template information:");
+    for template in &templates {
+        info!("  Title: {}", template.metadata.title);
+        info!("  Unit: {}", template.metadata.unit_name);
+        info!(
+            "  Target config: {}",
+            template.metadata.target_config.get_path().display()
+        );
+        info!("  Total steps: {}", template.steps.len());
+    }
+    Ok(())
 }
 
 /// 运行单个测试模板文件
@@ -69,7 +220,7 @@ fn parse_args() -> CliArgs {
 /// * `test_dir` - 工作目录
 fn run_single_template_test(
     template_file: &Path,
-    cli_args: &CliArgs,
+    cli_args: &RunArgs,
     test_dir: &Path,
 ) -> Result<String, Box<dyn Error>> {
     info!("Processing single template: {}", template_file.display());
@@ -84,35 +235,13 @@ fn run_single_template_test(
 
     let target_config_path = template.metadata.target_config.get_path();
 
-    // 如果是仅解析模式，则只验证模板格式并显示信息
-    if cli_args.parse_only {
-        info!("  Title: {}", template.metadata.title);
-        info!("  Unit: {}", template.metadata.unit_name);
-        info!("  Target config: {}", target_config_path.display());
-        info!("  Total steps: {}", template.steps.len());
-        return Ok("Template parsed successfully".to_string());
-    }
-
-    // 加载目标配置
+    // 与批量执行一样加载目标配置并确定执行器选项，无法加载时返回错误
     info!("Loading target config: {}", target_config_path.display());
-
-    let mut target_config: TargetConfig = match TargetConfig::from_file(
-        target_config_path
-            .to_str()
-            .expect("Target path does not exist"),
-    ) {
-        Ok(config) => config,
-        Err(e) => {
-            return Err(format!("Failed to load target config: {e}").into());
-        }
-    };
-
-    // 如果有环境类型覆盖，则更新目标配置
-    let environment_type = cli_args.get_environment_type();
-    if let Some(env_type) = &environment_type {
-        info!("Overriding environment type to: {env_type}");
-        target_config.testing_type = env_type.clone();
-    }
+    let executor_options = target_executor_options(
+        cli_args,
+        target_config_path,
+        cli_args.get_environment_type().as_ref(),
+    )?;
 
     // 创建变量管理器
     let variable_manager = template::VariableManager::new();
@@ -120,44 +249,20 @@ fn run_single_template_test(
     // 创建连接池
     let connection_pool = pool::ConnectionManagerPool::new();
 
-    // 准备执行选项，优先级顺序: CLI参数 > target_config.executor > 默认值
-    let default_options = ExecutorOptions::default();
-    let executor_options = ExecutorOptions {
-        command_timeout: cli_args
-            .timeout
-            .or(target_config.executor.command_timeout.map(|d| d.as_secs()))
-            .unwrap_or(default_options.command_timeout),
-        retry_count: cli_args
-            .retry
-            .or(target_config.executor.retry_count)
-            .unwrap_or(default_options.retry_count),
-        retry_interval: cli_args
-            .retry_interval
-            .or(target_config.executor.retry_interval)
-            .unwrap_or(default_options.retry_interval),
-        maintain_session: cli_args
-            .maintain_session
-            .or(target_config.executor.maintain_session)
-            .unwrap_or(default_options.maintain_session),
-        continue_on_error: cli_args
-            .continue_on_error
-            .or(target_config.executor.continue_on_error)
-            .unwrap_or(default_options.continue_on_error),
-    };
-
     // 定义报告目录
-    let report_dir = cli_args
-        .reports_dir
-        .as_ref()
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| test_dir.join("reports"));
+    let report_dir = resolve_reports_dir(cli_args.reports_dir.as_ref(), test_dir);
+    let history = load_run_history(cli_args, &report_dir)?;
 
     // 准备批量选项
+    let tap_stream = tap_stream(cli_args);
     let batch_options = BatchOptions {
         test_directory: Some(test_dir.to_path_buf()),
         report_directory: Some(report_dir.clone()),
         executor_options: executor_options.clone(),
         keep_template_directory_structure: cli_args.keep_template_directory_structure,
+        report_formats: cli_args.get_report_formats(),
+        tap_stream: tap_stream.clone(),
+        interactive: io::stdin().is_terminal(),
     };
 
     // 创建批量执行器
@@ -168,7 +273,19 @@ fn run_single_template_test(
     batch_executor.add_template(template.into())?;
 
     // 执行模板测试
-    match batch_executor.execute_all() {
+    let execution = batch_executor.execute_all();
+    if let Ok(results) = &execution {
+        write_run_reports(
+            cli_args,
+            &report_dir,
+            &results.iter().collect::<Vec<_>>(),
+            &history,
+        );
+    }
+    if let Some(tap_stream) = &tap_stream {
+        tap_stream.finish();
+    }
+    match execution {
         Ok(results) => {
             info!("Template execution completed");
 
@@ -197,68 +314,12 @@ fn run_single_template_test(
 ///
 /// * `cli_args` - Command line arguments.
 /// * `test_dir` - Working directory containing templates and target configs.
-fn run_template_tests(cli_args: &CliArgs, test_dir: &Path) -> Result<(), Box<dyn Error>> {
-    info!("Discovering Markdown test templates...");
-
-    // Guess the template directories in "tests" and "templates" subdirectories
-    // Hardcoded LOL...
-    let template_dirs = vec![
-        test_dir.to_path_buf(),
-        test_dir.join("tests"),
-        test_dir.join("templates"),
-    ];
-    let mut all_template_paths = Vec::new();
-    for dir in &template_dirs {
-        if let Ok(mut paths) = discover_templates(dir, true) {
-            paths.retain(|path| !all_template_paths.contains(path));
-            all_template_paths.extend(paths);
-        }
-    }
-    info!(
-        "Found {} template files (deduplicated)",
-        all_template_paths.len()
-    );
-    if all_template_paths.is_empty() {
-        warn!("No template files found in the specified directories.");
-        return Ok(());
-    }
-
-    let (unit_filter, tag_filter, target_metadata_filter) = cli_args.get_filters();
-    let filter = TemplateFilter {
-        target: target_metadata_filter.map(|t| t.to_string()),
-        unit: unit_filter.map(|u| u.to_string()),
-        tags: tag_filter.map_or_else(Vec::new, |t| vec![t.to_string()]),
-    };
-
-    let loaded_templates: Vec<TestTemplate> =
-        match filter_templates(all_template_paths, &filter, test_dir) {
-            Ok(t) => t,
-            Err(e) => {
-                error!("Failed to load or filter templates: {e}");
-                return Err(e.into()); // Propagate error
-            }
-        };
-
-    if loaded_templates.is_empty() {
-        warn!("No templates found matching the criteria after filtering.");
-        return Ok(());
-    }
-    info!(
-        "Successfully loaded and filtered {} templates.",
-        loaded_templates.len()
-    );
-
-    if cli_args.parse_only {
-        info!("Parse-only mode. Displaying template information:");
-        for template in &loaded_templates {
-            info!("  Title: {}", template.metadata.title);
-            info!("  Unit: {}", template.metadata.unit_name);
-            info!(
-                "  Target config: {}",
-                template.metadata.target_config.get_path().display()
-            );
-            info!("  Total steps: {}", template.steps.len());
-        }
+fn run_template_tests(cli_args: &RunArgs, test_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let LoadedTemplates {
+        templates: loaded_templates,
+        failures,
+    } = load_selected_templates(&cli_args.templates, test_dir)?;
+    if loaded_templates.is_empty() && failures.is_empty() {
         return Ok(());
     }
 
@@ -269,7 +330,8 @@ fn run_template_tests(cli_args: &CliArgs, test_dir: &Path) -> Result<(), Box<dyn
     let templates_for_display = loaded_templates.clone();
 
     for template in loaded_templates {
-        let target_config_file_path = test_dir.join(template.metadata.target_config.get_path());
+        // 按加载模板时实际读取的目标配置文件分组
+        let target_config_file_path = template.metadata.target_config.get_path().clone();
         let group_key = (target_config_file_path, environment_type_override.clone());
         grouped_templates
             .entry(group_key)
@@ -282,11 +344,7 @@ fn run_template_tests(cli_args: &CliArgs, test_dir: &Path) -> Result<(), Box<dyn
         grouped_templates.len()
     );
 
-    let report_dir = cli_args
-        .reports_dir
-        .as_ref()
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|| test_dir.join("reports"));
+    let report_dir = resolve_reports_dir(cli_args.reports_dir.as_ref(), test_dir);
 
     if !report_dir.exists() {
         std::fs::create_dir_all(&report_dir).map_err(|e| {
@@ -297,8 +355,16 @@ fn run_template_tests(cli_args: &CliArgs, test_dir: &Path) -> Result<(), Box<dyn
             )
         })?;
     }
-
-    let mut all_results: Vec<(PathBuf, ExecutionResult)> = Vec::new();
+    let history = load_run_history(cli_args, &report_dir)?;
+
+    // 加载失败的模板以失败结果计入汇总报告，其余模板照常执行
+    let mut all_results: Vec<(PathBuf, ExecutionResult)> = failures
+        .into_iter()
+        .map(|(path, error)| {
+            let template = Arc::new(TestTemplate::placeholder(&path, test_dir));
+            (path, ExecutionResult::failed(template, error))
+        })
+        .collect();
     for template in &templates_for_display {
         let metadata = &template.metadata;
         info!("Template: {}", metadata.title);
@@ -310,162 +376,643 @@ fn run_template_tests(cli_args: &CliArgs, test_dir: &Path) -> Result<(), Box<dyn
         info!("  Total steps: {}", template.steps.len());
     }
 
-    for ((target_config_path, group_env_override), templates_in_group) in grouped_templates {
+    let mut groups: Vec<_> = grouped_templates.into_iter().collect();
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let groups = merge_dependent_groups(groups);
+    let jobs = cli_args.get_jobs().min(groups.len().max(1));
+    let tap_stream = tap_stream(cli_args);
+    if jobs <= 1 {
+        for ((target_config_path, group_env_override), templates_in_group) in groups {
+            all_results.extend(run_template_group(
+                cli_args,
+                test_dir,
+                &report_dir,
+                &target_config_path,
+                group_env_override,
+                templates_in_group,
+                tap_stream.clone(),
+            )?);
+        }
+    } else {
         info!(
-            "Processing batch for target_config: {}, env_override: {:?}",
-            target_config_path.display(),
-            group_env_override
+            "Running {} target groups with {} parallel jobs",
+            groups.len(),
+            jobs
         );
+        // 每个工作线程从队列中领取一个目标分组，分组之间互不共享连接池
+        let queue = Mutex::new(groups.into_iter());
+        let group_results = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                scope.spawn(|| {
+                    loop {
+                        let next = queue.lock().unwrap().next();
+                        let Some(((target_config_path, group_env_override), templates_in_group)) =
+                            next
+                        else {
+                            break;
+                        };
+                        utils::set_log_prefix(Some(group_log_prefix(&target_config_path)));
+                        let result = run_template_group(
+                            cli_args,
+                            test_dir,
+                            &report_dir,
+                            &target_config_path,
+                            group_env_override,
+                            templates_in_group,
+                            tap_stream.clone(),
+                        );
+                        utils::set_log_prefix(None);
+                        group_results.lock().unwrap().push(result);
+                    }
+                });
+            }
+        });
+        for result in group_results.into_inner().unwrap() {
+            all_results.extend(result?);
+        }
+    }
+    if let Some(tap_stream) = &tap_stream {
+        tap_stream.finish();
+    }
 
-        if !target_config_path.exists() {
-            let msg = format!(
-                "Target configuration file not found: {}. Skipping {} templates in this group.",
-                target_config_path.display(),
-                templates_in_group.len()
-            );
-            error!("{msg}");
-            warn!(
-                "Skipping batch due to missing target configuration: {}",
-                target_config_path.display()
-            );
-            continue;
+    // 合并所有目标分组的结果，只生成一次汇总报告
+    let results: Vec<&ExecutionResult> = all_results.iter().map(|(_, result)| result).collect();
+    write_run_reports(cli_args, &report_dir, &results, &history);
+
+    let mut success_count = 0;
+    let mut fail_count = 0;
+    for (_path, result) in &all_results {
+        match result.overall_status {
+            StepStatus::Pass => success_count += 1,
+            StepStatus::Fail => fail_count += 1,
+            _ => {}
         }
+    }
 
-        let mut target_config: TargetConfig = match utils::read_toml_from_file(&target_config_path)
-        {
-            Ok(config) => config,
+    info!(
+        "Overall test summary: {} successful, {} failed out of {} executed templates/results.",
+        success_count,
+        fail_count,
+        all_results.len()
+    );
+
+    if fail_count > 0 {
+        return Err(format!("{fail_count} tests failed and continue_on_error is false.").into());
+    }
+
+    Ok(())
+}
+
+/// 执行同一目标配置（及环境覆盖）下的一组模板
+///
+/// 每个分组使用独立的连接池，因此不同分组可以在不同线程中并行执行。
+/// 目标配置缺失或加载失败、批次无法执行时，分组中的模板都以失败结果返回。
+fn run_template_group(
+    cli_args: &RunArgs,
+    test_dir: &Path,
+    report_dir: &Path,
+    target_config_path: &Path,
+    group_env_override: Option<String>,
+    templates_in_group: Vec<TestTemplate>,
+    tap_stream: Option<Arc<TapStream>>,
+) -> Result<Vec<(PathBuf, ExecutionResult)>, String> {
+    info!(
+        "Processing batch for target_config: {}, env_override: {:?}",
+        target_config_path.display(),
+        group_env_override
+    );
+
+    let executor_options =
+        match target_executor_options(cli_args, target_config_path, group_env_override.as_ref()) {
+            Ok(options) => options,
             Err(e) => {
                 let msg = format!(
-                    "Failed to load target config '{}': {}. Skipping {} templates in this group.",
-                    target_config_path.display(),
-                    e,
+                    "{e}. Skipping {} templates in this group.",
                     templates_in_group.len()
                 );
                 error!("{msg}");
                 warn!(
-                    "Skipping batch due to target configuration load failure: {}",
+                    "Skipping batch due to target configuration problem: {}",
                     target_config_path.display()
                 );
-                continue;
+                return Ok(failed_group_results(templates_in_group, &msg));
             }
         };
 
-        if let Some(env_type) = &group_env_override {
-            info!(
-                "Overriding environment type to: {} for target config {}",
-                env_type,
-                target_config_path.display()
-            );
-            target_config.testing_type = env_type.clone();
-        }
-
-        // 执行器选项，优先级顺序: CLI参数 > target_config.executor > 默认值
-        let default_options = ExecutorOptions::default();
-        let executor_options = ExecutorOptions {
-            command_timeout: cli_args
-                .timeout
-                .or(target_config.executor.command_timeout.map(|d| d.as_secs()))
-                .unwrap_or(default_options.command_timeout),
-            retry_count: cli_args
-                .retry
-                .or(target_config.executor.retry_count)
-                .unwrap_or(default_options.retry_count),
-            retry_interval: cli_args
-                .retry_interval
-                .or(target_config.executor.retry_interval)
-                .unwrap_or(default_options.retry_interval),
-            maintain_session: cli_args
-                .maintain_session
-                .or(target_config.executor.maintain_session)
-                .unwrap_or(default_options.maintain_session),
-            continue_on_error: cli_args
-                .continue_on_error
-                .or(target_config.executor.continue_on_error)
-                .unwrap_or(default_options.continue_on_error),
-        };
+    // 批量执行选项
+    let batch_options = BatchOptions {
+        test_directory: Some(test_dir.to_path_buf()),
+        report_directory: Some(report_dir.to_path_buf()),
+        executor_options: executor_options.clone(),
+        keep_template_directory_structure: cli_args.keep_template_directory_structure,
+        report_formats: cli_args.get_report_formats(),
+        tap_stream,
+        // 多个目标分组并行时各线程会争抢 stdin，因此不询问修正值
+        interactive: cli_args.get_jobs() <= 1 && io::stdin().is_terminal(),
+    };
 
-        // 批量执行选项
-        let batch_options = BatchOptions {
-            test_directory: Some(test_dir.to_path_buf()),
-            report_directory: Some(report_dir.clone()),
-            executor_options: executor_options.clone(),
-            keep_template_directory_structure: cli_args.keep_template_directory_structure,
-        };
+    let variable_manager = template::VariableManager::new();
+    // 创建连接池
+    let connection_pool = pool::ConnectionManagerPool::new();
+    // 无法加入批次的模板直接记为失败，不影响同组其他模板
+    let mut failed_results = Vec::new();
+    let batch_execution_results = {
+        let mut batch_executor =
+            BatchExecutor::new(variable_manager, connection_pool, Some(batch_options));
 
-        let variable_manager = template::VariableManager::new();
-        // 创建连接池
-        let connection_pool = pool::ConnectionManagerPool::new();
-        let batch_execution_results = {
-            let mut batch_executor =
-                BatchExecutor::new(variable_manager, connection_pool, Some(batch_options));
-
-            info!(
-                "Adding {} templates individually to batch for target_config '{}'",
-                templates_in_group.len(),
-                target_config_path.display()
-            );
-            for template in templates_in_group {
-                let metadata = &template.metadata;
-                debug!("Added template '{}' to batch executor", metadata.title);
-                batch_executor.add_template(template.into())?;
+        info!(
+            "Adding {} templates individually to batch for target_config '{}'",
+            templates_in_group.len(),
+            target_config_path.display()
+        );
+        let mut added_templates = Vec::new();
+        for template in templates_in_group {
+            let template = Arc::new(template);
+            let title = template.metadata.title.clone();
+            // 因跨模板依赖并入本分组的模板仍使用自己目标配置的执行器选项
+            let own_config_path = template.metadata.target_config.get_path();
+            if own_config_path != target_config_path {
+                match target_executor_options(
+                    cli_args,
+                    own_config_path,
+                    group_env_override.as_ref(),
+                ) {
+                    Ok(options) => batch_executor
+                        .set_template_executor_options(&template.get_template_id(), options),
+                    Err(e) => {
+                        let msg = format!("Failed to add template '{title}': {e}");
+                        error!("{msg}");
+                        failed_results.push(ExecutionResult::failed(template, msg));
+                        continue;
+                    }
+                }
             }
-
-            info!(
-                "Executing batch for target_config '{}'...",
-                target_config_path.display()
-            );
-            match batch_executor.execute_all() {
-                Ok(results_vec) => {
-                    info!(
-                        "Batch execution completed for target_config '{}'. {} results.",
-                        target_config_path.display(),
-                        results_vec.len()
-                    );
-                    results_vec
+            match batch_executor.add_template(template.clone()) {
+                Ok(()) => {
+                    debug!("Added template '{title}' to batch executor");
+                    added_templates.push(template);
                 }
                 Err(e) => {
-                    let msg = format!(
-                        "Failed to execute template batch for target_config '{}': {}",
-                        target_config_path.display(),
-                        e
-                    );
+                    let msg = format!("Failed to add template '{title}': {e}");
                     error!("{msg}");
-                    warn!(
-                        "Continuing after batch execution failure for: {}",
-                        target_config_path.display()
-                    );
-                    Vec::new()
+                    failed_results.push(ExecutionResult::failed(template, msg));
                 }
             }
-        };
+        }
 
-        let converted_results: Vec<(PathBuf, ExecutionResult)> = batch_execution_results
-            .into_iter()
-            .map(|exec_result| (exec_result.template.file_path.clone(), exec_result))
-            .collect();
-        all_results.extend(converted_results);
+        info!(
+            "Executing batch for target_config '{}'...",
+            target_config_path.display()
+        );
+        match batch_executor.execute_all() {
+            Ok(results_vec) => {
+                info!(
+                    "Batch execution completed for target_config '{}'. {} results.",
+                    target_config_path.display(),
+                    results_vec.len()
+                );
+                results_vec
+            }
+            Err(e) => {
+                let msg = format!(
+                    "Failed to execute template batch for target_config '{}': {}",
+                    target_config_path.display(),
+                    e
+                );
+                error!("{msg}");
+                warn!(
+                    "Continuing after batch execution failure for: {}",
+                    target_config_path.display()
+                );
+                added_templates
+                    .into_iter()
+                    .map(|template| ExecutionResult::failed(template, msg.clone()))
+                    .collect()
+            }
+        }
+    };
+
+    Ok(failed_results
+        .into_iter()
+        .chain(batch_execution_results)
+        .map(|exec_result| (exec_result.template.file_path.clone(), exec_result))
+        .collect())
+}
+
+/// 按目标配置确定执行器选项，优先级顺序: CLI参数 > target_config.executor > 默认值
+///
+/// 目标配置缺失或无法加载时返回错误信息
+fn target_executor_options(
+    cli_args: &RunArgs,
+    target_config_path: &Path,
+    env_override: Option<&String>,
+) -> Result<ExecutorOptions, String> {
+    if !target_config_path.exists() {
+        return Err(format!(
+            "Target configuration file not found: {}",
+            target_config_path.display()
+        ));
     }
 
-    let mut success_count = 0;
-    let mut fail_count = 0;
-    for (_path, result) in &all_results {
-        match result.overall_status {
-            StepStatus::Pass => success_count += 1,
-            StepStatus::Fail => fail_count += 1,
-            _ => {}
+    let mut target_config: TargetConfig =
+        utils::read_toml_from_file(&target_config_path.to_path_buf()).map_err(|e| {
+            format!(
+                "Failed to load target config '{}': {}",
+                target_config_path.display(),
+                e
+            )
+        })?;
+
+    if let Some(env_type) = env_override {
+        info!(
+            "Overriding environment type to: {} for target config {}",
+            env_type,
+            target_config_path.display()
+        );
+        target_config.testing_type = env_type.clone();
+    }
+
+    let default_options = ExecutorOptions::default();
+    Ok(ExecutorOptions {
+        command_timeout: cli_args
+            .timeout
+            .or(target_config.executor.command_timeout.map(|d| d.as_secs()))
+            .unwrap_or(default_options.command_timeout),
+        retry_count: cli_args
+            .retry
+            .or(target_config.executor.retry_count)
+            .unwrap_or(default_options.retry_count),
+        retry_interval: cli_args
+            .retry_interval
+            .or(target_config.executor.retry_interval)
+            .unwrap_or(default_options.retry_interval),
+        maintain_session: cli_args
+            .maintain_session
+            .or(target_config.executor.maintain_session)
+            .unwrap_or(default_options.maintain_session),
+        continue_on_error: cli_args
+            .continue_on_error
+            .or(target_config.executor.continue_on_error)
+            .unwrap_or(default_options.continue_on_error),
+        max_parallel_steps: target_config
+            .executor
+            .max_parallel_steps
+            .unwrap_or(default_options.max_parallel_steps),
+    })
+}
+
+/// 将整个分组的模板标记为失败
+fn failed_group_results(
+    templates: Vec<TestTemplate>,
+    error: &str,
+) -> Vec<(PathBuf, ExecutionResult)> {
+    templates
+        .into_iter()
+        .map(|template| {
+            let path = template.file_path.clone();
+            (path, ExecutionResult::failed(Arc::new(template), error))
+        })
+        .collect()
+}
+
+/// 读取报告目录中的运行历史
+///
+/// 指定了 `--compare-with` 时在执行测试前检查对比的运行是否存在；
+/// 未指定时历史记录无法读取只给出警告
+fn load_run_history(
+    cli_args: &RunArgs,
+    report_dir: &Path,
+) -> Result<Vec<RunRecord>, Box<dyn Error>> {
+    let history = match history::load_history(&report_dir.join(history::HISTORY_FILE_NAME)) {
+        Ok(history) => history,
+        Err(e) if cli_args.compare_with.is_none() => {
+            warn!("Ignoring unreadable run history: {e:#}"); // 忽略无法读取的运行历史
+            Vec::new()
         }
+        Err(e) => return Err(format!("{e:#}").into()),
+    };
+    if let Some(run_id) = &cli_args.compare_with {
+        history::find_run(&history, run_id)?;
     }
+    Ok(history)
+}
 
-    info!(
-        "Overall test summary: {} successful, {} failed out of {} executed templates/results.",
-        success_count,
-        fail_count,
-        all_results.len()
-    );
+/// `list` 子命令：列出筛选后的模板，以及可选的按执行顺序排列的步骤
+fn list_templates(args: &ListArgs, test_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let templates = load_selected_templates(&args.templates, test_dir)?.templates;
+    let listing =
+        TemplateListing::from_templates(&templates, args.steps).map_err(|e| format!("{e:#}"))?;
+    match args.format {
+        ListFormat::Table => print!("{}", listing.to_table()),
+        ListFormat::Json => println!("{}", listing.to_json()?),
+    }
+    Ok(())
+}
 
-    if fail_count > 0 {
-        return Err(format!("{fail_count} tests failed and continue_on_error is false.").into());
+/// `validate` 子命令：校验模板并以 `文件:行号` 的形式输出所有问题，存在错误时返回错误
+fn validate_templates(args: &TemplateArgs, test_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let paths = match args.template.as_ref() {
+        Some(template_file) => vec![template_file.clone()],
+        None => discover_template_paths(test_dir),
+    };
+    let filter = template_filter(args);
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+
+    let (mut validated, mut errors, mut warnings) = (0, 0, 0);
+    for path in paths {
+        let validation = validate_template(&path, test_dir);
+        if !validation.matches_filter(&filter) {
+            continue;
+        }
+        validated += 1;
+        errors += validation.error_count();
+        warnings += validation.warning_count();
+        for diagnostic in validation.diagnostics {
+            // 位于当前目录下的文件显示相对路径
+            let file = diagnostic
+                .file
+                .strip_prefix(&cwd)
+                .map(Path::to_path_buf)
+                .unwrap_or(diagnostic.file);
+            println!("{}", Diagnostic { file, ..diagnostic });
+        }
+    }
+    println!("Validated {validated} templates: {errors} errors, {warnings} warnings");
+    if errors > 0 {
+        return Err(format!("Template validation failed with {errors} errors").into()); // 模板校验发现错误
+    }
+    Ok(())
+}
+
+/// `report` 子命令：查看运行历史
+///
+/// 未指定 `--run` 时列出所有历史运行，否则显示该次运行中各模板的结果；
+/// 指定 `--compare-with` 时输出被查看运行与对比运行之间的差异
+fn show_report(args: &ReportArgs) -> Result<(), Box<dyn Error>> {
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let test_dir = args
+        .test_dir
+        .as_ref()
+        .map_or(cwd.clone(), |dir| cwd.join(dir));
+    let history_path =
+        resolve_reports_dir(args.reports_dir.as_ref(), &test_dir).join(history::HISTORY_FILE_NAME);
+    let history = history::load_history(&history_path).map_err(|e| format!("{e:#}"))?;
+    if history.is_empty() {
+        return Err(format!("No runs recorded in {}", history_path.display()).into());
+    }
+
+    if args.run.is_none() && args.compare_with.is_none() {
+        println!("| Run ID | Recorded At | Templates | Passed | Failed |");
+        println!("|--------|-------------|-----------|--------|--------|");
+        for run in &history {
+            let passed = run.results.iter().filter(|r| r.status == "Pass").count();
+            let failed = run.results.iter().filter(|r| r.status == "Fail").count();
+            println!(
+                "| {} | {} | {} | {} | {} |",
+                run.run_id,
+                run.recorded_at,
+                run.results.len(),
+                passed,
+                failed
+            );
+        }
+        return Ok(());
+    }
+
+    let run = history::find_run(&history, args.run.as_deref().unwrap_or("last"))?;
+    if args.run.is_some() {
+        println!("# Run {} ({})\n", run.run_id, run.recorded_at);
+        println!("| Target | Template ID | Unit | Status |");
+        println!("|--------|-------------|------|--------|");
+        for result in &run.results {
+            println!(
+                "| {} | {} | {} | {} |",
+                result.target, result.template_id, result.unit, result.status
+            );
+        }
     }
 
+    if let Some(baseline_id) = &args.compare_with {
+        // "last" 指被查看运行之前的最近一次运行
+        let position = history
+            .iter()
+            .position(|r| r.run_id == run.run_id)
+            .unwrap_or(history.len());
+        let baseline = history::find_run(&history[..position], baseline_id)?;
+        if args.run.is_some() {
+            println!();
+        }
+        print!("{}", RunComparison::compare(baseline, run).to_markdown());
+    }
     Ok(())
 }
+
+/// 所有批次执行完毕后生成整次运行的汇总报告、JSON 汇总和兼容性矩阵，并记录运行历史
+///
+/// 汇总报告写入 `--output` 指定的路径，未指定时写入报告目录下的 `summary.report.md`；
+/// 指定了 `--compare-with` 时汇总报告中包含与该次运行的对比
+fn write_run_reports(
+    cli_args: &RunArgs,
+    report_dir: &Path,
+    results: &[&ExecutionResult],
+    history: &[RunRecord],
+) {
+    let run_record = RunRecord::from_results(results.iter().copied(), history);
+    let comparison = cli_args
+        .compare_with
+        .as_ref()
+        .and_then(|run_id| history::find_run(history, run_id).ok())
+        .map(|baseline| RunComparison::compare(baseline, &run_record));
+    if let Some(comparison) = &comparison {
+        info!(
+            "Compared with run {}: {} regressions, {} newly passing, {} duration changes",
+            comparison.baseline_run_id,
+            comparison.regressions.len(),
+            comparison.newly_passing.len(),
+            comparison.duration_changes.len()
+        ); // 与历史运行的对比结果
+        for change in &comparison.regressions {
+            warn!(
+                "Regression: {}::{} on {} passed in run {} but failed now",
+                change.template_id, change.step_id, change.target, comparison.baseline_run_id
+            ); // 回归：之前通过的步骤本次失败
+        }
+    }
+
+    let summary_path = cli_args
+        .output
+        .clone()
+        .unwrap_or_else(|| report_dir.join("summary.report.md"));
+    if let Err(e) =
+        write_summary_report(&summary_path, results.iter().copied(), comparison.as_ref())
+    {
+        error!("Failed to write summary report: {e}"); // 写入汇总报告失败
+    }
+    if cli_args.get_report_formats().contains(&ReportFormat::Json) {
+        let json_summary_path = report_dir.join("summary.report.json");
+        if let Err(e) =
+            JsonReport::from_results(results.iter().copied()).write_to(&json_summary_path)
+        {
+            error!("Failed to write JSON summary report: {e}"); // 写入 JSON 汇总报告失败
+        }
+    }
+    let matrix_path = cli_args
+        .matrix_output
+        .clone()
+        .unwrap_or_else(|| report_dir.join("matrix.report.md"));
+    if let Err(e) =
+        CompatibilityMatrix::from_results(results.iter().copied()).write_to(&matrix_path)
+    {
+        error!("Failed to write compatibility matrix: {e}"); // 写入兼容性矩阵失败
+    }
+    if let Err(e) =
+        history::append_history(&report_dir.join(history::HISTORY_FILE_NAME), &run_record)
+    {
+        error!("Failed to record run history: {e}"); // 记录运行历史失败
+    }
+}
+
+/// `--format tap` 时创建输出到标准输出的 TAP 流
+fn tap_stream(cli_args: &RunArgs) -> Option<Arc<TapStream>> {
+    cli_args
+        .get_report_formats()
+        .contains(&ReportFormat::Tap)
+        .then(|| Arc::new(TapStream::stdout()))
+}
+
+/// 合并存在跨模板依赖的分组，保证被引用的模板与引用它的模板在同一个批次中按依赖顺序执行
+///
+/// 引用方模板会并入被引用模板所在的分组，执行时仍使用自己目标配置的执行器选项。
+/// 合并后的模板共享同一批次的执行结果，因此它们之间不能再按 `--jobs` 并行；
+/// 没有依赖关系的分组不受影响
+fn merge_dependent_groups<K: std::fmt::Debug>(
+    mut groups: Vec<(K, Vec<TestTemplate>)>,
+) -> Vec<(K, Vec<TestTemplate>)> {
+    loop {
+        let group_of: HashMap<String, usize> = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(index, (_, templates))| {
+                templates.iter().map(move |t| (t.get_template_id(), index))
+            })
+            .collect();
+        let cross_group = groups
+            .iter()
+            .enumerate()
+            .find_map(|(index, (_, templates))| {
+                templates.iter().find_map(|t| {
+                    t.referenced_template_ids()
+                        .iter()
+                        .filter_map(|id| group_of.get(id))
+                        .find(|&&other| other != index)
+                        .map(|&other| (index, other))
+                })
+            });
+        let Some((from, into)) = cross_group else {
+            return groups;
+        };
+        let (from_key, templates) = groups.remove(from);
+        let into = if into > from { into - 1 } else { into };
+        info!(
+            "Merging batch {:?} into {:?} because of cross-template dependencies",
+            from_key, groups[into].0
+        ); // 因跨模板依赖合并批次
+        groups[into].1.extend(templates);
+    }
+}
+
+/// 并行执行时用于日志前缀的目标名称（`targets/<name>/config.toml` 取目录名）
+fn group_log_prefix(target_config_path: &Path) -> String {
+    let file_stem = target_config_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match target_config_path.parent().and_then(|p| p.file_name()) {
+        Some(dir) if file_stem == "config" => dir.to_string_lossy().into_owned(),
+        _ => file_stem,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_merged_group_keeps_own_executor_options() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        // base 所在的目标顺序执行步骤，app 所在的目标允许两个步骤并行
+        for (target, max_parallel_steps) in [("serial_box", 1), ("parallel_box", 2)] {
+            let config_dir = dir.join("targets").join(target);
+            fs::create_dir_all(&config_dir).unwrap();
+            fs::write(
+                config_dir.join("config.toml"),
+                format!(
+                    "testing_type = \"locally\"\nname = \"{target}\"\ndescription = \"{target}\"\n\n[executor]\nmax_parallel_steps = {max_parallel_steps}\n"
+                ),
+            )
+            .unwrap();
+        }
+        let target_config = |target: &str| {
+            dir.join("targets")
+                .join(target)
+                .join("config.toml")
+                .display()
+                .to_string()
+        };
+        fs::write(
+            dir.join("base.test.md"),
+            format!(
+                "---\ntitle: \"Base\"\ntarget_config: \"{}\"\nunit_name: \"base\"\nunit_version: \"1\"\n---\n\n# Base\n\n```bash {{id=\"calc\" exec=true}}\necho base\n```\n",
+                target_config("serial_box")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("app.test.md"),
+            format!(
+                "---\ntitle: \"App\"\ntarget_config: \"{}\"\nunit_name: \"app\"\nunit_version: \"1\"\nreferences:\n  - template: \"base.test.md\"\n    as: \"base\"\n---\n\n# App\n\n```bash {{id=\"first\" exec=true depends_on=[\"base::calc\"]}}\nsleep 1\n```\n\n```bash {{id=\"second\" exec=true depends_on=[\"base::calc\"]}}\nsleep 1\n```\n",
+                target_config("parallel_box")
+            ),
+        )
+        .unwrap();
+
+        let cli =
+            Cli::try_parse_from(["lintestor", "run", "-D", &dir.display().to_string()]).unwrap();
+        let (Command::Run(args), _) = cli.resolve_command() else {
+            panic!("expected run command");
+        };
+        let start = Instant::now();
+        run_template_tests(&args, dir).unwrap();
+        // app 并入 base 的分组后仍按自己目标的 max_parallel_steps 并行执行
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_millis(1900), "took {elapsed:?}");
+    }
+
+    #[test]
+    fn test_single_template_load_error_is_returned() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let template_file = dir.join("missing.test.md");
+        let cli = Cli::try_parse_from([
+            "lintestor",
+            "run",
+            "--template",
+            &template_file.display().to_string(),
+        ])
+        .unwrap();
+        let (Command::Run(args), _) = cli.resolve_command() else {
+            panic!("expected run command");
+        };
+
+        let err = run_single_template_test(&template_file, &args, dir).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Failed to load template from file")
+        );
+    }
+}
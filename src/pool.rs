@@ -1,13 +1,14 @@
 use crate::config::target_config::TargetConfig;
-use crate::connection::{ConnectionFactory, ConnectionManager};
-use crate::template::ExecutorOptions;
-use anyhow::Result;
+use crate::connection::ConnectionManager;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::path::PathBuf;
 
 /// 连接管理池，复用ConnectionManager实例
 pub struct ConnectionManagerPool {
     pool: HashMap<PathBuf, Box<dyn ConnectionManager>>,
+    /// 并行执行步骤时额外创建的空闲连接
+    idle: HashMap<PathBuf, Vec<Box<dyn ConnectionManager>>>,
 }
 
 impl ConnectionManagerPool {
@@ -15,29 +16,34 @@ impl ConnectionManagerPool {
     pub fn new() -> Self {
         Self {
             pool: HashMap::new(),
+            idle: HashMap::new(),
         }
     }
 
-    /// 获取或创建指定TargetConfig的ConnectionManager
-    pub fn get_or_create(
-        &mut self,
-        config: &TargetConfig,
-        allow_reuse: bool,
-        executor_options: &ExecutorOptions,
-    ) -> Result<&mut Box<dyn ConnectionManager>> {
+    /// 取出指定TargetConfig的一个ConnectionManager（优先取主连接），没有可用连接时返回None
+    ///
+    /// 取出的连接在使用期间归调用方所有，可以移动到其他线程，用完后通过 `put_back` 归还
+    pub fn take(&mut self, config: &TargetConfig) -> Option<Box<dyn ConnectionManager>> {
+        let key = config.get_path();
+        self.pool
+            .remove(key)
+            .or_else(|| self.idle.get_mut(key).and_then(|idle| idle.pop()))
+    }
+
+    /// 归还通过 `take` 取出的ConnectionManager
+    pub fn put_back(&mut self, config: &TargetConfig, manager: Box<dyn ConnectionManager>) {
         let key = config.get_path().clone();
-        if !allow_reuse {
-            // 不允许多次复用，直接创建新连接
-            let manager = ConnectionFactory::create_manager(config, executor_options)?;
-            self.pool.insert(key.clone(), manager);
-        } else if !self.pool.contains_key(&key) {
-            // 允许复用但池中没有，创建新连接
-            let manager = ConnectionFactory::create_manager(config, executor_options)?;
-            self.pool.insert(key.clone(), manager);
+        match self.pool.entry(key) {
+            Entry::Occupied(entry) => {
+                self.idle
+                    .entry(entry.key().clone())
+                    .or_default()
+                    .push(manager);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(manager);
+            }
         }
-        self.pool
-            .get_mut(&key)
-            .ok_or_else(|| anyhow::anyhow!("Failed to get or create ConnectionManager"))
     }
 
     #[allow(dead_code)]
@@ -47,6 +53,7 @@ impl ConnectionManagerPool {
         // 大概会需要做一下HashMap<TargetConfig, i64>的计数工作，现在先不写感觉模板量比较小不需要
         let key = config.get_path();
         self.pool.remove(key);
+        self.idle.remove(key);
     }
 
     #[allow(dead_code)]
@@ -54,5 +61,6 @@ impl ConnectionManagerPool {
     pub fn clear(&mut self) {
         // 虽然还没想到什么时候会用到这个，但是留一个接口总是好的
         self.pool.clear();
+        self.idle.clear();
     }
 }
@@ -3,25 +3,131 @@
 //! 这个模块负责按照依赖关系顺序执行多个测试模板，
 //! 管理代码块级别的依赖并收集执行结果
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use log::{debug, error, info, warn};
 use std::cmp::max;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, mpsc};
+use std::thread;
 use std::time::{Duration, Instant};
 
+use crate::config::cli_args::ReportFormat;
+use crate::config::target_config::TargetConfig;
+use crate::connection::{CommandOutput, ConnectionFactory, ConnectionManager};
 use crate::pool::ConnectionManagerPool;
 use crate::template::dependency::StepDependencyManager;
-use crate::template::executor::{ExecutionResult, check_assertion, extract_variable};
+use crate::template::executor::{
+    ExecutionResult, ExecutorOptions, check_assertion, extract_variable,
+};
+use crate::template::html_report::write_html_report;
+use crate::template::json_report::JsonReport;
+use crate::template::junit_report::write_junit_report;
 use crate::template::reporter::Reporter;
-use crate::template::step::{GlobalStepId, StepType};
+use crate::template::step::{ExecutionStep, GlobalStepId, StepType};
+use crate::template::tap_report::TapStream;
 use crate::template::variable::VariableManager;
 use crate::template::{BatchOptions, StepStatus, TestTemplate};
 use crate::utils;
 use std::io::{self, Write};
 
+/// 步骤执行完成后的结果，用于写入变量和报告
+struct StepOutcome {
+    status: StepStatus,
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+    assertion_error: Option<String>,
+    assertion_status: StepStatus,
+    assertion_statuses: Vec<StepStatus>,
+    assertion_error_msgs: Vec<Option<String>>,
+    /// 跳过原因（例如依赖的步骤失败）
+    skip_reason: Option<String>,
+    /// 执行期间重建连接的记录
+    reconnects: Vec<String>,
+}
+
+impl StepOutcome {
+    /// 未执行命令的步骤（标题、输出占位符、未激活的代码块）
+    fn skipped() -> Self {
+        Self {
+            status: StepStatus::Skipped,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: 0,
+            assertion_error: None,
+            assertion_status: StepStatus::Skipped,
+            assertion_statuses: Vec::new(),
+            assertion_error_msgs: Vec::new(),
+            skip_reason: None,
+            reconnects: Vec::new(),
+        }
+    }
+}
+
+/// 已替换变量、等待在目标上执行的命令
+struct CommandJob {
+    command: String,
+    timeout: Duration,
+}
+
+/// 步骤准备的结果
+enum PreparedStep {
+    /// 不需要执行命令，结果已确定
+    Done(StepOutcome),
+    /// 需要在目标上执行命令
+    Command(CommandJob),
+}
+
+/// 并行工作线程执行完命令后发回的结果
+struct CommandCompletion {
+    step_def: ExecutionStep,
+    start_time: Instant,
+    /// 归还给连接池的连接
+    connection: Option<Box<dyn ConnectionManager>>,
+    /// 外层错误表示连接建立失败，内层错误表示重试后命令仍执行失败
+    result: Result<Result<CommandOutput>>,
+}
+
+/// 在给定连接上执行命令，失败时按执行器选项重试
+///
+/// `connection` 为空或不保持会话时会新建连接。连接建立失败直接返回外层错误。
+fn run_command_with_retry(
+    connection: &mut Option<Box<dyn ConnectionManager>>,
+    target_config: &TargetConfig,
+    executor_options: &ExecutorOptions,
+    job: &CommandJob,
+) -> Result<Result<CommandOutput>> {
+    let mut last_err = None;
+    for attempt in 0..=executor_options.retry_count {
+        // 根据 maintain_session 决定是否复用连接
+        if connection.is_none() || !executor_options.maintain_session {
+            *connection = Some(ConnectionFactory::create_manager(
+                target_config,
+                executor_options,
+            )?);
+        }
+        let Some(current_connection) = connection.as_mut() else {
+            continue;
+        };
+        current_connection.setup()?;
+
+        match current_connection.execute_command(&job.command, Some(job.timeout)) {
+            Ok(output) => return Ok(Ok(output)),
+            Err(e) => {
+                last_err = Some(e);
+                if attempt < executor_options.retry_count {
+                    thread::sleep(Duration::from_secs(executor_options.retry_interval));
+                }
+            }
+        }
+    }
+    Ok(Err(
+        last_err.unwrap_or_else(|| anyhow!("Command was not executed"))
+    ))
+}
+
 /// 批量测试执行器
 pub struct BatchExecutor {
     variable_manager: VariableManager,
@@ -30,9 +136,23 @@ pub struct BatchExecutor {
     executed_step_results: HashMap<GlobalStepId, crate::template::executor::StepResult>,
     templates: HashMap<String, Arc<TestTemplate>>,
     options: Option<BatchOptions>,
+    /// 单独指定了执行器选项的模板（例如因跨模板依赖并入其他目标分组的模板）
+    template_executor_options: HashMap<String, ExecutorOptions>,
     report_dir: Option<PathBuf>,
 }
 
+/// 交互式修正时打印提示并从终端读取一行，读取失败时按跳过处理
+fn read_correction(prompt: &str) -> String {
+    print!("{prompt}");
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_line(&mut input) {
+        warn!("Failed to read input: {e}"); // 读取输入失败
+        input.clear();
+    }
+    input.trim().to_string()
+}
+
 impl BatchExecutor {
     pub fn new(
         variable_manager: VariableManager,
@@ -47,6 +167,7 @@ impl BatchExecutor {
             executed_step_results: HashMap::new(),
             templates: HashMap::new(),
             options,
+            template_executor_options: HashMap::new(),
             report_dir,
         }
     }
@@ -55,6 +176,30 @@ impl BatchExecutor {
         &self.options
     }
 
+    /// 为单个模板指定执行器选项，代替批量选项中的 `executor_options`
+    pub fn set_template_executor_options(&mut self, template_id: &str, options: ExecutorOptions) {
+        self.template_executor_options
+            .insert(template_id.to_string(), options);
+    }
+
+    /// 模板使用的执行器选项：单独指定的选项优先，其次是批量选项
+    fn executor_options(&self, template_id: &str) -> ExecutorOptions {
+        self.template_executor_options
+            .get(template_id)
+            .cloned()
+            .or_else(|| {
+                self.get_options()
+                    .as_ref()
+                    .map(|o| o.executor_options.clone())
+            })
+            .unwrap_or_default()
+    }
+
+    /// 断言或变量提取失败时是否在终端上询问修正值
+    fn is_interactive(&self) -> bool {
+        self.options.as_ref().is_some_and(|o| o.interactive)
+    }
+
     pub fn add_template(&mut self, template: Arc<TestTemplate>) -> Result<(), Box<dyn Error>> {
         let template_id = template.get_template_id();
         // 在这里注册一下模板
@@ -97,7 +242,27 @@ impl BatchExecutor {
 
         self.step_dependency_manager = StepDependencyManager::new();
 
-        let execution_steps_from_template = template_arc.steps.clone();
+        // 依赖其他模板的步骤不进入本模板的依赖图，而是在调度时检查被依赖步骤的执行结果
+        let mut external_dependencies: HashMap<GlobalStepId, Vec<GlobalStepId>> = HashMap::new();
+        let execution_steps_from_template: Vec<ExecutionStep> = template_arc
+            .steps
+            .iter()
+            .cloned()
+            .map(|mut step| {
+                let (local, external): (HashSet<_>, HashSet<_>) =
+                    step.dependencies.drain().partition(|dep| {
+                        dep.split_once("::")
+                            .is_none_or(|(dep_template_id, _)| dep_template_id == template_id)
+                    });
+                if !external.is_empty() {
+                    let mut external: Vec<_> = external.into_iter().collect();
+                    external.sort();
+                    external_dependencies.insert(step.id.clone(), external);
+                }
+                step.dependencies = local;
+                step
+            })
+            .collect();
         if execution_steps_from_template.is_empty() {
             warn!("Template {template_id} has no executable steps.");
             return Ok(ExecutionResult {
@@ -108,25 +273,24 @@ impl BatchExecutor {
                 step_results: HashMap::new(),
                 variables: self.variable_manager.get_all_variables().clone(),
                 report_path: None,
+                error: None,
             });
         }
 
         self.step_dependency_manager
             .add_steps(execution_steps_from_template);
-        self.step_dependency_manager.build_graph();
+        if let Err(e) = self.step_dependency_manager.build_graph() {
+            error!("Failed to resolve dependencies for template {template_id}: {e}"); // 无法解析模板的依赖
+            return Ok(ExecutionResult::failed(template_arc, e.to_string()));
+        }
 
         let execution_order = match self.step_dependency_manager.get_execution_order() {
             Ok(order) => order,
             Err(e) => {
                 error!("Failed to get execution order for template {template_id}: {e}");
                 return Ok(ExecutionResult {
-                    template: template_arc.clone(),
-                    unit_name: template_arc.metadata.unit_name.clone(),
-                    target_name: template_arc.metadata.target_config.get_name().to_string(),
-                    overall_status: StepStatus::Fail,
-                    step_results: HashMap::new(),
                     variables: self.variable_manager.get_all_variables().clone(),
-                    report_path: None,
+                    ..ExecutionResult::failed(template_arc, e)
                 });
             }
         };
@@ -138,491 +302,220 @@ impl BatchExecutor {
             crate::template::executor::StepResult,
         > = HashMap::new();
         let mut template_overall_status = StepStatus::Pass;
-        let continue_on_error = self
-            .options
-            .as_ref()
-            .is_some_and(|o| o.executor_options.continue_on_error);
+        let executor_options = self.executor_options(template_id);
+        let continue_on_error = executor_options.continue_on_error;
 
-        for step_id in execution_order {
-            let step_def = match self.step_dependency_manager.get_step(&step_id) {
-                Some(s) => s,
-                None => {
-                    warn!("Step {step_id} not found in dependency manager, skipping.");
-                    continue;
-                }
-            };
+        let target_config = template_arc.metadata.target_config.clone();
+        let max_parallel_steps = if ConnectionFactory::supports_parallel_sessions(&target_config) {
+            executor_options.max_parallel_steps.max(1)
+        } else {
+            if executor_options.max_parallel_steps > 1 {
+                info!(
+                    "Target type '{}' does not support parallel sessions, executing steps of {template_id} sequentially",
+                    target_config.testing_type
+                );
+            }
+            1
+        };
 
-            let parsed_step_details_opt = step_def.original_parsed_step.as_ref();
-
-            let mut step_status = StepStatus::Pass;
-            let mut stdout_val = String::new();
-            let mut stderr_val = String::new();
-            let mut exit_code_val = 0;
-            let mut assertion_error_msg: Option<String> = None;
-            let mut assertion_status = StepStatus::Skipped;
-            let mut assertion_statuses: Vec<StepStatus> = Vec::new();
-            let mut assertion_error_msgs: Vec<Option<String>> = Vec::new();
-
-            let step_start_time = Instant::now();
-
-            match &step_def.step_type {
-                StepType::CodeBlock {
-                    command: cmd_template,
-                    ..
-                } => {
-                    if let Some(parsed_step_details) = parsed_step_details_opt {
-                        if parsed_step_details.executable
-                            && parsed_step_details.active.unwrap_or(true)
-                        {
-                            let hydrated_command = self.variable_manager.replace_variables(
-                                cmd_template,
-                                Some(&step_def.template_id),
-                                Some(&step_def.local_id),
-                            );
-                            debug!("Executing command for step {step_id}: {hydrated_command}");
-
-                            let target_config = &template_arc.metadata.target_config;
-                            debug!("Executing command on target: {target_config:?}");
-
-                            // 获取执行器选项
-                            let default_options =
-                                crate::template::executor::ExecutorOptions::default();
-                            let executor_options = match &self.options {
-                                Some(opts) => &opts.executor_options,
-                                None => &default_options,
-                            };
-                            let mut last_err = None;
-                            let mut retry_success = false;
-
-                            for attempt in 0..=executor_options.retry_count {
-                                // 根据 maintain_session 决定是否复用连接
-                                let current_connection =
-                                    self.connection_manager_pool.get_or_create(
-                                        target_config,
-                                        executor_options.maintain_session,
-                                        executor_options,
-                                    )?;
-                                current_connection.setup()?;
-
-                                let step_timeout_opt =
-                                    parsed_step_details.timeout_ms.map(Duration::from_millis);
-                                let global_timeout_opt = self
-                                    .options
-                                    .as_ref()
-                                    .map(|o| o.executor_options.command_timeout);
-                                let step_timeout_opt = max(
-                                    step_timeout_opt,
-                                    global_timeout_opt.map(Duration::from_secs),
-                                );
+        // 按拓扑顺序调度：依赖全部完成的步骤即可开始执行，
+        // 最多同时有 max_parallel_steps 条命令在各自的连接上运行
+        let mut pending_steps = execution_order;
+        let mut finished_steps: HashSet<GlobalStepId> = HashSet::new();
+        let mut running_count = 0;
+        let mut stop_scheduling = false;
+        let (completion_tx, completion_rx) = mpsc::channel::<CommandCompletion>();
 
-                                let timeout_duration = step_timeout_opt.unwrap_or(
-                                    Duration::from_secs(executor_options.command_timeout),
-                                );
+        loop {
+            while !stop_scheduling {
+                let next_ready = pending_steps.iter().position(|id| {
+                    self.dependencies_finished(id, &finished_steps)
+                        && (running_count < max_parallel_steps || !self.runs_command(id))
+                });
+                let Some(position) = next_ready else {
+                    break;
+                };
+                let step_id = pending_steps.remove(position);
+                let step_def = match self.step_dependency_manager.get_step(&step_id) {
+                    Some(s) => s.clone(),
+                    None => {
+                        warn!("Step {step_id} not found in dependency manager, skipping.");
+                        finished_steps.insert(step_id);
+                        continue;
+                    }
+                };
+                let step_start_time = Instant::now();
 
-                                match current_connection
-                                    .execute_command(&hydrated_command, Some(timeout_duration))
-                                {
-                                    Ok(output) => {
-                                        retry_success = true;
-                                        stdout_val = output.stdout;
-                                        stderr_val = output.stderr;
-                                        exit_code_val = output.exit_code;
-                                        break;
-                                    }
-                                    Err(e) => {
-                                        last_err = Some(e);
-                                        if attempt < executor_options.retry_count {
-                                            std::thread::sleep(Duration::from_secs(
-                                                executor_options.retry_interval,
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
+                if let Some(reason) = external_dependencies
+                    .get(&step_id)
+                    .and_then(|deps| self.external_dependency_block_reason(deps))
+                {
+                    info!("Skipping step {step_id}: {reason}");
+                    let outcome = StepOutcome {
+                        skip_reason: Some(reason.clone()),
+                        ..StepOutcome::skipped()
+                    };
+                    self.record_step_result(
+                        template_id,
+                        &step_def,
+                        outcome,
+                        step_start_time,
+                        &mut template_overall_status,
+                        &mut current_template_step_results,
+                    );
+                    finished_steps.insert(step_id);
+                    self.skip_dependents(
+                        &step_def,
+                        &reason,
+                        &mut pending_steps,
+                        &mut finished_steps,
+                        &mut template_overall_status,
+                        &mut current_template_step_results,
+                    );
+                    continue;
+                }
 
-                            if !retry_success {
-                                let e = last_err.unwrap();
-                                error!("Command execution failed for step {step_id}: {e}");
-                                continue;
+                match self.prepare_step(&step_id, &step_def, &executor_options) {
+                    PreparedStep::Done(outcome) => {
+                        let status = self.record_step_result(
+                            template_id,
+                            &step_def,
+                            outcome,
+                            step_start_time,
+                            &mut template_overall_status,
+                            &mut current_template_step_results,
+                        );
+                        finished_steps.insert(step_id);
+                        if status == StepStatus::Fail {
+                            self.skip_dependents(
+                                &step_def,
+                                &format!("dependency {} failed", step_def.local_id),
+                                &mut pending_steps,
+                                &mut finished_steps,
+                                &mut template_overall_status,
+                                &mut current_template_step_results,
+                            );
+                        }
+                    }
+                    PreparedStep::Command(job) => {
+                        let mut connection = self.connection_manager_pool.take(&target_config);
+                        if max_parallel_steps <= 1 {
+                            let result = run_command_with_retry(
+                                &mut connection,
+                                &target_config,
+                                &executor_options,
+                                &job,
+                            );
+                            if let Some(connection) = connection {
+                                self.connection_manager_pool
+                                    .put_back(&target_config, connection);
                             }
-
-                            self.variable_manager.set_variable(
-                                &step_def.template_id,
-                                &step_def.local_id,
-                                "stdout",
-                                &stdout_val,
-                            )?;
-                            // OMG 我们又加了一个硬编码。。
-                            // 新增 stdout_summary 变量，取前 5 行，每行不超过 200 字符，合并为单行
-                            let stdout_summary = {
-                                let mut summary = String::new();
-                                for (line_count, line) in stdout_val.lines().enumerate() {
-                                    if line_count >= 5 {
-                                        break;
-                                    }
-                                    if !summary.is_empty() {
-                                        summary.push(' ');
-                                    }
-                                    let line = line.replace(['\n', '\r'], " ");
-                                    if line.len() > 200 {
-                                        summary.push_str(&line[..200]);
-                                        summary.push_str("...");
-                                    } else {
-                                        summary.push_str(&line);
-                                    }
-                                }
-                                if stdout_val.lines().count() > 5 || stdout_val.len() > 200 {
-                                    summary.push_str(" ...");
-                                }
-                                summary
-                            };
-                            self.variable_manager.set_variable(
-                                &step_def.template_id,
-                                &step_def.local_id,
-                                "stdout_summary",
-                                &stdout_summary,
-                            )?;
-                            self.variable_manager.set_variable(
-                                &step_def.template_id,
-                                &step_def.local_id,
-                                "stderr",
-                                &stderr_val,
-                            )?;
-                            // 新增 stderr_summary 变量，取前 5 行，每行不超过 200 字符，合并为单行
-                            let stderr_summary = {
-                                let mut summary = String::new();
-                                for (line_count, line) in stderr_val.lines().enumerate() {
-                                    if line_count >= 5 {
-                                        break;
-                                    }
-                                    if !summary.is_empty() {
-                                        summary.push(' ');
-                                    }
-                                    let line = line.replace(['\n', '\r'], " ");
-                                    if line.len() > 200 {
-                                        summary.push_str(&line[..200]);
-                                        summary.push_str("...");
-                                    } else {
-                                        summary.push_str(&line);
-                                    }
-                                }
-                                if stderr_val.lines().count() > 5 || stderr_val.len() > 200 {
-                                    summary.push_str(" ...");
-                                }
-                                summary
-                            };
-                            self.variable_manager.set_variable(
-                                &step_def.template_id,
-                                &step_def.local_id,
-                                "stderr_summary",
-                                &stderr_summary,
-                            )?;
-
-                            self.variable_manager.set_variable(
-                                &step_def.template_id,
-                                &step_def.local_id,
-                                "exit_code",
-                                &exit_code_val.to_string(),
+                            let status = self.finish_command_step(
+                                template_id,
+                                &step_def,
+                                result?,
+                                step_start_time,
+                                &mut template_overall_status,
+                                &mut current_template_step_results,
                             )?;
-
-                            if !parsed_step_details.assertions.is_empty() {
-                                assertion_status = StepStatus::Pass;
-                                for (idx, assertion_details) in
-                                    parsed_step_details.assertions.iter().enumerate()
-                                {
-                                    let assertion_result = check_assertion(
-                                        assertion_details,
-                                        &stdout_val,
-                                        &stderr_val,
-                                        exit_code_val,
-                                    );
-                                    match assertion_result {
-                                        Ok(_) => {
-                                            assertion_statuses.push(StepStatus::Pass);
-                                            assertion_error_msgs.push(None);
-                                        }
-                                        Err(e) => {
-                                            step_status = StepStatus::Fail;
-                                            assertion_status = StepStatus::Fail;
-                                            assertion_statuses.push(StepStatus::Fail);
-                                            assertion_error_msgs.push(Some(e.to_string()));
-                                            error!(
-                                                "Assertion {idx} failed for step {step_id}: {e}"
-                                            );
-                                            if !self.get_options().as_ref().is_some_and(|o| {
-                                                o.executor_options.continue_on_error
-                                            }) {
-                                                info!(
-                                                    "Assertion failed, please input a new value (or press Enter to skip):"
-                                                );
-                                                loop {
-                                                    print!(
-                                                        "Enter new value for assertion (or Enter to skip): "
-                                                    );
-                                                    io::stdout().flush().unwrap();
-                                                    let mut new_val = String::new();
-                                                    io::stdin().read_line(&mut new_val).unwrap();
-                                                    let new_val = new_val.trim();
-                                                    if new_val.is_empty() {
-                                                        info!(
-                                                            "User chose to skip assertion correction."
-                                                        );
-                                                        break;
-                                                    }
-                                                    // 根据断言类型构造新断言
-                                                    use crate::template::AssertionType;
-                                                    let new_assertion = match assertion_details {
-                                                        AssertionType::ExitCode(_) => {
-                                                            match new_val.parse::<i32>() {
-                                                                Ok(code) => {
-                                                                    AssertionType::ExitCode(code)
-                                                                }
-                                                                Err(_) => {
-                                                                    warn!(
-                                                                        "Invalid exit code, must be integer"
-                                                                    );
-                                                                    continue;
-                                                                }
-                                                            }
-                                                        }
-                                                        AssertionType::StdoutContains(_) => {
-                                                            AssertionType::StdoutContains(
-                                                                new_val.to_string(),
-                                                            )
-                                                        }
-                                                        AssertionType::StdoutNotContains(_) => {
-                                                            AssertionType::StdoutNotContains(
-                                                                new_val.to_string(),
-                                                            )
-                                                        }
-                                                        AssertionType::StdoutMatches(_) => {
-                                                            AssertionType::StdoutMatches(
-                                                                new_val.to_string(),
-                                                            )
-                                                        }
-                                                        AssertionType::StderrContains(_) => {
-                                                            AssertionType::StderrContains(
-                                                                new_val.to_string(),
-                                                            )
-                                                        }
-                                                        AssertionType::StderrNotContains(_) => {
-                                                            AssertionType::StderrNotContains(
-                                                                new_val.to_string(),
-                                                            )
-                                                        }
-                                                        AssertionType::StderrMatches(_) => {
-                                                            AssertionType::StderrMatches(
-                                                                new_val.to_string(),
-                                                            )
-                                                        }
-                                                    };
-                                                    match check_assertion(
-                                                        &new_assertion,
-                                                        &stdout_val,
-                                                        &stderr_val,
-                                                        exit_code_val,
-                                                    ) {
-                                                        Ok(_) => {
-                                                            assertion_statuses.pop();
-                                                            assertion_error_msgs.pop();
-                                                            assertion_statuses
-                                                                .push(StepStatus::Pass);
-                                                            assertion_error_msgs.push(None);
-                                                            info!(
-                                                                "Assertion passed with user-provided value."
-                                                            );
-                                                            break;
-                                                        }
-                                                        Err(e) => {
-                                                            warn!("Still failed: {e}");
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            } else {
-                                assertion_statuses.clear();
-                                assertion_error_msgs.clear();
-                            }
-
-                            if step_status == StepStatus::Pass
-                                && !parsed_step_details.extractions.is_empty()
-                            {
-                                for extraction_rule in &parsed_step_details.extractions {
-                                    // 不知道为什么 ruyi 喜欢把一些信息打到 stderr 里，为了匹配先拼起来
-                                    // 以后可以改成 extract 里可以指定 stdout 和 stderr 或者 both(concat)
-                                    match extract_variable(
-                                        &format!("{stdout_val}\n{stderr_val}"),
-                                        &extraction_rule.regex,
-                                    ) {
-                                        Ok(var_value) => {
-                                            debug!(
-                                                "Extracted variable {}={} for step {}",
-                                                extraction_rule.variable, var_value, step_id
-                                            );
-                                            self.variable_manager.set_variable(
-                                                &step_def.template_id,
-                                                &step_def.local_id,
-                                                &extraction_rule.variable,
-                                                &var_value,
-                                            )?;
-                                        }
-                                        Err(e) => {
-                                            warn!(
-                                                "Failed to extract variable '{}' for step {}: {}",
-                                                extraction_rule.variable, step_id, e
-                                            );
-                                            debug!("Extraction rule: {extraction_rule:?}");
-                                            debug!(
-                                                "Command output: \n{}",
-                                                &format!("{stdout_val}\n{stderr_val}")
-                                            );
-                                            info!(
-                                                "Extraction failed for variable '{}', please input a new regex (or empty to skip):",
-                                                extraction_rule.variable
-                                            );
-                                            loop {
-                                                print!(
-                                                    "Enter new regex for '{}': ",
-                                                    extraction_rule.variable
-                                                );
-                                                io::stdout().flush().unwrap();
-                                                let mut new_regex = String::new();
-                                                io::stdin().read_line(&mut new_regex).unwrap();
-                                                let new_regex = new_regex.trim();
-                                                if new_regex.is_empty() {
-                                                    info!(
-                                                        "User chose to skip extraction for '{}'.",
-                                                        extraction_rule.variable
-                                                    );
-                                                    break;
-                                                }
-                                                match extract_variable(
-                                                    &format!("{stdout_val}\n{stderr_val}"),
-                                                    new_regex,
-                                                ) {
-                                                    Ok(var_value) => {
-                                                        debug!(
-                                                            "Extracted variable {}={} for step {} (user provided regex)",
-                                                            extraction_rule.variable,
-                                                            var_value,
-                                                            step_id
-                                                        );
-                                                        self.variable_manager.set_variable(
-                                                            &step_def.template_id,
-                                                            &step_def.local_id,
-                                                            &extraction_rule.variable,
-                                                            &var_value,
-                                                        )?;
-                                                        break;
-                                                    }
-                                                    Err(e) => {
-                                                        warn!(
-                                                            "Still failed to extract variable '{}': {}",
-                                                            extraction_rule.variable, e
-                                                        );
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+                            finished_steps.insert(step_id);
+                            if status == StepStatus::Fail {
+                                self.skip_dependents(
+                                    &step_def,
+                                    &format!("dependency {} failed", step_def.local_id),
+                                    &mut pending_steps,
+                                    &mut finished_steps,
+                                    &mut template_overall_status,
+                                    &mut current_template_step_results,
+                                );
                             }
                         } else {
-                            info!(
-                                "Step {step_id} is inactive or not executable, skipping execution."
-                            );
-                            step_status = StepStatus::Skipped;
-                            assertion_status = StepStatus::Skipped;
+                            debug!("Dispatching step {step_id} to a parallel worker");
+                            running_count += 1;
+                            let completion_tx = completion_tx.clone();
+                            let target_config = target_config.clone();
+                            let executor_options = executor_options.clone();
+                            thread::spawn(move || {
+                                let result = run_command_with_retry(
+                                    &mut connection,
+                                    &target_config,
+                                    &executor_options,
+                                    &job,
+                                );
+                                // 接收端已关闭说明模板执行已中止，直接丢弃结果
+                                let _ = completion_tx.send(CommandCompletion {
+                                    step_def,
+                                    start_time: step_start_time,
+                                    connection,
+                                    result,
+                                });
+                            });
                         }
-                    } else {
-                        error!(
-                            "CodeBlock step {step_id} is missing original parsed details. Cannot execute."
-                        );
-                        step_status = StepStatus::Fail;
-                        assertion_status = StepStatus::Fail;
-                        stderr_val =
-                            format!("Internal error: CodeBlock {step_id} missing parsed details.");
-                        assertion_error_msg = Some(stderr_val.clone());
                     }
                 }
-                StepType::Heading { .. } => {
-                    info!("Skipping execution for heading step: {step_id}");
-                    step_status = StepStatus::Skipped;
-                    assertion_status = StepStatus::Skipped;
-                }
-                StepType::OutputPlaceholder => {
-                    // OutputPlaceholder steps are handled by the reporter, not executed here.
-                    info!("Skipping execution for OutputPlaceholder step: {step_id}");
-                    step_status = StepStatus::Skipped;
-                    assertion_status = StepStatus::Skipped;
+
+                if template_overall_status == StepStatus::Fail && !continue_on_error {
+                    info!(
+                        "Stopping execution of template {template_id} due to step failure and continue_on_error=false."
+                    );
+                    stop_scheduling = true;
                 }
             }
 
-            let duration_ms = step_start_time.elapsed().as_millis();
-
-            if step_status == StepStatus::Fail {
-                template_overall_status = StepStatus::Fail;
+            if running_count == 0 {
+                break;
             }
 
-            let _ = self.variable_manager.set_variable(
-                &step_def.template_id,
-                &step_def.local_id,
-                "status.execution",
-                step_status.as_str(),
-            );
-
-            let exec_step_result = crate::template::executor::StepResult {
-                id: step_def.local_id.clone(),
-                description: Some(step_def.description()),
-                status: step_status,
-                stdout: stdout_val,
-                stderr: stderr_val,
-                exit_code: exit_code_val,
-                duration_ms: Some(duration_ms),
-                assertion_error: assertion_error_msg,
-            };
-            current_template_step_results.insert(
-                utils::get_result_id(template_id, step_def.local_id.as_str()),
-                exec_step_result.clone(),
-            );
-            self.executed_step_results
-                .insert(step_id.clone(), exec_step_result);
-
-            // 注册断言状态变量（整体）
-            let _ = self.variable_manager.set_variable(
-                &step_def.template_id,
-                &step_def.local_id,
-                "status.assertion",
-                assertion_status.as_str(),
-            );
-            // 注册每个断言的状态和错误信息
-            for (idx, status) in assertion_statuses.iter().enumerate() {
-                let var_name = format!("status.assertion.{idx}");
-                let _ = self.variable_manager.set_variable(
-                    &step_def.template_id,
-                    &step_def.local_id,
-                    &var_name,
-                    status.as_str(),
+            // 等待任意一个正在运行的命令完成
+            let completion = completion_rx
+                .recv()
+                .map_err(|e| anyhow!("Parallel step worker disconnected: {}", e))?;
+            running_count -= 1;
+            if let Some(connection) = completion.connection {
+                self.connection_manager_pool
+                    .put_back(&target_config, connection);
+            }
+            let status = self.finish_command_step(
+                template_id,
+                &completion.step_def,
+                completion.result?,
+                completion.start_time,
+                &mut template_overall_status,
+                &mut current_template_step_results,
+            )?;
+            finished_steps.insert(completion.step_def.id.clone());
+            if status == StepStatus::Fail {
+                self.skip_dependents(
+                    &completion.step_def,
+                    &format!("dependency {} failed", completion.step_def.local_id),
+                    &mut pending_steps,
+                    &mut finished_steps,
+                    &mut template_overall_status,
+                    &mut current_template_step_results,
                 );
-                if let Some(Some(err_msg)) = assertion_error_msgs.get(idx) {
-                    let err_var_name = format!("assertion_error.{idx}");
-                    let _ = self.variable_manager.set_variable(
-                        &step_def.template_id,
-                        &step_def.local_id,
-                        &err_var_name,
-                        err_msg,
-                    );
-                }
             }
 
-            if template_overall_status == StepStatus::Fail && !continue_on_error {
+            if template_overall_status == StepStatus::Fail && !continue_on_error && !stop_scheduling
+            {
                 info!(
                     "Stopping execution of template {template_id} due to step failure and continue_on_error=false."
                 );
-                break;
+                stop_scheduling = true;
+            }
+        }
+
+        // 因执行中止而没有运行的步骤也要出现在 TAP 流中
+        if let Some(tap_stream) = self.tap_stream() {
+            for step in template_arc
+                .steps
+                .iter()
+                .filter(|s| TapStream::is_test_step(s))
+            {
+                if !current_template_step_results
+                    .contains_key(&utils::get_result_id(template_id, &step.local_id))
+                {
+                    tap_stream.emit(step, None);
+                }
             }
         }
 
@@ -647,13 +540,21 @@ impl BatchExecutor {
                     None
                 }
             },
+            error: None,
         };
 
         if let Some(report_dir_path) = self.report_dir.as_ref() {
-            let test_dir = self.options.clone().unwrap().test_directory;
-            let reporter = Reporter::new(test_dir, Some(report_dir_path.clone()));
-            match reporter.generate_report(&template_arc, &execution_result, &self.variable_manager)
-            {
+            let test_dir = self.options.as_ref().and_then(|o| o.test_directory.clone());
+            let report = Reporter::new(test_dir, Some(report_dir_path.clone()))
+                .map_err(anyhow::Error::from)
+                .and_then(|reporter| {
+                    reporter.generate_report(
+                        &template_arc,
+                        &execution_result,
+                        &self.variable_manager,
+                    )
+                });
+            match report {
                 Ok(path) => {
                     info!("Report generated for {template_id}: {path:?}");
                     execution_result.report_path = Some(path);
@@ -662,6 +563,35 @@ impl BatchExecutor {
                     error!("Failed to generate report for {template_id}: {e}");
                 }
             }
+            if self.report_format_enabled(ReportFormat::Json)
+                && let Some(report_path) = execution_result.report_path.as_ref()
+            {
+                let json_path = report_path.with_extension("json");
+                if let Err(e) = JsonReport::from_results([&execution_result]).write_to(&json_path) {
+                    error!("Failed to generate JSON report for {template_id}: {e}"); // 生成 JSON 报告失败
+                }
+            }
+            if self.report_format_enabled(ReportFormat::Junit)
+                && let Some(report_path) = execution_result.report_path.as_ref()
+            {
+                let junit_path = report_path.with_extension("junit.xml");
+                if let Err(e) = write_junit_report(&junit_path, [&execution_result]) {
+                    error!("Failed to generate JUnit report for {template_id}: {e}"); // 生成 JUnit 报告失败
+                }
+            }
+            if self.report_format_enabled(ReportFormat::Html)
+                && let Some(report_path) = execution_result.report_path.as_ref()
+            {
+                let html_path = report_path.with_extension("html");
+                if let Err(e) = write_html_report(
+                    &html_path,
+                    &template_arc,
+                    &execution_result,
+                    &self.variable_manager,
+                ) {
+                    error!("Failed to generate HTML report for {template_id}: {e}"); // 生成 HTML 报告失败
+                }
+            }
         } else {
             warn!(
                 "Report directory not configured. Skipping report generation for template {template_id}."
@@ -671,100 +601,1008 @@ impl BatchExecutor {
         Ok(execution_result)
     }
 
-    pub fn execute_all(&mut self) -> Result<Vec<ExecutionResult>> {
-        let mut all_results = Vec::new();
-        let all_template_ids: Vec<String> = self.templates.keys().cloned().collect();
+    /// 是否需要生成指定格式的报告
+    fn report_format_enabled(&self, format: ReportFormat) -> bool {
+        self.options
+            .as_ref()
+            .is_some_and(|o| o.report_formats.contains(&format))
+    }
 
-        if all_template_ids.is_empty() {
-            info!("No templates to execute.");
-            return Ok(all_results);
-        }
+    /// `--format tap` 时共享的 TAP 输出流
+    fn tap_stream(&self) -> Option<&TapStream> {
+        self.options.as_ref().and_then(|o| o.tap_stream.as_deref())
+    }
 
-        for template_id in &all_template_ids {
-            match self.execute(template_id) {
-                Ok(result) => {
-                    all_results.push(result);
+    /// 检查跨模板依赖：被依赖的步骤失败、因依赖失败被跳过或尚未执行时返回跳过原因
+    fn external_dependency_block_reason(&self, dependencies: &[GlobalStepId]) -> Option<String> {
+        dependencies
+            .iter()
+            .find_map(|dep| match self.executed_step_results.get(dep) {
+                None => Some(format!("dependency {dep} was not executed")),
+                Some(result)
+                    if result.status == StepStatus::Fail || result.skip_reason.is_some() =>
+                {
+                    Some(format!("dependency {dep} failed"))
                 }
-                Err(e) => {
+                Some(_) => None,
+            })
+    }
+
+    /// 步骤的所有依赖是否都已执行完毕
+    fn dependencies_finished(
+        &self,
+        step_id: &GlobalStepId,
+        finished_steps: &HashSet<GlobalStepId>,
+    ) -> bool {
+        self.step_dependency_manager
+            .get_dependencies(step_id)
+            .is_none_or(|deps| deps.iter().all(|dep| finished_steps.contains(dep)))
+    }
+
+    /// 步骤是否需要在目标上执行命令（用于判断是否占用并行名额）
+    fn runs_command(&self, step_id: &GlobalStepId) -> bool {
+        self.step_dependency_manager
+            .get_step(step_id)
+            .and_then(|s| match &s.step_type {
+                StepType::CodeBlock { .. } => s.original_parsed_step.as_ref(),
+                _ => None,
+            })
+            .is_some_and(|p| p.executable && p.active.unwrap_or(true))
+    }
+
+    /// 准备步骤：不需要执行命令的步骤直接得到结果，代码块则替换变量并计算超时
+    fn prepare_step(
+        &self,
+        step_id: &GlobalStepId,
+        step_def: &ExecutionStep,
+        executor_options: &ExecutorOptions,
+    ) -> PreparedStep {
+        match &step_def.step_type {
+            StepType::CodeBlock {
+                command: cmd_template,
+                ..
+            } => {
+                let Some(parsed_step_details) = step_def.original_parsed_step.as_ref() else {
                     error!(
-                        "Failed to execute template {template_id}: {e}. This error will be part of the summary if possible."
+                        "CodeBlock step {step_id} is missing original parsed details. Cannot execute."
                     );
-                    // Consider creating a synthetic ExecutionResult for failed templates if needed for summary
+                    let stderr =
+                        format!("Internal error: CodeBlock {step_id} missing parsed details.");
+                    return PreparedStep::Done(StepOutcome {
+                        status: StepStatus::Fail,
+                        assertion_status: StepStatus::Fail,
+                        assertion_error: Some(stderr.clone()),
+                        stderr,
+                        ..StepOutcome::skipped()
+                    });
+                };
+                if !(parsed_step_details.executable && parsed_step_details.active.unwrap_or(true)) {
+                    info!("Step {step_id} is inactive or not executable, skipping execution.");
+                    return PreparedStep::Done(StepOutcome::skipped());
                 }
+
+                let hydrated_command = self.variable_manager.replace_variables(
+                    cmd_template,
+                    Some(&step_def.template_id),
+                    Some(&step_def.local_id),
+                );
+                debug!("Executing command for step {step_id}: {hydrated_command}");
+
+                let step_timeout_opt = parsed_step_details.timeout_ms.map(Duration::from_millis);
+                let global_timeout_opt = self
+                    .options
+                    .as_ref()
+                    .map(|_| executor_options.command_timeout);
+                let step_timeout_opt = max(
+                    step_timeout_opt,
+                    global_timeout_opt.map(Duration::from_secs),
+                );
+                let timeout = step_timeout_opt
+                    .unwrap_or(Duration::from_secs(executor_options.command_timeout));
+
+                PreparedStep::Command(CommandJob {
+                    command: hydrated_command,
+                    timeout,
+                })
+            }
+            StepType::Heading { .. } => {
+                info!("Skipping execution for heading step: {step_id}");
+                PreparedStep::Done(StepOutcome::skipped())
+            }
+            StepType::OutputPlaceholder => {
+                // OutputPlaceholder steps are handled by the reporter, not executed here.
+                info!("Skipping execution for OutputPlaceholder step: {step_id}");
+                PreparedStep::Done(StepOutcome::skipped())
             }
         }
+    }
 
-        if let Some(report_dir_path) = self.report_dir.as_ref() {
-            info!("Generating summary report for all executed templates...");
-            if !all_results.is_empty() {
-                let summary_file_name = format!("{}.{}", "summary", "report.md");
-                let summary_file_path = report_dir_path.join(summary_file_name);
-                let mut summary_content = format!(
-                    "# Test Execution Summary ({})\n\n",
-                    chrono::Local::now().to_rfc3339()
+    /// 处理命令执行结果：写入输出变量、检查断言、提取变量并记录步骤结果
+    ///
+    /// 返回记录的步骤状态
+    fn finish_command_step(
+        &mut self,
+        template_id: &str,
+        step_def: &ExecutionStep,
+        result: Result<CommandOutput>,
+        start_time: Instant,
+        template_overall_status: &mut StepStatus,
+        step_results: &mut HashMap<String, crate::template::executor::StepResult>,
+    ) -> Result<StepStatus, Box<dyn Error>> {
+        let step_id = &step_def.id;
+        let output = match result {
+            Ok(output) => output,
+            Err(e) => {
+                error!(
+                    "Command execution failed for step {}: {e}",
+                    step_def.display_name()
                 );
-                summary_content.push_str("| Template ID | Overall Status | Steps Passed | Steps Failed | Steps Skipped | Steps Blocked | Steps Not Run | Report File |\n");
-                summary_content.push_str("|-------------|----------------|--------------|--------------|---------------|---------------|---------------|-------------|\n");
-
-                for result in &all_results {
-                    let mut passed = 0;
-                    let mut failed = 0;
-                    let mut skipped = 0;
-                    let mut blocked = 0;
-                    let mut not_run = 0;
-                    for step_result in result.step_results.values() {
-                        match step_result.status {
-                            StepStatus::Pass => passed += 1,
-                            StepStatus::Fail => failed += 1,
-                            StepStatus::Skipped => skipped += 1,
-                            StepStatus::Blocked => blocked += 1,
-                            StepStatus::NotRun => not_run += 1,
+                let message = format!("Command execution failed: {e}");
+                let outcome = StepOutcome {
+                    status: StepStatus::Fail,
+                    exit_code: -1,
+                    assertion_status: StepStatus::Fail,
+                    assertion_error: Some(message.clone()),
+                    stderr: message,
+                    ..StepOutcome::skipped()
+                };
+                return Ok(self.record_step_result(
+                    template_id,
+                    step_def,
+                    outcome,
+                    start_time,
+                    template_overall_status,
+                    step_results,
+                ));
+            }
+        };
+        let Some(parsed_step_details) = step_def.original_parsed_step.as_ref() else {
+            return Ok(StepStatus::Skipped);
+        };
+
+        let mut step_status = StepStatus::Pass;
+        let stdout_val = output.stdout;
+        let stderr_val = output.stderr;
+        let exit_code_val = output.exit_code;
+        let mut assertion_status = StepStatus::Skipped;
+        let mut assertion_statuses: Vec<StepStatus> = Vec::new();
+        let mut assertion_error_msgs: Vec<Option<String>> = Vec::new();
+
+        self.variable_manager.set_variable(
+            &step_def.template_id,
+            &step_def.local_id,
+            "stdout",
+            &stdout_val,
+        )?;
+        // OMG 我们又加了一个硬编码。。
+        // 新增 stdout_summary 变量，取前 5 行，每行不超过 200 字符，合并为单行
+        let stdout_summary = {
+            let mut summary = String::new();
+            for (line_count, line) in stdout_val.lines().enumerate() {
+                if line_count >= 5 {
+                    break;
+                }
+                if !summary.is_empty() {
+                    summary.push(' ');
+                }
+                let line = line.replace(['\n', '\r'], " ");
+                if line.len() > 200 {
+                    summary.push_str(&line[..200]);
+                    summary.push_str("...");
+                } else {
+                    summary.push_str(&line);
+                }
+            }
+            if stdout_val.lines().count() > 5 || stdout_val.len() > 200 {
+                summary.push_str(" ...");
+            }
+            summary
+        };
+        self.variable_manager.set_variable(
+            &step_def.template_id,
+            &step_def.local_id,
+            "stdout_summary",
+            &stdout_summary,
+        )?;
+        self.variable_manager.set_variable(
+            &step_def.template_id,
+            &step_def.local_id,
+            "stderr",
+            &stderr_val,
+        )?;
+        // 新增 stderr_summary 变量，取前 5 行，每行不超过 200 字符，合并为单行
+        let stderr_summary = {
+            let mut summary = String::new();
+            for (line_count, line) in stderr_val.lines().enumerate() {
+                if line_count >= 5 {
+                    break;
+                }
+                if !summary.is_empty() {
+                    summary.push(' ');
+                }
+                let line = line.replace(['\n', '\r'], " ");
+                if line.len() > 200 {
+                    summary.push_str(&line[..200]);
+                    summary.push_str("...");
+                } else {
+                    summary.push_str(&line);
+                }
+            }
+            if stderr_val.lines().count() > 5 || stderr_val.len() > 200 {
+                summary.push_str(" ...");
+            }
+            summary
+        };
+        self.variable_manager.set_variable(
+            &step_def.template_id,
+            &step_def.local_id,
+            "stderr_summary",
+            &stderr_summary,
+        )?;
+
+        self.variable_manager.set_variable(
+            &step_def.template_id,
+            &step_def.local_id,
+            "exit_code",
+            &exit_code_val.to_string(),
+        )?;
+
+        if !parsed_step_details.assertions.is_empty() {
+            assertion_status = StepStatus::Pass;
+            for (idx, assertion_details) in parsed_step_details.assertions.iter().enumerate() {
+                let assertion_result =
+                    check_assertion(assertion_details, &stdout_val, &stderr_val, exit_code_val);
+                match assertion_result {
+                    Ok(_) => {
+                        assertion_statuses.push(StepStatus::Pass);
+                        assertion_error_msgs.push(None);
+                    }
+                    Err(e) => {
+                        step_status = StepStatus::Fail;
+                        assertion_status = StepStatus::Fail;
+                        assertion_statuses.push(StepStatus::Fail);
+                        assertion_error_msgs.push(Some(e.to_string()));
+                        error!(
+                            "Assertion {idx} failed for step {}: {e}",
+                            step_def.display_name()
+                        );
+                        if self.is_interactive()
+                            && !self.executor_options(template_id).continue_on_error
+                        {
+                            info!(
+                                "Assertion failed, please input a new value (or press Enter to skip):"
+                            );
+                            loop {
+                                let new_val = read_correction(
+                                    "Enter new value for assertion (or Enter to skip): ",
+                                );
+                                let new_val = new_val.as_str();
+                                if new_val.is_empty() {
+                                    info!("User chose to skip assertion correction.");
+                                    break;
+                                }
+                                // 根据断言类型构造新断言
+                                use crate::template::AssertionType;
+                                let new_assertion = match assertion_details {
+                                    AssertionType::ExitCode(_) => match new_val.parse::<i32>() {
+                                        Ok(code) => AssertionType::ExitCode(code),
+                                        Err(_) => {
+                                            warn!("Invalid exit code, must be integer");
+                                            continue;
+                                        }
+                                    },
+                                    AssertionType::StdoutContains(_) => {
+                                        AssertionType::StdoutContains(new_val.to_string())
+                                    }
+                                    AssertionType::StdoutNotContains(_) => {
+                                        AssertionType::StdoutNotContains(new_val.to_string())
+                                    }
+                                    AssertionType::StdoutMatches(_) => {
+                                        AssertionType::StdoutMatches(new_val.to_string())
+                                    }
+                                    AssertionType::StderrContains(_) => {
+                                        AssertionType::StderrContains(new_val.to_string())
+                                    }
+                                    AssertionType::StderrNotContains(_) => {
+                                        AssertionType::StderrNotContains(new_val.to_string())
+                                    }
+                                    AssertionType::StderrMatches(_) => {
+                                        AssertionType::StderrMatches(new_val.to_string())
+                                    }
+                                };
+                                match check_assertion(
+                                    &new_assertion,
+                                    &stdout_val,
+                                    &stderr_val,
+                                    exit_code_val,
+                                ) {
+                                    Ok(_) => {
+                                        assertion_statuses.pop();
+                                        assertion_error_msgs.pop();
+                                        assertion_statuses.push(StepStatus::Pass);
+                                        assertion_error_msgs.push(None);
+                                        info!("Assertion passed with user-provided value.");
+                                        break;
+                                    }
+                                    Err(e) => {
+                                        warn!("Still failed: {e}");
+                                    }
+                                }
+                            }
                         }
                     }
-                    let report_link = result
-                        .report_path
-                        .as_ref()
-                        .map(|p| {
-                            p.file_name()
-                                .unwrap_or_default()
-                                .to_string_lossy()
-                                .into_owned()
-                        })
-                        .unwrap_or_else(|| "N/A".to_string());
-
-                    summary_content.push_str(&format!(
-                        "| {} | {:?} | {} | {} | {} | {} | {} | {} |\n",
-                        result.template.get_template_id(),
-                        result.overall_status,
-                        passed,
-                        failed,
-                        skipped,
-                        blocked,
-                        not_run,
-                        report_link
-                    ));
                 }
+            }
+        } else {
+            assertion_statuses.clear();
+            assertion_error_msgs.clear();
+        }
 
-                match std::fs::write(&summary_file_path, summary_content) {
-                    Ok(_) => info!(
-                        "Summary report generated at: {}",
-                        summary_file_path.display()
-                    ),
-                    Err(e) => error!(
-                        "Failed to write summary report {}: {}",
-                        summary_file_path.display(),
-                        e
-                    ),
+        if step_status == StepStatus::Pass && !parsed_step_details.extractions.is_empty() {
+            for extraction_rule in &parsed_step_details.extractions {
+                // 不知道为什么 ruyi 喜欢把一些信息打到 stderr 里，为了匹配先拼起来
+                // 以后可以改成 extract 里可以指定 stdout 和 stderr 或者 both(concat)
+                match extract_variable(
+                    &format!("{stdout_val}\n{stderr_val}"),
+                    &extraction_rule.regex,
+                ) {
+                    Ok(var_value) => {
+                        debug!(
+                            "Extracted variable {}={} for step {}",
+                            extraction_rule.variable, var_value, step_id
+                        );
+                        self.variable_manager.set_variable(
+                            &step_def.template_id,
+                            &step_def.local_id,
+                            &extraction_rule.variable,
+                            &var_value,
+                        )?;
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to extract variable '{}' for step {}: {}",
+                            extraction_rule.variable, step_id, e
+                        );
+                        debug!("Extraction rule: {extraction_rule:?}");
+                        debug!(
+                            "Command output: \n{}",
+                            &format!("{stdout_val}\n{stderr_val}")
+                        );
+                        if !self.is_interactive() {
+                            continue;
+                        }
+                        info!(
+                            "Extraction failed for variable '{}', please input a new regex (or empty to skip):",
+                            extraction_rule.variable
+                        );
+                        loop {
+                            let new_regex = read_correction(&format!(
+                                "Enter new regex for '{}': ",
+                                extraction_rule.variable
+                            ));
+                            let new_regex = new_regex.as_str();
+                            if new_regex.is_empty() {
+                                info!(
+                                    "User chose to skip extraction for '{}'.",
+                                    extraction_rule.variable
+                                );
+                                break;
+                            }
+                            match extract_variable(
+                                &format!("{stdout_val}\n{stderr_val}"),
+                                new_regex,
+                            ) {
+                                Ok(var_value) => {
+                                    debug!(
+                                        "Extracted variable {}={} for step {} (user provided regex)",
+                                        extraction_rule.variable, var_value, step_id
+                                    );
+                                    self.variable_manager.set_variable(
+                                        &step_def.template_id,
+                                        &step_def.local_id,
+                                        &extraction_rule.variable,
+                                        &var_value,
+                                    )?;
+                                    break;
+                                }
+                                Err(e) => {
+                                    warn!(
+                                        "Still failed to extract variable '{}': {}",
+                                        extraction_rule.variable, e
+                                    );
+                                }
+                            }
+                        }
+                    }
                 }
-            } else {
+            }
+        }
+
+        // 汇总失败断言的错误信息（每条一行），供各格式的报告使用
+        let failed_assertions: Vec<&str> = assertion_error_msgs
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        let assertion_error = (!failed_assertions.is_empty()).then(|| failed_assertions.join("\n"));
+
+        let outcome = StepOutcome {
+            status: step_status,
+            stdout: stdout_val,
+            stderr: stderr_val,
+            exit_code: exit_code_val,
+            assertion_error,
+            assertion_status,
+            assertion_statuses,
+            assertion_error_msgs,
+            skip_reason: None,
+            reconnects: output.reconnects,
+        };
+        Ok(self.record_step_result(
+            template_id,
+            step_def,
+            outcome,
+            start_time,
+            template_overall_status,
+            step_results,
+        ))
+    }
+
+    /// 将失败（或因跨模板依赖失败而跳过）步骤的所有（传递）依赖者标记为跳过，并记录跳过原因
+    ///
+    /// 只处理尚未开始执行的步骤；与失败步骤无关的分支不受影响。
+    /// 只有会执行命令的代码块记录跳过原因，标题等步骤和平时一样直接跳过
+    fn skip_dependents(
+        &mut self,
+        failed_step: &ExecutionStep,
+        reason: &str,
+        pending_steps: &mut Vec<GlobalStepId>,
+        finished_steps: &mut HashSet<GlobalStepId>,
+        template_overall_status: &mut StepStatus,
+        step_results: &mut HashMap<String, crate::template::executor::StepResult>,
+    ) {
+        let mut queue: VecDeque<GlobalStepId> = self
+            .step_dependency_manager
+            .get_dependents(&failed_step.id)
+            .map(|deps| deps.iter().cloned().collect())
+            .unwrap_or_default();
+
+        while let Some(dependent_id) = queue.pop_front() {
+            let Some(position) = pending_steps.iter().position(|id| *id == dependent_id) else {
+                continue;
+            };
+            pending_steps.remove(position);
+            let Some(step_def) = self
+                .step_dependency_manager
+                .get_step(&dependent_id)
+                .cloned()
+            else {
+                continue;
+            };
+            let skip_reason = self.runs_command(&dependent_id).then(|| reason.to_string());
+            if skip_reason.is_some() {
+                info!("Skipping step {dependent_id}: {reason}");
+            }
+            let outcome = StepOutcome {
+                skip_reason,
+                ..StepOutcome::skipped()
+            };
+            self.record_step_result(
+                &failed_step.template_id,
+                &step_def,
+                outcome,
+                Instant::now(),
+                template_overall_status,
+                step_results,
+            );
+            if let Some(dependents) = self.step_dependency_manager.get_dependents(&dependent_id) {
+                queue.extend(dependents.iter().cloned());
+            }
+            finished_steps.insert(dependent_id);
+        }
+    }
+
+    /// 记录步骤结果，并注册执行状态和断言状态变量
+    ///
+    /// 返回记录的步骤状态
+    fn record_step_result(
+        &mut self,
+        template_id: &str,
+        step_def: &ExecutionStep,
+        outcome: StepOutcome,
+        start_time: Instant,
+        template_overall_status: &mut StepStatus,
+        step_results: &mut HashMap<String, crate::template::executor::StepResult>,
+    ) -> StepStatus {
+        let duration_ms = start_time.elapsed().as_millis();
+        let status = outcome.status.clone();
+
+        if outcome.status == StepStatus::Fail {
+            *template_overall_status = StepStatus::Fail;
+        }
+
+        let _ = self.variable_manager.set_variable(
+            &step_def.template_id,
+            &step_def.local_id,
+            "status.execution",
+            outcome.status.as_str(),
+        );
+
+        let exec_step_result = crate::template::executor::StepResult {
+            id: step_def.local_id.clone(),
+            description: Some(step_def.description()),
+            status: outcome.status,
+            stdout: outcome.stdout,
+            stderr: outcome.stderr,
+            exit_code: outcome.exit_code,
+            duration_ms: Some(duration_ms),
+            assertion_error: outcome.assertion_error,
+            skip_reason: outcome.skip_reason,
+            reconnects: outcome.reconnects,
+        };
+        if TapStream::is_test_step(step_def)
+            && let Some(tap_stream) = self.tap_stream()
+        {
+            tap_stream.emit(step_def, Some(&exec_step_result));
+        }
+        step_results.insert(
+            utils::get_result_id(template_id, step_def.local_id.as_str()),
+            exec_step_result.clone(),
+        );
+        self.executed_step_results
+            .insert(step_def.id.clone(), exec_step_result);
+
+        // 注册断言状态变量（整体）
+        let _ = self.variable_manager.set_variable(
+            &step_def.template_id,
+            &step_def.local_id,
+            "status.assertion",
+            outcome.assertion_status.as_str(),
+        );
+        // 注册每个断言的状态和错误信息
+        for (idx, status) in outcome.assertion_statuses.iter().enumerate() {
+            let var_name = format!("status.assertion.{idx}");
+            let _ = self.variable_manager.set_variable(
+                &step_def.template_id,
+                &step_def.local_id,
+                &var_name,
+                status.as_str(),
+            );
+            if let Some(Some(err_msg)) = outcome.assertion_error_msgs.get(idx) {
+                let err_var_name = format!("assertion_error.{idx}");
+                let _ = self.variable_manager.set_variable(
+                    &step_def.template_id,
+                    &step_def.local_id,
+                    &err_var_name,
+                    err_msg,
+                );
+            }
+        }
+        status
+    }
+
+    /// 加载被引用但尚未添加的模板（包括被筛选条件排除的模板），递归处理其引用
+    fn load_referenced_templates(&mut self) -> Result<()> {
+        let mut queue: VecDeque<Arc<TestTemplate>> = self.templates.values().cloned().collect();
+        while let Some(template) = queue.pop_front() {
+            for reference in &template.metadata.references {
+                let referenced_id = reference.template_id(&template.tests_dir);
+                if self.templates.contains_key(&referenced_id) {
+                    continue;
+                }
+                let path = template.tests_dir.join(&reference.template_path);
                 info!(
-                    "No results to summarize (all template executions might have failed before producing a result object)."
+                    "Loading template {referenced_id} referenced by {}", // 加载被引用的模板
+                    template.get_template_id()
                 );
+                let referenced =
+                    TestTemplate::from_file(&path, &template.tests_dir).with_context(|| {
+                        format!(
+                            "Failed to load template '{}' referenced by {}",
+                            path.display(),
+                            template.get_template_id()
+                        )
+                    })?; // 加载被引用的模板失败
+                let referenced = Arc::new(referenced);
+                self.add_template(referenced.clone())
+                    .map_err(|e| anyhow!("Failed to add template {referenced_id}: {e}"))?;
+                queue.push_back(referenced);
+            }
+        }
+        Ok(())
+    }
+
+    /// 按模板间的依赖关系计算执行顺序：被引用的模板先执行，其余按模板ID排序
+    ///
+    /// 存在跨模板循环依赖时返回错误，错误信息中包含循环路径
+    fn template_execution_order(&self) -> Result<Vec<String>> {
+        let mut template_ids: Vec<&String> = self.templates.keys().collect();
+        template_ids.sort();
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        let mut path = Vec::new();
+        for template_id in template_ids {
+            self.visit_template(template_id, &mut visited, &mut path, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// 深度优先遍历模板依赖，`path` 为当前遍历路径，用于检测循环
+    fn visit_template(
+        &self,
+        template_id: &str,
+        visited: &mut HashSet<String>,
+        path: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<()> {
+        if let Some(position) = path.iter().position(|id| id == template_id) {
+            let mut cycle = path[position..].to_vec();
+            cycle.push(template_id.to_string());
+            bail!(
+                "Cross-template dependency cycle detected: {}",
+                cycle.join(" -> ")
+            ); // 检测到跨模板循环依赖
+        }
+        if visited.contains(template_id) {
+            return Ok(());
+        }
+        let Some(template) = self.templates.get(template_id) else {
+            return Ok(());
+        };
+        path.push(template_id.to_string());
+        for dependency_id in template.referenced_template_ids() {
+            if self.templates.contains_key(&dependency_id) {
+                self.visit_template(&dependency_id, visited, path, order)?;
+            } else {
+                warn!(
+                    "Template {template_id} depends on template {dependency_id}, which is not loaded"
+                ); // 模板依赖的其他模板未加载
+            }
+        }
+        path.pop();
+        visited.insert(template_id.to_string());
+        order.push(template_id.to_string());
+        Ok(())
+    }
+
+    /// 按跨模板依赖顺序执行所有模板
+    ///
+    /// 只生成各模板自己的报告，整次运行的汇总报告由调用方在所有批次执行完毕后生成
+    pub fn execute_all(&mut self) -> Result<Vec<ExecutionResult>> {
+        let mut all_results = Vec::new();
+        if self.templates.is_empty() {
+            info!("No templates to execute.");
+            return Ok(all_results);
+        }
+
+        self.load_referenced_templates()?;
+        let all_template_ids = self.template_execution_order()?;
+        info!("Template execution order: {all_template_ids:?}"); // 模板执行顺序
+
+        for template_id in &all_template_ids {
+            match self.execute(template_id) {
+                Ok(result) => {
+                    all_results.push(result);
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to execute template {template_id}: {e}. The template is marked as failed in the summary."
+                    ); // 模板执行失败，在汇总报告中标记为失败
+                    if let Some(template) = self.templates.get(template_id) {
+                        all_results.push(ExecutionResult::failed(template.clone(), e.to_string()));
+                    }
+                }
             }
-        } else {
-            warn!("Report directory not configured. Skipping summary report generation.");
         }
 
         Ok(all_results)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::test_support::{
+        continue_on_error_options, execute_template, write_template,
+    };
+
+    const PARALLEL_TEMPLATE: &str = r#"
+# Parallel
+
+```bash {id="first" exec=true}
+sleep 1; date +%s%N
+```
+
+```bash {id="second" exec=true}
+sleep 1; date +%s%N
+```
+
+```bash {id="after" exec=true depends_on=["first", "second"] assert.exit_code=0}
+date +%s%N
+```
+"#;
+
+    /// 在临时目录中写入模板并执行，返回执行结果和耗时
+    fn run_template(body: &str, executor_options: ExecutorOptions) -> (ExecutionResult, Duration) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let template_path = write_template(
+            dir,
+            "sample.test.md",
+            "title: \"Sample\"\nunit_name: \"sample\"\nunit_version: \"1\"\n",
+            body,
+        );
+        let options = BatchOptions {
+            executor_options,
+            ..BatchOptions::default()
+        };
+        let start = Instant::now();
+        let result = execute_template(&template_path, dir, options);
+        (result, start.elapsed())
+    }
+
+    fn step_result<'a>(
+        result: &'a ExecutionResult,
+        local_id: &str,
+    ) -> &'a crate::template::executor::StepResult {
+        result
+            .step_results
+            .values()
+            .find(|r| r.id == local_id)
+            .unwrap_or_else(|| panic!("no result for step {local_id}"))
+    }
+
+    fn step_timestamp(result: &ExecutionResult, local_id: &str) -> u128 {
+        step_result(result, local_id).stdout.trim().parse().unwrap()
+    }
+
+    #[test]
+    fn test_independent_steps_run_in_parallel() {
+        let options = ExecutorOptions {
+            max_parallel_steps: 2,
+            ..ExecutorOptions::default()
+        };
+        let (result, elapsed) = run_template(PARALLEL_TEMPLATE, options);
+        assert_eq!(result.overall_status, StepStatus::Pass);
+        assert!(elapsed < Duration::from_millis(1900), "took {elapsed:?}");
+        // 依赖两个并行步骤的步骤必须在它们都完成后才执行
+        let after = step_timestamp(&result, "after");
+        assert!(after >= step_timestamp(&result, "first"));
+        assert!(after >= step_timestamp(&result, "second"));
+    }
+
+    #[test]
+    fn test_variable_reference_orders_parallel_steps() {
+        // 没有 depends_on，读取变量的步骤仍要等变量提取之后才执行
+        let body = r#"
+# Variables
+
+```bash {id="consumer" exec=true}
+echo "got {{ producer::version }} {{ sample::builder::build }}"
+```
+
+```bash {id="producer" exec=true extract.version=/version=(\S+)/}
+sleep 1
+echo version=1.2
+```
+
+```bash {id="builder" exec=true extract.build=/build=(\S+)/}
+sleep 1
+echo build=42
+```
+"#;
+        let options = ExecutorOptions {
+            max_parallel_steps: 3,
+            ..ExecutorOptions::default()
+        };
+        let (result, _) = run_template(body, options);
+        assert_eq!(result.overall_status, StepStatus::Pass);
+        assert_eq!(step_result(&result, "consumer").stdout.trim(), "got 1.2 42");
+    }
+
+    #[test]
+    fn test_steps_run_sequentially_by_default() {
+        let (result, elapsed) = run_template(PARALLEL_TEMPLATE, ExecutorOptions::default());
+        assert_eq!(result.overall_status, StepStatus::Pass);
+        assert!(elapsed >= Duration::from_secs(2), "took {elapsed:?}");
+    }
+
+    #[test]
+    fn test_failed_step_skips_transitive_dependents_only() {
+        let body = r#"
+# Propagation
+
+```bash {id="broken" exec=true assert.exit_code=0}
+exit 1
+```
+
+## After
+
+```bash {id="child" exec=true depends_on=["broken"]}
+echo child
+```
+
+```bash {id="grandchild" exec=true depends_on=["child"]}
+echo grandchild
+```
+
+```bash {id="unrelated" exec=true}
+echo unrelated
+```
+"#;
+        let options = ExecutorOptions {
+            continue_on_error: true,
+            ..ExecutorOptions::default()
+        };
+        let (result, _) = run_template(body, options);
+        assert_eq!(result.overall_status, StepStatus::Fail);
+        assert_eq!(step_result(&result, "broken").status, StepStatus::Fail);
+        for id in ["child", "grandchild"] {
+            let skipped = step_result(&result, id);
+            assert_eq!(skipped.status, StepStatus::Skipped);
+            assert_eq!(
+                skipped.skip_reason.as_deref(),
+                Some("dependency broken failed")
+            );
+            assert!(skipped.stdout.is_empty());
+        }
+        let unrelated = step_result(&result, "unrelated");
+        assert_eq!(unrelated.status, StepStatus::Pass);
+        assert_eq!(unrelated.stdout.trim(), "unrelated");
+        // 标题不会带着依赖失败的原因出现在跳过列表中
+        let mut with_reason: Vec<&str> = result
+            .step_results
+            .values()
+            .filter(|r| r.skip_reason.is_some())
+            .map(|r| r.id.as_str())
+            .collect();
+        with_reason.sort();
+        assert_eq!(with_reason, ["child", "grandchild"]);
+    }
+
+    /// 只向执行器添加 `added` 中的模板，其余模板需要通过引用自动加载
+    fn execute_all_templates(
+        templates: &[(&str, &str, &str)],
+        added: &[&str],
+    ) -> Result<Vec<ExecutionResult>> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        // 模板以（文件名, 额外的前置数据, 正文）给出
+        for (name, front_matter, body) in templates {
+            write_template(
+                dir,
+                name,
+                &format!(
+                    "title: \"{name}\"\nunit_name: \"{name}\"\nunit_version: \"1\"\n{front_matter}"
+                ),
+                body,
+            );
+        }
+        let options = BatchOptions {
+            test_directory: Some(dir.to_path_buf()),
+            ..continue_on_error_options()
+        };
+        let mut executor = BatchExecutor::new(
+            VariableManager::new(),
+            ConnectionManagerPool::new(),
+            Some(options),
+        );
+        for name in added {
+            let template = TestTemplate::from_file(dir.join(name), dir).unwrap();
+            executor.add_template(Arc::new(template)).unwrap();
+        }
+        executor.execute_all()
+    }
+
+    const REFERENCING_FRONT_MATTER: &str =
+        "references:\n  - template: \"base.test.md\"\n    as: \"base\"\n";
+
+    const DEPENDENT_TEMPLATE: &str = r#"
+# Dependent
+
+```bash {id="use" exec=true depends_on=["base::calc"] assert.exit_code=0}
+echo dependent
+```
+
+```bash {id="local" exec=true}
+echo local
+```
+"#;
+
+    #[test]
+    fn test_referenced_template_is_loaded_and_runs_first() {
+        let base =
+            "\n# Base\n\n```bash {id=\"calc\" exec=true assert.exit_code=0}\necho base\n```\n";
+        let results = execute_all_templates(
+            &[
+                ("base.test.md", "", base),
+                (
+                    "zz_app.test.md",
+                    REFERENCING_FRONT_MATTER,
+                    DEPENDENT_TEMPLATE,
+                ),
+                (
+                    "aa_app.test.md",
+                    REFERENCING_FRONT_MATTER,
+                    DEPENDENT_TEMPLATE,
+                ),
+            ],
+            &["zz_app.test.md", "aa_app.test.md"],
+        )
+        .unwrap();
+        let order: Vec<String> = results
+            .iter()
+            .map(|r| r.template.get_template_id())
+            .collect();
+        assert_eq!(order, ["base", "aa_app", "zz_app"]);
+        for result in &results {
+            assert_eq!(result.overall_status, StepStatus::Pass);
+        }
+        assert_eq!(step_result(&results[1], "use").stdout.trim(), "dependent");
+    }
+
+    #[test]
+    fn test_undefined_dependency_fails_only_its_template() {
+        let broken =
+            "\n# Broken\n\n```bash {id=\"a\" exec=true depends_on=[\"ghost\"]}\necho a\n```\n";
+        let ok = "\n# Ok\n\n```bash {id=\"b\" exec=true assert.exit_code=0}\necho b\n```\n";
+        let results = execute_all_templates(
+            &[("broken.test.md", "", broken), ("ok.test.md", "", ok)],
+            &["broken.test.md", "ok.test.md"],
+        )
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].overall_status, StepStatus::Fail);
+        let error = results[0].error.as_deref().unwrap();
+        assert!(error.contains("broken::ghost (referenced by broken::a ("));
+        assert!(error.contains("broken.test.md:10))"));
+        assert_eq!(results[1].overall_status, StepStatus::Pass);
+        assert!(results[1].error.is_none());
+    }
+
+    #[test]
+    fn test_failed_cross_template_dependency_skips_step() {
+        let base = "\n# Base\n\n```bash {id=\"calc\" exec=true assert.exit_code=0}\nexit 3\n```\n";
+        let results = execute_all_templates(
+            &[
+                ("base.test.md", "", base),
+                ("app.test.md", REFERENCING_FRONT_MATTER, DEPENDENT_TEMPLATE),
+            ],
+            &["app.test.md", "base.test.md"],
+        )
+        .unwrap();
+        assert_eq!(results[0].overall_status, StepStatus::Fail);
+        let skipped = step_result(&results[1], "use");
+        assert_eq!(skipped.status, StepStatus::Skipped);
+        assert_eq!(
+            skipped.skip_reason.as_deref(),
+            Some("dependency base::calc failed")
+        );
+        assert_eq!(step_result(&results[1], "local").status, StepStatus::Pass);
+    }
+
+    #[test]
+    fn test_cross_template_cycle_is_reported() {
+        let body = "\n# Cycle\n\n```bash {id=\"step\" exec=true}\necho cycle\n```\n";
+        let err = execute_all_templates(
+            &[
+                (
+                    "a.test.md",
+                    "references:\n  - template: \"b.test.md\"\n    as: \"b\"\n",
+                    body,
+                ),
+                (
+                    "b.test.md",
+                    "references:\n  - template: \"a.test.md\"\n    as: \"a\"\n",
+                    body,
+                ),
+            ],
+            &["a.test.md"],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cross-template dependency cycle detected: a -> b -> a"
+        );
+    }
+}
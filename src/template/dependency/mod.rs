@@ -8,6 +8,7 @@ use log::{debug, warn};
 use regex::Regex;
 use std::collections::{HashMap, HashSet, VecDeque};
 
+use crate::template::TemplateError;
 use crate::template::step::{ExecutionStep, GlobalStepId, StepType};
 
 /// Manages dependencies between individual execution steps.
@@ -119,22 +120,41 @@ impl StepDependencyManager {
         }
     }
 
-    pub fn build_graph(&mut self) {
+    /// 建立依赖图，存在未定义的依赖步骤时返回错误
+    pub fn build_graph(&mut self) -> Result<(), TemplateError> {
         for step_id in self.nodes.keys() {
             self.graph.entry(step_id.clone()).or_default();
         }
         // 检查所有依赖节点是否都已定义
-        let undefined_nodes: Vec<_> = self
+        let mut undefined_nodes: Vec<_> = self
             .graph
-            .keys()
-            .filter(|id| !self.nodes.contains_key(*id))
-            .cloned()
+            .iter()
+            .filter(|(id, _)| !self.nodes.contains_key(*id))
+            .map(|(id, node)| self.describe_undefined_node(id, node))
             .collect();
         if !undefined_nodes.is_empty() {
-            panic!(
-                "Undefined dependency nodes exist: {undefined_nodes:?}. Please check documentation to ensure all dependent steps are defined."
-            ); // 存在未定义的依赖节点：{undefined_nodes:?}。请检查文档，确保所有依赖的步骤都已定义。
+            undefined_nodes.sort();
+            return Err(TemplateError::UndefinedDependencies(undefined_nodes));
         }
+        Ok(())
+    }
+
+    /// 描述一个未定义的依赖节点，列出引用它的步骤及其源文件位置
+    fn describe_undefined_node(&self, id: &GlobalStepId, node: &NodeData) -> String {
+        let mut referrers: Vec<String> = node
+            .dependents
+            .iter()
+            .map(|dependent| {
+                self.nodes
+                    .get(dependent)
+                    .map_or_else(|| dependent.clone(), ExecutionStep::display_name)
+            })
+            .collect();
+        if referrers.is_empty() {
+            return id.clone();
+        }
+        referrers.sort();
+        format!("{id} (referenced by {})", referrers.join(", "))
     }
 
     pub fn get_execution_order(&self) -> Result<Vec<GlobalStepId>, String> {
@@ -233,6 +253,16 @@ impl StepDependencyManager {
         self.nodes.get(step_id)
     }
 
+    /// 获取步骤直接依赖的步骤集合
+    pub fn get_dependencies(&self, step_id: &GlobalStepId) -> Option<&HashSet<GlobalStepId>> {
+        self.graph.get(step_id).map(|node| &node.dependencies)
+    }
+
+    /// 获取直接依赖该步骤的步骤集合
+    pub fn get_dependents(&self, step_id: &GlobalStepId) -> Option<&HashSet<GlobalStepId>> {
+        self.graph.get(step_id).map(|node| &node.dependents)
+    }
+
     /// 也许有必要，以后自动基于亲子关系设定执行依赖
     /// 比如，代码块依赖于它的父标题
     #[allow(dead_code)]
@@ -287,6 +317,7 @@ mod tests {
             step_type,
             dependencies: dependencies.into_iter().collect(),
             original_parsed_step: original_parsed,
+            span: None,
         }
     }
 
@@ -339,12 +370,42 @@ mod tests {
         );
 
         manager.add_steps(vec![step1.clone(), step2.clone()]);
-        manager.build_graph();
+        manager.build_graph().unwrap();
         let order = manager.get_execution_order().unwrap();
 
         assert_eq!(order, vec![step1.id, step2.id]);
     }
 
+    #[test]
+    fn test_undefined_dependency_is_error() {
+        let mut manager = StepDependencyManager::new();
+        let mut step = create_test_execution_step(
+            "t1",
+            "s1",
+            StepType::CodeBlock {
+                lang: "bash".into(),
+                command: "echo s1".into(),
+                attributes: HashMap::new(),
+            },
+            vec!["t1::ghost".to_string()],
+            None,
+        );
+        step.span = Some(crate::template::SourceSpan {
+            file: "t1.test.md".into(),
+            start_line: 12,
+            end_line: 14,
+        });
+
+        manager.add_steps(vec![step]);
+        let error = manager.build_graph().unwrap_err();
+        assert_eq!(
+            error,
+            TemplateError::UndefinedDependencies(vec![
+                "t1::ghost (referenced by t1::s1 (t1.test.md:12))".to_string()
+            ])
+        );
+    }
+
     #[test]
     fn test_circular_dependency() {
         let mut manager = StepDependencyManager::new();
@@ -375,7 +436,7 @@ mod tests {
         );
 
         manager.add_steps(vec![step1.clone(), step2.clone()]);
-        manager.build_graph();
+        manager.build_graph().unwrap();
         let result = manager.get_execution_order();
         assert!(result.is_err());
         assert!(
@@ -421,7 +482,7 @@ mod tests {
         );
 
         manager.add_steps(vec![step1.clone(), step2.clone()]);
-        manager.build_graph();
+        manager.build_graph().unwrap();
 
         let order = manager
             .get_execution_order()
@@ -484,7 +545,7 @@ mod tests {
         );
 
         manager.add_steps(vec![h1.clone(), h2.clone(), code.clone()]);
-        manager.build_graph();
+        manager.build_graph().unwrap();
 
         let parents = manager.identify_parent_headings(&code);
         assert_eq!(parents, vec![h1.id, h2.id]);
@@ -73,35 +73,49 @@ fn walk_directory(dir: &Path, templates: &mut Vec<PathBuf>, recursive: bool) ->
     Ok(())
 }
 
-/// 根据过滤条件过滤测试模板
-pub fn filter_templates(
+/// 加载模板的结果：通过过滤条件的模板，以及加载失败的模板文件和错误信息
+#[derive(Debug, Default)]
+pub struct LoadedTemplates {
+    pub templates: Vec<TestTemplate>,
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+/// 加载模板并根据过滤条件过滤，加载失败的模板不中断加载，记录在 `failures` 中
+pub fn load_templates(
     template_paths: Vec<PathBuf>,
     filter: &TemplateFilter,
     tests_dir: &Path,
-) -> Result<Vec<TestTemplate>> {
-    let mut filtered_templates = Vec::new();
+) -> LoadedTemplates {
+    let mut loaded = LoadedTemplates::default();
 
-    for path in &template_paths {
-        match TestTemplate::from_file(path, tests_dir) {
+    for path in template_paths {
+        match TestTemplate::from_file(&path, tests_dir) {
             Ok(template) => {
                 // 应用过滤条件
                 if matches_filter(&template, filter) {
-                    filtered_templates.push(template);
+                    loaded.templates.push(template);
                 }
             }
             Err(e) => {
-                warn!("Failed to parse template {}: {}", path.display(), e); // 解析模板 {} 失败: {}
-                continue;
+                warn!("Failed to parse template {}: {:#}", path.display(), e); // 解析模板 {} 失败: {}
+                loaded.failures.push((path, format!("{e:#}")));
             }
         }
     }
 
     info!(
         "Remaining {} test templates after filtering",
-        filtered_templates.len()
+        loaded.templates.len()
     ); // 过滤后剩余 {} 个测试模板
 
-    Ok(filtered_templates)
+    loaded
+}
+
+impl TemplateFilter {
+    /// 是否没有设置任何过滤条件
+    pub fn is_empty(&self) -> bool {
+        self.target.is_none() && self.unit.is_none() && self.tags.is_empty()
+    }
 }
 
 /// 检查模板是否匹配过滤条件
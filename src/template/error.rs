@@ -0,0 +1,42 @@
+//! 模板加载和报告生成中的错误类型
+//!
+//! 这些错误只影响出错的模板：调用方把它们转换为该模板的失败结果，其余模板照常执行。
+
+use std::error::Error;
+use std::fmt;
+
+/// 模板加载、依赖解析和报告生成中的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// 无法加载前置数据中 `target_config` 指定的目标配置文件
+    TargetConfig { path: String, reason: String },
+    /// 步骤依赖了未定义的步骤，每一项描述一个未定义的依赖及引用它的步骤
+    UndefinedDependencies(Vec<String>),
+    /// 执行结果中没有报告路径
+    MissingReportPath { template_id: String },
+    /// 报告生成器缺少必需的目录，值为目录的用途
+    MissingDirectory(&'static str),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::TargetConfig { path, reason } => {
+                write!(f, "Unable to load target_config file {path}: {reason}") // 无法加载 target_config 文件
+            }
+            TemplateError::UndefinedDependencies(nodes) => write!(
+                f,
+                "Undefined dependency nodes exist: {}. Please check documentation to ensure all dependent steps are defined.",
+                nodes.join("; ")
+            ), // 存在未定义的依赖节点。请检查文档，确保所有依赖的步骤都已定义。
+            TemplateError::MissingReportPath { template_id } => {
+                write!(f, "No report path provided for template {template_id}") // 执行结果中没有模板的报告路径
+            }
+            TemplateError::MissingDirectory(purpose) => {
+                write!(f, "Reporter requires a {purpose} directory") // 报告生成器缺少必需的目录
+            }
+        }
+    }
+}
+
+impl Error for TemplateError {}
@@ -8,7 +8,7 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::template::{AssertionType, StepStatus, TestTemplate};
+use crate::template::{AssertionType, SourceSpan, StepStatus, TestTemplate};
 
 /// 测试执行结果
 #[derive(Debug, Clone)]
@@ -32,9 +32,25 @@ pub struct ExecutionResult {
     pub variables: HashMap<String, String>,
     /// 报告文件路径
     pub report_path: Option<PathBuf>,
+    /// 模板未能执行时的错误信息（加载失败、依赖无法解析等），此时总体状态为失败
+    pub error: Option<String>,
 }
 
 impl ExecutionResult {
+    /// 为未能执行的模板构造失败结果，使其出现在汇总报告中而不中断整次运行
+    pub fn failed(template: Arc<TestTemplate>, error: impl Into<String>) -> Self {
+        Self {
+            unit_name: template.metadata.unit_name.clone(),
+            target_name: template.metadata.target_config.get_name().to_string(),
+            template,
+            overall_status: StepStatus::Fail,
+            step_results: HashMap::new(),
+            variables: HashMap::new(),
+            report_path: None,
+            error: Some(error.into()),
+        }
+    }
+
     /// 获取模板ID
     pub fn template_id(&self) -> String {
         self.template.get_template_id()
@@ -45,6 +61,15 @@ impl ExecutionResult {
         &self.template.metadata.title
     }
 
+    /// 获取步骤在模板源文件中的位置
+    pub fn step_span(&self, step_id: &str) -> Option<&SourceSpan> {
+        self.template
+            .steps
+            .iter()
+            .find(|step| step.id == step_id)
+            .and_then(|step| step.span.as_ref())
+    }
+
     #[allow(dead_code)]
     // 呃说不准有天生成 summary 的时候可以用到（这是不是也该注册到模板变量里）
     /// 获取模板文件路径
@@ -75,6 +100,10 @@ pub struct StepResult {
     #[allow(dead_code)]
     /// 断言失败信息
     pub assertion_error: Option<String>,
+    /// 跳过原因（例如 "dependency X failed"）
+    pub skip_reason: Option<String>,
+    /// 执行期间重建连接的记录
+    pub reconnects: Vec<String>,
 }
 
 /// 执行器选项
@@ -90,6 +119,8 @@ pub struct ExecutorOptions {
     pub maintain_session: bool,
     /// 是否在出错时继续执行（尽可能多地执行其他独立步骤）
     pub continue_on_error: bool,
+    /// 同一模板内最多同时执行的步骤数（1 表示顺序执行）
+    pub max_parallel_steps: usize,
 }
 
 impl Default for ExecutorOptions {
@@ -100,6 +131,7 @@ impl Default for ExecutorOptions {
             retry_interval: 5,
             maintain_session: true,
             continue_on_error: false,
+            max_parallel_steps: 1,
         }
     }
 }
@@ -0,0 +1,469 @@
+//! 运行历史记录与回归检测
+//!
+//! 每次运行结束后把各模板、各步骤的状态、退出码、耗时和提取的变量追加到报告目录下的
+//! `history.jsonl`（每行一次运行）。`--compare-with` 会把本次结果与历史中的某次运行对比，
+//! 找出从通过变为失败的步骤、新通过的步骤以及耗时明显变化的步骤。
+
+use anyhow::{Context, Result, bail};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::template::StepStatus;
+use crate::template::executor::ExecutionResult;
+use crate::utils;
+
+/// 历史记录文件名（位于报告目录下）
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// 耗时变化超过该比例才视为明显变化
+const DURATION_CHANGE_RATIO: f64 = 0.5;
+/// 耗时变化的绝对值低于该毫秒数时忽略，避免很短的步骤产生噪音
+const DURATION_CHANGE_MIN_MS: u128 = 1000;
+
+/// 一次运行的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    /// 记录时间（RFC 3339）
+    pub recorded_at: String,
+    pub results: Vec<TemplateRecord>,
+}
+
+/// 单个模板在某个目标上的执行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateRecord {
+    pub template_id: String,
+    pub target: String,
+    pub unit: String,
+    pub status: String,
+    pub steps: Vec<StepRecord>,
+    /// 本模板通过 `extract.*` 提取的变量，键为 `step_id::变量名`
+    pub variables: BTreeMap<String, String>,
+}
+
+/// 单个步骤的执行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub id: String,
+    pub status: String,
+    pub exit_code: i32,
+    pub duration_ms: Option<u128>,
+}
+
+impl RunRecord {
+    /// 由本次运行的执行结果构建记录，`history` 用于保证运行ID不重复
+    pub fn from_results<'a>(
+        results: impl IntoIterator<Item = &'a ExecutionResult>,
+        history: &[RunRecord],
+    ) -> Self {
+        let now = chrono::Local::now();
+        let base_id = now.format("%Y%m%d-%H%M%S").to_string();
+        let mut run_id = base_id.clone();
+        let mut suffix = 1;
+        while history.iter().any(|run| run.run_id == run_id) {
+            suffix += 1;
+            run_id = format!("{base_id}-{suffix}");
+        }
+        Self {
+            run_id,
+            recorded_at: now.to_rfc3339(),
+            results: results
+                .into_iter()
+                .map(TemplateRecord::from_result)
+                .collect(),
+        }
+    }
+
+    /// 以 (目标, 模板ID, 步骤ID) 为键索引所有步骤
+    fn steps(&self) -> HashMap<(&str, &str, &str), &StepRecord> {
+        self.results
+            .iter()
+            .flat_map(|template| {
+                template.steps.iter().map(move |step| {
+                    (
+                        (
+                            template.target.as_str(),
+                            template.template_id.as_str(),
+                            step.id.as_str(),
+                        ),
+                        step,
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl TemplateRecord {
+    fn from_result(result: &ExecutionResult) -> Self {
+        let template_id = result.template_id();
+        let steps = result
+            .template
+            .steps
+            .iter()
+            .filter_map(|step| {
+                result
+                    .step_results
+                    .get(&utils::get_result_id(&template_id, &step.local_id))
+            })
+            .map(|step_result| StepRecord {
+                id: step_result.id.clone(),
+                status: step_result.status.as_str().to_string(),
+                exit_code: step_result.exit_code,
+                duration_ms: step_result.duration_ms,
+            })
+            .collect();
+        // 只保留 extract.* 定义的变量，stdout、状态等内置变量不写入历史
+        let extracted: HashSet<String> = result
+            .template
+            .steps
+            .iter()
+            .filter_map(|step| {
+                step.original_parsed_step
+                    .as_ref()
+                    .map(|parsed| (&step.local_id, parsed))
+            })
+            .flat_map(|(local_id, parsed)| {
+                parsed
+                    .extractions
+                    .iter()
+                    .map(move |extraction| format!("{local_id}::{}", extraction.variable))
+            })
+            .collect();
+        let variable_prefix = format!("{template_id}::");
+        let variables = result
+            .variables
+            .iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix(&variable_prefix)
+                    .filter(|name| extracted.contains(*name))
+                    .map(|name| (name.to_string(), value.clone()))
+            })
+            .collect();
+        Self {
+            template_id,
+            target: result.target_name.clone(),
+            unit: result.template.metadata.unit_name.clone(),
+            status: result.overall_status.as_str().to_string(),
+            steps,
+            variables,
+        }
+    }
+}
+
+/// 读取历史记录，文件不存在时返回空列表
+pub fn load_history(path: &Path) -> Result<Vec<RunRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Unable to read run history: {}", path.display()))?; // 无法读取运行历史
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Invalid run history record at {}:{}",
+                    path.display(),
+                    index + 1
+                )
+            }) // 运行历史记录格式错误
+        })
+        .collect()
+}
+
+/// 将一次运行的记录追加到历史记录文件
+pub fn append_history(path: &Path, record: &RunRecord) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Unable to create report directory: {}", parent.display()))?; // 无法创建报告目录
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Unable to open run history: {}", path.display()))?; // 无法打开运行历史
+    writeln!(file, "{}", serde_json::to_string(record)?)
+        .with_context(|| format!("Unable to write run history: {}", path.display()))?; // 无法写入运行历史
+    info!("Run {} recorded in {}", record.run_id, path.display()); // 已记录本次运行
+    Ok(())
+}
+
+/// 按运行ID查找历史运行，`last` 表示最近一次运行
+pub fn find_run<'a>(history: &'a [RunRecord], run_id: &str) -> Result<&'a RunRecord> {
+    let found = if run_id == "last" {
+        history.last()
+    } else {
+        history.iter().find(|run| run.run_id == run_id)
+    };
+    match found {
+        Some(run) => Ok(run),
+        None if history.is_empty() => bail!("No previous runs recorded to compare with"), // 没有可对比的历史运行
+        None => bail!(
+            "Run '{}' not found in history (available: {})",
+            run_id,
+            history
+                .iter()
+                .map(|run| run.run_id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ), // 历史中没有该运行
+    }
+}
+
+/// 两次运行之间状态发生变化的步骤
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepChange {
+    pub target: String,
+    pub template_id: String,
+    pub step_id: String,
+    /// 对比运行中的状态，对比运行中没有该步骤时为 None
+    pub previous_status: Option<String>,
+}
+
+/// 两次运行之间耗时明显变化的步骤
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DurationChange {
+    pub target: String,
+    pub template_id: String,
+    pub step_id: String,
+    pub previous_ms: u128,
+    pub current_ms: u128,
+}
+
+/// 本次运行与历史运行的对比结果
+#[derive(Debug, Clone)]
+pub struct RunComparison {
+    pub baseline_run_id: String,
+    /// 从通过变为失败的步骤
+    pub regressions: Vec<StepChange>,
+    /// 之前未通过（或不存在）、本次通过的步骤
+    pub newly_passing: Vec<StepChange>,
+    pub duration_changes: Vec<DurationChange>,
+}
+
+impl RunComparison {
+    /// 对比两次运行
+    pub fn compare(baseline: &RunRecord, current: &RunRecord) -> Self {
+        let previous_steps = baseline.steps();
+        let mut current_steps: Vec<_> = current.steps().into_iter().collect();
+        current_steps.sort_by_key(|(key, _)| *key);
+
+        let pass = StepStatus::Pass.as_str();
+        let fail = StepStatus::Fail.as_str();
+        let mut comparison = Self {
+            baseline_run_id: baseline.run_id.clone(),
+            regressions: Vec::new(),
+            newly_passing: Vec::new(),
+            duration_changes: Vec::new(),
+        };
+        for ((target, template_id, step_id), step) in current_steps {
+            let previous = previous_steps.get(&(target, template_id, step_id));
+            let change = || StepChange {
+                target: target.to_string(),
+                template_id: template_id.to_string(),
+                step_id: step_id.to_string(),
+                previous_status: previous.map(|p| p.status.clone()),
+            };
+            let previous_status = previous.map(|p| p.status.as_str());
+            if step.status == fail && previous_status == Some(pass) {
+                comparison.regressions.push(change());
+            } else if step.status == pass && previous_status != Some(pass) {
+                comparison.newly_passing.push(change());
+            }
+
+            let executed = |status: &str| status == pass || status == fail;
+            if let Some(previous) = previous
+                && executed(&step.status)
+                && executed(&previous.status)
+                && let (Some(previous_ms), Some(current_ms)) =
+                    (previous.duration_ms, step.duration_ms)
+            {
+                let diff = previous_ms.abs_diff(current_ms);
+                if diff >= DURATION_CHANGE_MIN_MS
+                    && diff as f64 >= previous_ms as f64 * DURATION_CHANGE_RATIO
+                {
+                    comparison.duration_changes.push(DurationChange {
+                        target: target.to_string(),
+                        template_id: template_id.to_string(),
+                        step_id: step_id.to_string(),
+                        previous_ms,
+                        current_ms,
+                    });
+                }
+            }
+        }
+        comparison
+    }
+
+    /// 渲染为汇总报告中的一节
+    pub fn to_markdown(&self) -> String {
+        let mut content = format!("## Comparison with Run {}\n\n", self.baseline_run_id);
+        if self.regressions.is_empty()
+            && self.newly_passing.is_empty()
+            && self.duration_changes.is_empty()
+        {
+            content
+                .push_str("No regressions, newly passing steps or significant duration changes.\n");
+            return content;
+        }
+
+        for (title, changes) in [
+            ("Regressions (Pass → Fail)", &self.regressions),
+            ("Newly Passing", &self.newly_passing),
+        ] {
+            if changes.is_empty() {
+                continue;
+            }
+            content.push_str(&format!("### {title}\n\n"));
+            content.push_str("| Target | Template ID | Step ID | Previous Status |\n");
+            content.push_str("|--------|-------------|---------|-----------------|\n");
+            for change in changes {
+                content.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    change.target,
+                    change.template_id,
+                    change.step_id,
+                    change.previous_status.as_deref().unwrap_or("-")
+                ));
+            }
+            content.push('\n');
+        }
+
+        if !self.duration_changes.is_empty() {
+            content.push_str("### Duration Changes\n\n");
+            content.push_str(
+                "| Target | Template ID | Step ID | Previous (ms) | Current (ms) | Change |\n",
+            );
+            content.push_str(
+                "|--------|-------------|---------|---------------|--------------|--------|\n",
+            );
+            for change in &self.duration_changes {
+                let percent = (change.current_ms as f64 - change.previous_ms as f64)
+                    / (change.previous_ms.max(1) as f64)
+                    * 100.0;
+                content.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {percent:+.0}% |\n",
+                    change.target,
+                    change.template_id,
+                    change.step_id,
+                    change.previous_ms,
+                    change.current_ms
+                ));
+            }
+            content.push('\n');
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::test_support::{
+        continue_on_error_options, execute_template, write_template,
+    };
+
+    fn record(run_id: &str, steps: &[(&str, &str, u128)]) -> RunRecord {
+        RunRecord {
+            run_id: run_id.to_string(),
+            recorded_at: String::new(),
+            results: vec![TemplateRecord {
+                template_id: "curl".to_string(),
+                target: "riscv".to_string(),
+                unit: "curl".to_string(),
+                status: "Pass".to_string(),
+                steps: steps
+                    .iter()
+                    .map(|(id, status, duration)| StepRecord {
+                        id: id.to_string(),
+                        status: status.to_string(),
+                        exit_code: 0,
+                        duration_ms: Some(*duration),
+                    })
+                    .collect(),
+                variables: BTreeMap::from([("version::v".to_string(), "8.5.0".to_string())]),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_history_round_trip_and_compare() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("reports").join(HISTORY_FILE_NAME);
+        assert!(load_history(&path).unwrap().is_empty());
+        assert!(find_run(&[], "last").is_err());
+
+        let first = record(
+            "run-1",
+            &[
+                ("build", "Pass", 2000),
+                ("install", "Pass", 100),
+                ("check", "Fail", 50),
+                ("slow", "Pass", 1000),
+            ],
+        );
+        let second = record(
+            "run-2",
+            &[
+                ("build", "Pass", 2100),
+                ("install", "Fail", 90),
+                ("check", "Pass", 40),
+                ("slow", "Pass", 4000),
+                ("new", "Pass", 10),
+            ],
+        );
+        append_history(&path, &first).unwrap();
+        append_history(&path, &second).unwrap();
+
+        let history = load_history(&path).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(find_run(&history, "last").unwrap().run_id, "run-2");
+        assert_eq!(find_run(&history, "run-1").unwrap().steps().len(), 4);
+        assert_eq!(history[0].results[0].variables["version::v"], "8.5.0");
+        let err = find_run(&history, "run-9").unwrap_err().to_string();
+        assert!(err.contains("available: run-1, run-2"), "{err}");
+
+        let comparison = RunComparison::compare(&history[0], &history[1]);
+        let step_ids = |changes: &[StepChange]| -> Vec<String> {
+            changes.iter().map(|c| c.step_id.clone()).collect()
+        };
+        assert_eq!(step_ids(&comparison.regressions), ["install"]);
+        assert_eq!(step_ids(&comparison.newly_passing), ["check", "new"]);
+        assert_eq!(comparison.newly_passing[1].previous_status, None);
+        // build 只慢了 100ms，不算明显变化
+        assert_eq!(comparison.duration_changes.len(), 1);
+        assert_eq!(comparison.duration_changes[0].step_id, "slow");
+
+        let markdown = comparison.to_markdown();
+        assert!(markdown.starts_with("## Comparison with Run run-1\n"));
+        assert!(markdown.contains("| riscv | curl | install | Pass |"));
+        assert!(markdown.contains("| riscv | curl | new | - |"));
+        assert!(markdown.contains("| riscv | curl | slow | 1000 | 4000 | +300% |"));
+    }
+
+    #[test]
+    fn test_record_keeps_only_extracted_variables() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let path = write_template(
+            dir,
+            "curl.test.md",
+            "title: \"Curl\"\nunit_name: \"curl\"\nunit_version: \"1\"\n",
+            "\n# Curl\n\n```bash {id=\"version\" exec=true extract.v=/curl (\\S+)/}\necho curl 8.5.0\n```\n",
+        );
+        let result = execute_template(&path, dir, continue_on_error_options());
+
+        let record = TemplateRecord::from_result(&result);
+        assert_eq!(
+            record.variables,
+            BTreeMap::from([("version::v".to_string(), "8.5.0".to_string())])
+        );
+    }
+}
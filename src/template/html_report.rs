@@ -0,0 +1,492 @@
+//! HTML 测试报告生成
+//!
+//! 与 Markdown 报告遍历同一个 `ContentBlock` 序列，生成内联 CSS 的单个 HTML 文件，
+//! 便于作为 CI 产物归档。每个代码块带有通过/失败标记，标准输出和标准错误放在可折叠区域中，
+//! 失败的断言会突出显示。
+
+use anyhow::{Context, Result};
+use log::info;
+use pulldown_cmark::{Event, Options, Parser, html};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::template::executor::{ExecutionResult, StepResult};
+use crate::template::reporter::replace_text_variables;
+use crate::template::variable::VariableManager;
+use crate::template::{ContentBlock, SourceSpan, StepStatus, TestTemplate};
+use crate::utils;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; max-width: 1100px; margin: 2em auto; padding: 0 1em; color: #1f2328; line-height: 1.5; }
+header { border-bottom: 1px solid #d0d7de; margin-bottom: 1.5em; padding-bottom: 1em; }
+header dl { display: grid; grid-template-columns: max-content auto; gap: 0.2em 1em; margin: 0.5em 0 0; }
+header dt { font-weight: 600; }
+header dd { margin: 0; }
+pre { background: #f6f8fa; border-radius: 6px; padding: 0.8em; overflow-x: auto; font-size: 0.9em; margin: 0.4em 0; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+.badge { display: inline-block; border-radius: 1em; padding: 0 0.7em; font-size: 0.8em; font-weight: 600; color: #fff; vertical-align: middle; }
+.badge.pass { background: #1a7f37; }
+.badge.fail { background: #cf222e; }
+.badge.skipped { background: #9a6700; }
+.badge.notrun { background: #6e7781; }
+.step { border: 1px solid #d0d7de; border-left: 4px solid #6e7781; border-radius: 6px; margin: 1em 0; padding: 0.6em 1em; }
+.step.pass { border-left-color: #1a7f37; }
+.step.fail { border-left-color: #cf222e; background: #fff8f8; }
+.step.skipped { border-left-color: #9a6700; }
+.step-header { display: flex; gap: 0.8em; align-items: baseline; flex-wrap: wrap; }
+.step-header .meta { color: #656d76; font-size: 0.85em; }
+.assertion-error { background: #ffebe9; border: 1px solid #ff8182; border-radius: 6px; padding: 0.5em 0.8em; margin: 0.5em 0; white-space: pre-wrap; font-family: ui-monospace, monospace; font-size: 0.9em; }
+.skip-reason { color: #9a6700; margin: 0.4em 0; }
+.reconnect { color: #9a6700; margin: 0.4em 0; }
+details { margin: 0.4em 0; }
+summary { cursor: pointer; color: #0969da; }
+details pre { max-height: 40em; overflow: auto; }
+table { border-collapse: collapse; margin: 1em 0; width: 100%; }
+th, td { border: 1px solid #d0d7de; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f6f8fa; }
+"#;
+
+/// 转义 HTML 特殊字符
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 状态对应的 CSS 类名和显示文本
+fn status_badge(status: &StepStatus) -> (&'static str, &'static str) {
+    match status {
+        StepStatus::Pass => ("pass", "Pass"),
+        StepStatus::Fail => ("fail", "Fail"),
+        StepStatus::Skipped => ("skipped", "Skipped"),
+        StepStatus::Blocked => ("notrun", "Blocked"),
+        StepStatus::NotRun => ("notrun", "Not Run"),
+    }
+}
+
+/// 把 Markdown 正文渲染为 HTML
+///
+/// 正文中的原始 HTML 按文本输出，避免替换进来的命令输出破坏页面结构
+fn markdown_to_html(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        event => event,
+    });
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+/// 可折叠的输出区域，失败的步骤默认展开
+fn push_output_details(html: &mut String, label: &str, output: &str, open: bool) {
+    let output = output.trim_end_matches('\n');
+    if output.is_empty() {
+        return;
+    }
+    let _ = writeln!(
+        html,
+        "<details{}><summary>{label} ({} lines)</summary><pre><code>{}</code></pre></details>",
+        if open { " open" } else { "" },
+        output.lines().count(),
+        escape_html(output)
+    );
+}
+
+/// 渲染一个代码块及其执行结果
+fn push_code_step(
+    html: &mut String,
+    id: &str,
+    lang: &str,
+    code: &str,
+    executable: bool,
+    step_result: Option<&StepResult>,
+    span: &SourceSpan,
+) {
+    let status = step_result
+        .filter(|_| executable)
+        .map(|r| status_badge(&r.status));
+    let _ = writeln!(
+        html,
+        "<div class=\"step {}\" id=\"step-{}\">",
+        status.map_or("", |(class, _)| class),
+        escape_html(id)
+    );
+    html.push_str("<div class=\"step-header\">");
+    let _ = write!(html, "<code>{}</code>", escape_html(id));
+    if let Some((class, text)) = status {
+        let _ = write!(html, "<span class=\"badge {class}\">{text}</span>");
+    }
+    if let Some(result) = step_result.filter(|_| executable) {
+        let _ = write!(html, "<span class=\"meta\">exit code {}", result.exit_code);
+        if let Some(ms) = result.duration_ms {
+            let _ = write!(html, " · {ms} ms");
+        }
+        html.push_str("</span>");
+        if let Some(description) = &result.description {
+            let _ = write!(
+                html,
+                "<span class=\"meta\">{}</span>",
+                escape_html(description)
+            );
+        }
+        // 失败的步骤显示源文件位置，便于跳转
+        if result.status == StepStatus::Fail {
+            let _ = write!(
+                html,
+                "<span class=\"meta\">{}</span>",
+                escape_html(&span.to_string())
+            );
+        }
+    }
+    html.push_str("</div>\n");
+    let _ = writeln!(
+        html,
+        "<pre><code class=\"language-{}\">{}</code></pre>",
+        escape_html(lang),
+        escape_html(code)
+    );
+
+    if let Some(result) = step_result.filter(|_| executable) {
+        if let Some(reason) = &result.skip_reason {
+            let _ = writeln!(
+                html,
+                "<p class=\"skip-reason\">Skipped: {}</p>",
+                escape_html(reason)
+            );
+        }
+        for reconnect in &result.reconnects {
+            let _ = writeln!(
+                html,
+                "<p class=\"reconnect\">Reconnected: {}</p>",
+                escape_html(reconnect)
+            );
+        }
+        if result.status == StepStatus::Fail
+            && let Some(error) = &result.assertion_error
+        {
+            let _ = writeln!(
+                html,
+                "<div class=\"assertion-error\">{}</div>",
+                escape_html(error)
+            );
+        }
+        let failed = result.status == StepStatus::Fail;
+        push_output_details(html, "stdout", &result.stdout, failed);
+        push_output_details(html, "stderr", &result.stderr, failed);
+    }
+    html.push_str("</div>\n");
+}
+
+/// 渲染步骤摘要表
+fn push_summary_table(
+    html: &mut String,
+    template_id: &str,
+    result: &ExecutionResult,
+    var_manager: &VariableManager,
+) {
+    html.push_str(
+        "<table>\n<tr><th>Step ID</th><th>Description</th><th>Status</th><th>Exit Code</th><th>Error Message</th></tr>\n",
+    );
+    let mut step_ids: Vec<_> = result.step_results.keys().collect();
+    step_ids.sort();
+    for step_id in step_ids {
+        let step_result = &result.step_results[step_id];
+        let local_id = step_id.split("::").last().unwrap_or(step_id);
+        let description = var_manager.replace_variables(
+            step_result.description.as_deref().unwrap_or("-"),
+            Some(template_id),
+            Some(local_id),
+        );
+        let (class, text) = status_badge(&step_result.status);
+        let mut message = step_result
+            .skip_reason
+            .as_deref()
+            .or(step_result.assertion_error.as_deref())
+            .unwrap_or("-")
+            .to_string();
+        if step_result.status == StepStatus::Fail
+            && let Some(span) = result.step_span(step_id)
+        {
+            message = match message.as_str() {
+                "-" => span.to_string(),
+                _ => format!("{span}: {message}"),
+            };
+        }
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"#step-{id}\"><code>{id}</code></a></td><td>{}</td><td><span class=\"badge {class}\">{text}</span></td><td>{}</td><td>{}</td></tr>",
+            escape_html(&description),
+            step_result.exit_code,
+            escape_html(&message),
+            id = escape_html(local_id),
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+/// 生成单个模板的 HTML 报告内容
+pub fn generate_html_content(
+    template: &TestTemplate,
+    result: &ExecutionResult,
+    var_manager: &VariableManager,
+) -> String {
+    let template_id = template.get_template_id();
+    let metadata = &template.metadata;
+    let (status_class, status_text) = status_badge(&result.overall_status);
+    let title =
+        escape_html(&var_manager.replace_variables(&metadata.title, Some(&template_id), None));
+
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>"
+    );
+    let _ = writeln!(
+        html,
+        "<header>\n<h1>{title} <span class=\"badge {status_class}\">{status_text}</span></h1>\n<dl>"
+    );
+    for (name, value) in [
+        ("Template", template_id.as_str()),
+        ("Unit", metadata.unit_name.as_str()),
+        ("Unit version", metadata.unit_version.as_str()),
+        ("Target", metadata.target_config.get_name()),
+        ("Generated", &chrono::Local::now().to_rfc3339()),
+    ] {
+        let _ = writeln!(html, "<dt>{name}</dt><dd>{}</dd>", escape_html(value));
+    }
+    html.push_str("</dl>\n</header>\n<main>\n");
+
+    for content_block in &template.content_blocks {
+        match content_block {
+            ContentBlock::Metadata(_) => {}
+            ContentBlock::Text(text) => {
+                let text = replace_text_variables(text, &template_id, result, var_manager);
+                html.push_str(&markdown_to_html(&text));
+            }
+            ContentBlock::HeadingBlock {
+                id,
+                level,
+                text,
+                attributes,
+                ..
+            } => {
+                if attributes.get("visible").is_some_and(|v| v == "false") {
+                    continue;
+                }
+                let text = var_manager.replace_variables(text, Some(&template_id), Some(id));
+                let text = var_manager.replace_variables(&text, Some(&template_id), None);
+                let level = (*level).clamp(1, 6);
+                let _ = writeln!(
+                    html,
+                    "<h{level} id=\"{}\">{}</h{level}>",
+                    escape_html(id),
+                    escape_html(text.trim())
+                );
+            }
+            ContentBlock::CodeBlock {
+                id,
+                lang,
+                code,
+                attributes,
+                span,
+            } => {
+                if attributes.get("visible").is_some_and(|v| v == "false") {
+                    continue;
+                }
+                let code = var_manager.replace_variables(code, Some(&template_id), Some(id));
+                let executable = template
+                    .steps
+                    .iter()
+                    .find(|s| s.local_id == *id)
+                    .and_then(|s| s.original_parsed_step.as_ref())
+                    .is_some_and(|p| p.executable);
+                let step_result = result
+                    .step_results
+                    .get(&utils::get_result_id(&template_id, id));
+                push_code_step(&mut html, id, lang, &code, executable, step_result, span);
+            }
+            ContentBlock::OutputBlock {
+                step_id, stream, ..
+            } => {
+                match result
+                    .step_results
+                    .get(&utils::get_result_id(&template_id, step_id))
+                {
+                    Some(step_result) => {
+                        let label = format!("Output of {step_id}");
+                        let output = match stream.as_str() {
+                            "stderr" => step_result.stderr.clone(),
+                            "both" => format!("{}{}", step_result.stdout, step_result.stderr),
+                            _ => step_result.stdout.clone(),
+                        };
+                        push_output_details(&mut html, &escape_html(&label), &output, false);
+                    }
+                    None => {
+                        let _ = writeln!(
+                            html,
+                            "<p><em>Output for step '{}' not found</em></p>",
+                            escape_html(step_id)
+                        );
+                    }
+                }
+            }
+            ContentBlock::SummaryTablePlaceholder => {
+                push_summary_table(&mut html, &template_id, result, var_manager);
+            }
+        }
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+/// 生成 HTML 报告并写入文件
+pub fn write_html_report(
+    path: &Path,
+    template: &TestTemplate,
+    result: &ExecutionResult,
+    var_manager: &VariableManager,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Unable to create report directory: {}", parent.display()))?; // 无法创建报告目录
+    }
+    fs::write(path, generate_html_content(template, result, var_manager))
+        .with_context(|| format!("Unable to write HTML report: {}", path.display()))?; // 无法写入 HTML 报告
+    info!("HTML report generated at: {}", path.display()); // 已生成 HTML 报告
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::test_support::{
+        continue_on_error_options, execute_template, write_template,
+    };
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    const FRONT_MATTER: &str = "title: \"Sample\"\nunit_name: \"sample\"\nunit_version: \"1\"\n";
+
+    const TEMPLATE: &str = r#"
+
+# Sample
+
+Some **prose** with <b>raw html</b>.
+
+```bash {id="ok" exec=true}
+echo ok
+```
+
+```bash {id="broken" exec=true assert.exit_code=0}
+cat big.log; exit 1
+```
+
+```bash {id="doc" exec=false}
+echo just documentation
+```
+"#;
+
+    fn step(id: &str, status: StepStatus) -> StepResult {
+        StepResult {
+            id: id.to_string(),
+            description: None,
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms: Some(12),
+            assertion_error: None,
+            skip_reason: None,
+            reconnects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_generate_html_content() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let path = write_template(dir, "sample.test.md", FRONT_MATTER, TEMPLATE);
+        let template = Arc::new(TestTemplate::from_file(&path, dir).unwrap());
+
+        let step_results = HashMap::from([
+            (
+                "sample::ok".to_string(),
+                StepResult {
+                    stdout: "ok\n".to_string(),
+                    ..step("ok", StepStatus::Pass)
+                },
+            ),
+            (
+                "sample::broken".to_string(),
+                StepResult {
+                    exit_code: 1,
+                    stdout: "line <1>\nline 2\n".to_string(),
+                    assertion_error: Some("Expected exit code 0, got 1".to_string()),
+                    ..step("broken", StepStatus::Fail)
+                },
+            ),
+            ("sample::doc".to_string(), step("doc", StepStatus::Skipped)),
+        ]);
+        let result = ExecutionResult {
+            template: template.clone(),
+            unit_name: "sample".to_string(),
+            target_name: "local".to_string(),
+            overall_status: StepStatus::Fail,
+            step_results,
+            variables: HashMap::new(),
+            report_path: None,
+            error: None,
+        };
+
+        let html = generate_html_content(&template, &result, &VariableManager::new());
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<style>"));
+        assert!(html.contains("<h1 id=\"heading_1\">Sample</h1>"));
+        assert!(html.contains("<strong>prose</strong>"));
+        assert!(html.contains("&lt;b&gt;raw html&lt;/b&gt;"));
+        assert!(html.contains("<div class=\"step pass\" id=\"step-ok\">"));
+        assert!(html.contains("<span class=\"badge pass\">Pass</span>"));
+        // 通过的步骤输出默认折叠，失败的步骤默认展开
+        assert!(html.contains("<details><summary>stdout (1 lines)</summary>"));
+        assert!(html.contains(
+            "<details open><summary>stdout (2 lines)</summary><pre><code>line &lt;1&gt;\nline 2</code></pre></details>"
+        ));
+        assert!(html.contains("<div class=\"assertion-error\">Expected exit code 0, got 1</div>"));
+        // 不可执行的代码块只显示代码，不显示状态
+        assert!(html.contains("<div class=\"step \" id=\"step-doc\">"));
+        assert_eq!(html.matches("class=\"badge ").count(), 3);
+    }
+
+    #[test]
+    fn test_html_highlights_executed_assertion_failure() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let path = write_template(
+            dir,
+            "sample.test.md",
+            FRONT_MATTER,
+            "\n# Sample\n\n```bash {id=\"greet\" exec=true assert.stdout_contains=\"<bye>\"}\necho hello\n```\n",
+        );
+        let result = execute_template(&path, dir, continue_on_error_options());
+
+        let html = generate_html_content(&result.template, &result, &VariableManager::new());
+        assert!(html.contains("<div class=\"step fail\" id=\"step-greet\">"));
+        assert!(html.contains(
+            "<div class=\"assertion-error\">Stdout does not contain expected pattern: &#39;&lt;bye&gt;&#39;</div>"
+        ), "{html}");
+    }
+}
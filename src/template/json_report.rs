@@ -0,0 +1,292 @@
+//! JSON 格式的测试结果导出
+//!
+//! 将 `ExecutionResult` 转换为结构稳定的 JSON 文档，供看板等外部工具读取。
+//! 文档结构见仓库中的 `schemas/report.schema.json`，修改字段时需要同步更新 schema
+//! 并增加 `SCHEMA_VERSION`。
+
+use anyhow::{Context, Result};
+use log::info;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::template::executor::ExecutionResult;
+use crate::utils;
+
+/// JSON 报告结构版本，字段发生不兼容变化时递增
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON 报告文档
+#[derive(Debug, Serialize)]
+pub struct JsonReport {
+    pub schema_version: u32,
+    /// 报告生成时间（RFC 3339）
+    pub generated_at: String,
+    pub results: Vec<JsonTemplateResult>,
+}
+
+/// 单个模板的执行结果
+#[derive(Debug, Serialize)]
+pub struct JsonTemplateResult {
+    pub template_id: String,
+    pub title: String,
+    /// 模板文件路径（相对于测试目录）
+    pub template_file: String,
+    pub unit: JsonUnit,
+    pub target: JsonTarget,
+    pub tags: Vec<String>,
+    /// 前置数据中的自定义字段
+    pub metadata: BTreeMap<String, String>,
+    pub status: String,
+    /// 对应的 Markdown 报告文件路径
+    pub report_file: Option<String>,
+    /// 模板未能执行时的错误信息
+    pub error: Option<String>,
+    /// 按模板中的出现顺序排列的步骤结果
+    pub steps: Vec<JsonStepResult>,
+    /// 本模板记录的变量，键为 `step_id::变量名`
+    pub variables: BTreeMap<String, String>,
+}
+
+/// 测试单元信息
+#[derive(Debug, Serialize)]
+pub struct JsonUnit {
+    pub name: String,
+    pub version: String,
+}
+
+/// 目标信息
+#[derive(Debug, Serialize)]
+pub struct JsonTarget {
+    pub name: String,
+    pub testing_type: String,
+    pub config: String,
+}
+
+/// 单个步骤的执行结果
+#[derive(Debug, Serialize)]
+pub struct JsonStepResult {
+    pub id: String,
+    pub description: Option<String>,
+    pub status: String,
+    pub exit_code: i32,
+    pub duration_ms: Option<u128>,
+    pub stdout: String,
+    pub stderr: String,
+    pub assertion_error: Option<String>,
+    pub skip_reason: Option<String>,
+    /// 执行期间重建连接的记录
+    pub reconnects: Vec<String>,
+    /// 步骤在模板源文件中的位置
+    pub source: Option<JsonSourceSpan>,
+}
+
+/// 源文件位置
+#[derive(Debug, Serialize)]
+pub struct JsonSourceSpan {
+    /// 模板文件路径（相对于测试目录）
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl JsonReport {
+    /// 由若干执行结果构建报告文档
+    pub fn from_results<'a>(results: impl IntoIterator<Item = &'a ExecutionResult>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            generated_at: chrono::Local::now().to_rfc3339(),
+            results: results
+                .into_iter()
+                .map(JsonTemplateResult::from_result)
+                .collect(),
+        }
+    }
+
+    /// 将报告写入文件
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Unable to create report directory: {}", parent.display())
+            })?; // 无法创建报告目录
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("Unable to write JSON report: {}", path.display()))?; // 无法写入 JSON 报告
+        info!("JSON report generated at: {}", path.display()); // 已生成 JSON 报告
+        Ok(())
+    }
+}
+
+impl JsonTemplateResult {
+    fn from_result(result: &ExecutionResult) -> Self {
+        let template = &result.template;
+        let metadata = &template.metadata;
+        let template_id = template.get_template_id();
+
+        let template_file = template
+            .file_path
+            .strip_prefix(&template.tests_dir)
+            .unwrap_or(&template.file_path)
+            .to_string_lossy()
+            .into_owned();
+
+        let steps = template
+            .steps
+            .iter()
+            .filter_map(|step| {
+                result
+                    .step_results
+                    .get(&utils::get_result_id(&template_id, &step.local_id))
+                    .map(|step_result| (step, step_result))
+            })
+            .map(|(step, step_result)| JsonStepResult {
+                id: step_result.id.clone(),
+                description: step_result.description.clone(),
+                status: step_result.status.as_str().to_string(),
+                exit_code: step_result.exit_code,
+                duration_ms: step_result.duration_ms,
+                stdout: step_result.stdout.clone(),
+                stderr: step_result.stderr.clone(),
+                assertion_error: step_result.assertion_error.clone(),
+                skip_reason: step_result.skip_reason.clone(),
+                reconnects: step_result.reconnects.clone(),
+                source: step.span.as_ref().map(|span| JsonSourceSpan {
+                    file: template_file.clone(),
+                    start_line: span.start_line,
+                    end_line: span.end_line,
+                }),
+            })
+            .collect();
+
+        let variable_prefix = format!("{template_id}::");
+        let variables = result
+            .variables
+            .iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix(&variable_prefix)
+                    .map(|name| (name.to_string(), value.clone()))
+            })
+            .collect();
+
+        Self {
+            template_id,
+            title: metadata.title.clone(),
+            template_file,
+            unit: JsonUnit {
+                name: metadata.unit_name.clone(),
+                version: metadata.unit_version.clone(),
+            },
+            target: JsonTarget {
+                name: metadata.target_config.get_name().to_string(),
+                testing_type: metadata.target_config.testing_type.clone(),
+                config: metadata
+                    .target_config
+                    .get_path()
+                    .to_string_lossy()
+                    .into_owned(),
+            },
+            tags: metadata.tags.clone(),
+            metadata: metadata
+                .custom
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            status: result.overall_status.as_str().to_string(),
+            report_file: result
+                .report_path
+                .as_ref()
+                .map(|p| p.to_string_lossy().into_owned()),
+            error: result.error.clone(),
+            steps,
+            variables,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::test_support::{
+        continue_on_error_options, execute_template, write_template,
+    };
+    use serde_json::Value;
+
+    const SCHEMA: &str = include_str!("../../schemas/report.schema.json");
+
+    /// 实际执行一个模板：`second` 通过并提取变量，`first` 的断言失败
+    fn sample_result(dir: &Path) -> ExecutionResult {
+        let template_path = write_template(
+            dir,
+            "sample.test.md",
+            "title: \"Sample\"\nunit_name: \"sample\"\nunit_version: \"1.2\"\nowner: \"qa\"\n",
+            "\n# Sample\n\n```bash {id=\"second\" exec=true extract.answer=/answer=(\\d+)/}\necho answer=42\n```\n\n```bash {id=\"first\" exec=true assert.stdout_contains=\"2\"}\necho 1\n```\n",
+        );
+        let mut result = execute_template(&template_path, dir, continue_on_error_options());
+        result
+            .variables
+            .insert("other::second::answer".to_string(), "0".to_string());
+        result
+    }
+
+    /// 检查对象包含 schema 中 `required` 列出的全部字段
+    fn assert_required(schema: &Value, value: &Value) {
+        for field in schema["required"].as_array().unwrap() {
+            let field = field.as_str().unwrap();
+            assert!(value.get(field).is_some(), "missing field {field}: {value}");
+        }
+    }
+
+    #[test]
+    fn test_json_report_matches_schema() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let result = sample_result(temp_dir.path());
+        let report = serde_json::to_value(JsonReport::from_results([&result])).unwrap();
+        let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+
+        assert_required(&schema, &report);
+        assert_eq!(
+            report["schema_version"],
+            schema["properties"]["schema_version"]["const"]
+        );
+        let template_result = &report["results"][0];
+        assert_required(&schema["$defs"]["template_result"], template_result);
+        for step in template_result["steps"].as_array().unwrap() {
+            assert_required(&schema["$defs"]["step_result"], step);
+        }
+
+        assert_eq!(template_result["template_id"], "sample");
+        assert_eq!(template_result["unit"]["version"], "1.2");
+        assert_eq!(template_result["target"]["testing_type"], "locally");
+        assert_eq!(template_result["metadata"]["owner"], "qa");
+        assert_eq!(template_result["status"], "Fail");
+        // 步骤按模板中出现的顺序输出
+        let step_ids: Vec<&str> = template_result["steps"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(step_ids, ["heading_1", "second", "first"]);
+        // 步骤带有源文件位置
+        assert_eq!(
+            template_result["steps"][1]["source"],
+            serde_json::json!({"file": "sample.test.md", "start_line": 11, "end_line": 13})
+        );
+        // 断言失败的信息来自实际执行的结果
+        assert!(template_result["steps"][1]["assertion_error"].is_null());
+        assert_eq!(
+            template_result["steps"][2]["assertion_error"],
+            "Stdout does not contain expected pattern: '2'"
+        );
+        // 只包含本模板的变量
+        assert_eq!(template_result["variables"]["second::answer"], "42");
+        assert!(
+            !template_result["variables"]
+                .as_object()
+                .unwrap()
+                .contains_key("other::second::answer")
+        );
+    }
+}
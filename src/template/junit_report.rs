@@ -0,0 +1,332 @@
+//! JUnit XML 格式的测试结果导出
+//!
+//! 供 CI 系统的测试结果页面读取：每个模板对应一个 `<testsuite>`，
+//! 每个可执行的代码块步骤对应一个 `<testcase>`。
+
+use anyhow::{Context, Result};
+use log::info;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::template::StepStatus;
+use crate::template::executor::{ExecutionResult, StepResult};
+use crate::template::step::StepType;
+use crate::utils;
+
+/// 单个测试用例（可执行步骤）的结果
+struct TestCase<'a> {
+    id: &'a str,
+    description: String,
+    /// 因执行中止而没有结果的步骤为 None
+    result: Option<&'a StepResult>,
+}
+
+impl TestCase<'_> {
+    fn is_failure(&self) -> bool {
+        self.result.is_some_and(|r| r.status == StepStatus::Fail)
+    }
+
+    fn is_skipped(&self) -> bool {
+        self.result
+            .is_none_or(|r| !matches!(r.status, StepStatus::Pass | StepStatus::Fail))
+    }
+
+    fn seconds(&self) -> f64 {
+        self.result
+            .and_then(|r| r.duration_ms)
+            .map_or(0.0, |ms| ms as f64 / 1000.0)
+    }
+}
+
+/// 收集模板中所有可执行代码块步骤的结果，按模板中的出现顺序排列
+fn test_cases(result: &ExecutionResult) -> Vec<TestCase<'_>> {
+    let template_id = result.template_id();
+    result
+        .template
+        .steps
+        .iter()
+        .filter(|step| {
+            matches!(step.step_type, StepType::CodeBlock { .. })
+                && step
+                    .original_parsed_step
+                    .as_ref()
+                    .is_some_and(|p| p.executable)
+        })
+        .map(|step| TestCase {
+            id: &step.local_id,
+            description: step.description(),
+            result: result
+                .step_results
+                .get(&utils::get_result_id(&template_id, &step.local_id)),
+        })
+        .collect()
+}
+
+/// 转义 XML 特殊字符，并去掉 XML 1.0 不允许出现的控制字符
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 将若干执行结果渲染为 JUnit XML 文档
+pub fn generate_junit_xml<'a>(results: impl IntoIterator<Item = &'a ExecutionResult>) -> String {
+    let suites: Vec<(&ExecutionResult, Vec<TestCase>)> = results
+        .into_iter()
+        .map(|result| (result, test_cases(result)))
+        .collect();
+    let all_cases = || suites.iter().flat_map(|(_, cases)| cases);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"lintestor\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">",
+        all_cases().count(),
+        all_cases().filter(|c| c.is_failure()).count(),
+        all_cases().filter(|c| c.is_skipped()).count(),
+        all_cases().map(TestCase::seconds).sum::<f64>(),
+    );
+
+    for (result, cases) in &suites {
+        let template_id = escape_xml(&result.template_id());
+        let metadata = &result.template.metadata;
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{template_id}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">",
+            cases.len(),
+            cases.iter().filter(|c| c.is_failure()).count(),
+            cases.iter().filter(|c| c.is_skipped()).count(),
+            cases.iter().map(TestCase::seconds).sum::<f64>(),
+        );
+        xml.push_str("    <properties>\n");
+        for (name, value) in [
+            ("title", metadata.title.as_str()),
+            ("unit_name", metadata.unit_name.as_str()),
+            ("unit_version", metadata.unit_version.as_str()),
+            ("target", metadata.target_config.get_name()),
+            ("testing_type", metadata.target_config.testing_type.as_str()),
+        ] {
+            let _ = writeln!(
+                xml,
+                "      <property name=\"{name}\" value=\"{}\"/>",
+                escape_xml(value)
+            );
+        }
+        xml.push_str("    </properties>\n");
+
+        for case in cases {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{template_id}\" time=\"{:.3}\">",
+                escape_xml(case.id),
+                case.seconds()
+            );
+            let _ = writeln!(
+                xml,
+                "      <properties><property name=\"description\" value=\"{}\"/></properties>",
+                escape_xml(&case.description)
+            );
+            match case.result {
+                Some(step) if step.status == StepStatus::Fail => {
+                    let message = step
+                        .assertion_error
+                        .clone()
+                        .unwrap_or_else(|| format!("exit code {}", step.exit_code));
+                    let _ = writeln!(
+                        xml,
+                        "      <failure message=\"{}\" type=\"AssertionError\">{}</failure>",
+                        escape_xml(message.lines().next().unwrap_or_default()),
+                        escape_xml(&message)
+                    );
+                }
+                Some(step) if case.is_skipped() => {
+                    let message = step.skip_reason.as_deref().unwrap_or("step was skipped");
+                    let _ = writeln!(xml, "      <skipped message=\"{}\"/>", escape_xml(message));
+                }
+                Some(_) => {}
+                None => xml.push_str("      <skipped message=\"step was not executed\"/>\n"),
+            }
+            if let Some(step) = case.result {
+                if !step.stdout.is_empty() {
+                    let _ = writeln!(
+                        xml,
+                        "      <system-out>{}</system-out>",
+                        escape_xml(&step.stdout)
+                    );
+                }
+                if !step.stderr.is_empty() {
+                    let _ = writeln!(
+                        xml,
+                        "      <system-err>{}</system-err>",
+                        escape_xml(&step.stderr)
+                    );
+                }
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// 将执行结果写入 JUnit XML 文件
+pub fn write_junit_report<'a>(
+    path: &Path,
+    results: impl IntoIterator<Item = &'a ExecutionResult>,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Unable to create report directory: {}", parent.display()))?; // 无法创建报告目录
+    }
+    fs::write(path, generate_junit_xml(results))
+        .with_context(|| format!("Unable to write JUnit report: {}", path.display()))?; // 无法写入 JUnit 报告
+    info!("JUnit report generated at: {}", path.display()); // 已生成 JUnit 报告
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::TestTemplate;
+    use crate::template::test_support::{
+        continue_on_error_options, execute_template, write_template,
+    };
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    const FRONT_MATTER: &str = "title: \"Sample\"\nunit_name: \"sample\"\nunit_version: \"1\"\n";
+
+    const TEMPLATE: &str = r#"
+
+# Sample
+
+```bash {id="ok" exec=true}
+echo ok
+```
+
+```bash {id="broken" exec=true assert.exit_code=0}
+exit 1
+```
+
+```bash {id="child" exec=true depends_on=["broken"]}
+echo child
+```
+
+```bash {id="never" exec=true}
+echo never
+```
+
+```bash {id="doc" exec=false}
+echo not a test
+```
+"#;
+
+    fn step(id: &str, status: StepStatus) -> StepResult {
+        StepResult {
+            id: id.to_string(),
+            description: None,
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms: Some(250),
+            assertion_error: None,
+            skip_reason: None,
+            reconnects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_generate_junit_xml() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let path = write_template(dir, "sample.test.md", FRONT_MATTER, TEMPLATE);
+        let template = Arc::new(TestTemplate::from_file(&path, dir).unwrap());
+
+        let step_results = HashMap::from([
+            (
+                "sample::ok".to_string(),
+                StepResult {
+                    stdout: "ok <done> & more\n".to_string(),
+                    ..step("ok", StepStatus::Pass)
+                },
+            ),
+            (
+                "sample::broken".to_string(),
+                StepResult {
+                    exit_code: 1,
+                    stderr: "boom\u{1b}[0m\n".to_string(),
+                    assertion_error: Some("Expected exit code 0, got 1".to_string()),
+                    ..step("broken", StepStatus::Fail)
+                },
+            ),
+            (
+                "sample::child".to_string(),
+                StepResult {
+                    skip_reason: Some("dependency broken failed".to_string()),
+                    ..step("child", StepStatus::Skipped)
+                },
+            ),
+        ]);
+        let result = ExecutionResult {
+            template,
+            unit_name: "sample".to_string(),
+            target_name: "local".to_string(),
+            overall_status: StepStatus::Fail,
+            step_results,
+            variables: HashMap::new(),
+            report_path: None,
+            error: None,
+        };
+
+        let xml = generate_junit_xml([&result]);
+        assert!(xml.contains(
+            "<testsuites name=\"lintestor\" tests=\"4\" failures=\"1\" errors=\"0\" skipped=\"2\" time=\"0.750\">"
+        ));
+        assert!(xml.contains("<testsuite name=\"sample\" tests=\"4\" failures=\"1\""));
+        assert!(xml.contains("<testcase name=\"ok\" classname=\"sample\" time=\"0.250\">"));
+        assert!(xml.contains("<system-out>ok &lt;done&gt; &amp; more\n</system-out>"));
+        assert!(xml.contains(
+            "<failure message=\"Expected exit code 0, got 1\" type=\"AssertionError\">Expected exit code 0, got 1</failure>"
+        ));
+        // 控制字符会被去掉
+        assert!(xml.contains("<system-err>boom[0m\n</system-err>"));
+        assert!(xml.contains("<skipped message=\"dependency broken failed\"/>"));
+        assert!(xml.contains(
+            "<testcase name=\"never\" classname=\"sample\" time=\"0.000\">\n      <properties><property name=\"description\" value=\"echo never\"/></properties>\n      <skipped message=\"step was not executed\"/>"
+        ));
+        assert!(!xml.contains("name=\"doc\""));
+        assert_eq!(xml.matches("<testcase ").count(), 4);
+    }
+
+    #[test]
+    fn test_junit_failure_from_executed_assertion() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let path = write_template(
+            dir,
+            "sample.test.md",
+            FRONT_MATTER,
+            "\n# Sample\n\n```bash {id=\"greet\" exec=true assert.exit_code=0 assert.stdout_contains=\"bye\"}\necho hello\n```\n",
+        );
+        let result = execute_template(&path, dir, continue_on_error_options());
+
+        let xml = generate_junit_xml([&result]);
+        assert!(xml.contains(
+            "<failure message=\"Stdout does not contain expected pattern: &apos;bye&apos;\" type=\"AssertionError\">Stdout does not contain expected pattern: &apos;bye&apos;</failure>"
+        ), "{xml}");
+    }
+}
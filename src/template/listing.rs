@@ -0,0 +1,232 @@
+//! 模板列表
+//!
+//! `lintestor list` 的实现：在执行前列出筛选后的模板、所属单元、标签和目标，
+//! 以及可选的按执行顺序排列的步骤及其依赖。支持表格和 JSON 两种输出。
+
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::template::TestTemplate;
+use crate::template::dependency::StepDependencyManager;
+use crate::template::step::{ExecutionStep, StepType};
+
+/// 模板列表
+#[derive(Debug, Serialize)]
+pub struct TemplateListing {
+    pub templates: Vec<ListedTemplate>,
+}
+
+/// 列表中的一个模板
+#[derive(Debug, Serialize)]
+pub struct ListedTemplate {
+    pub id: String,
+    pub title: String,
+    pub unit: String,
+    pub tags: Vec<String>,
+    pub target: String,
+    pub testing_type: String,
+    pub file: String,
+    /// 按执行顺序排列的步骤，未指定 `--steps` 时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<ListedStep>>,
+}
+
+/// 列表中的一个步骤
+#[derive(Debug, Serialize)]
+pub struct ListedStep {
+    pub id: String,
+    /// 步骤类型：heading、code 或 output
+    pub kind: &'static str,
+    pub description: String,
+    /// 直接依赖的步骤（包括隐式依赖和其他模板中的步骤），按名称排序
+    pub depends_on: Vec<String>,
+}
+
+/// 步骤类型名称
+fn step_kind(step: &ExecutionStep) -> &'static str {
+    match step.step_type {
+        StepType::Heading { .. } => "heading",
+        StepType::CodeBlock { .. } => "code",
+        StepType::OutputPlaceholder => "output",
+    }
+}
+
+/// 按执行器的方式解析模板的执行顺序
+///
+/// 依赖其他模板的步骤在执行时由批次调度保证顺序，因此不进入依赖图，只在依赖列表中显示
+fn resolve_steps(template: &TestTemplate) -> Result<Vec<ListedStep>> {
+    let template_id = template.get_template_id();
+    let mut external_dependencies: HashMap<String, Vec<String>> = HashMap::new();
+    let local_steps: Vec<ExecutionStep> = template
+        .steps
+        .iter()
+        .cloned()
+        .map(|mut step| {
+            let (local, external): (HashSet<_>, HashSet<_>) =
+                step.dependencies.drain().partition(|dep| {
+                    dep.split_once("::")
+                        .is_none_or(|(dep_template_id, _)| dep_template_id == template_id)
+                });
+            if !external.is_empty() {
+                external_dependencies.insert(step.id.clone(), external.into_iter().collect());
+            }
+            step.dependencies = local;
+            step
+        })
+        .collect();
+
+    let mut manager = StepDependencyManager::new();
+    manager.add_steps(local_steps);
+    manager
+        .build_graph()
+        .with_context(|| format!("Failed to resolve dependencies for template {template_id}"))?; // 无法解析模板的依赖
+    let order = manager.get_execution_order().map_err(|e| {
+        anyhow!("Failed to resolve execution order for template {template_id}: {e}")
+    })?; // 无法确定模板的执行顺序
+
+    order
+        .iter()
+        .map(|step_id| {
+            let step = manager
+                .get_step(step_id)
+                .ok_or_else(|| anyhow!("Step {step_id} is missing from the dependency graph"))?; // 依赖图中缺少该步骤
+            let mut depends_on: Vec<String> = manager
+                .get_dependencies(step_id)
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            if let Some(external) = external_dependencies.get(step_id) {
+                depends_on.extend(external.iter().cloned());
+            }
+            depends_on.sort();
+            Ok(ListedStep {
+                id: step.id.clone(),
+                kind: step_kind(step),
+                description: step.description(),
+                depends_on,
+            })
+        })
+        .collect()
+}
+
+/// 转义 Markdown 表格中的竖线，并把换行替换为空格
+fn escape_cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+impl TemplateListing {
+    /// 由筛选后的模板构建列表，`include_steps` 为 true 时解析每个模板的执行顺序
+    pub fn from_templates<'a>(
+        templates: impl IntoIterator<Item = &'a TestTemplate>,
+        include_steps: bool,
+    ) -> Result<Self> {
+        let mut listed = templates
+            .into_iter()
+            .map(|template| {
+                let metadata = &template.metadata;
+                Ok(ListedTemplate {
+                    id: template.get_template_id(),
+                    title: metadata.title.clone(),
+                    unit: metadata.unit_name.clone(),
+                    tags: metadata.tags.clone(),
+                    target: metadata.target_config.get_name().to_string(),
+                    testing_type: metadata.target_config.get_testing_type().to_string(),
+                    file: template.file_path.to_string_lossy().into_owned(),
+                    steps: include_steps.then(|| resolve_steps(template)).transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        listed.sort_by(|a, b| (&a.target, &a.id).cmp(&(&b.target, &b.id)));
+        Ok(Self { templates: listed })
+    }
+
+    /// 渲染为表格：先是模板表，指定了步骤时每个模板再列出一张步骤表
+    pub fn to_table(&self) -> String {
+        if self.templates.is_empty() {
+            return "No templates found.\n".to_string();
+        }
+
+        let mut content = String::from("| Template ID | Title | Unit | Tags | Target | Type |\n");
+        content.push_str("|-------------|-------|------|------|--------|------|\n");
+        for template in &self.templates {
+            content.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                escape_cell(&template.id),
+                escape_cell(&template.title),
+                escape_cell(&template.unit),
+                escape_cell(&template.tags.join(", ")),
+                escape_cell(&template.target),
+                escape_cell(&template.testing_type)
+            ));
+        }
+
+        for template in &self.templates {
+            let Some(steps) = &template.steps else {
+                continue;
+            };
+            content.push_str(&format!("\n## {} ({})\n\n", template.id, template.target));
+            content.push_str("| # | Step ID | Kind | Description | Depends On |\n");
+            content.push_str("|---|---------|------|-------------|------------|\n");
+            for (index, step) in steps.iter().enumerate() {
+                content.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    index + 1,
+                    escape_cell(&step.id),
+                    step.kind,
+                    escape_cell(&step.description),
+                    escape_cell(&step.depends_on.join(", "))
+                ));
+            }
+        }
+        content
+    }
+
+    /// 渲染为 JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::test_support::write_template;
+
+    #[test]
+    fn test_listing_with_steps() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let path = write_template(
+            dir,
+            "sample.test.md",
+            "title: \"Sample | demo\"\nunit_name: \"sample\"\nunit_version: \"1\"\ntags: [\"smoke\", \"net\"]\n",
+            "\n# Sample\n\n```bash {id=\"second\" exec=true depends_on=[\"first\"]}\necho second\n```\n\n```bash {id=\"first\" exec=true}\necho first\n```\n",
+        );
+        let template = TestTemplate::from_file(&path, dir).unwrap();
+
+        let listing = TemplateListing::from_templates([&template], false).unwrap();
+        assert!(listing.templates[0].steps.is_none());
+        let table = listing.to_table();
+        assert!(table.contains("| sample | Sample \\| demo | sample | smoke, net | "));
+        assert!(!table.contains("## sample"));
+
+        let listing = TemplateListing::from_templates([&template], true).unwrap();
+        let steps = listing.templates[0].steps.as_ref().unwrap();
+        let position = |id: &str| steps.iter().position(|s| s.id == id).unwrap();
+        assert!(position("sample::first") < position("sample::second"));
+        let second = &steps[position("sample::second")];
+        assert_eq!(second.kind, "code");
+        assert!(second.depends_on.contains(&"sample::first".to_string()));
+
+        let table = listing.to_table();
+        assert!(table.contains("| # | Step ID | Kind | Description | Depends On |"));
+        let json: serde_json::Value = serde_json::from_str(&listing.to_json().unwrap()).unwrap();
+        assert_eq!(json["templates"][0]["tags"][1], "net");
+        assert!(json["templates"][0]["steps"].is_array());
+    }
+}
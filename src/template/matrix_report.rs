@@ -0,0 +1,298 @@
+//! 跨目标兼容性矩阵报告
+//!
+//! 汇总一次运行中所有目标分组的执行结果：行为测试单元，列为目标，
+//! 单元格显示该单元在该目标上的测试状态和 `unit_version`。同时输出 Markdown 和 JSON 两种格式。
+
+use anyhow::{Context, Result};
+use log::info;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::template::StepStatus;
+use crate::template::executor::ExecutionResult;
+
+/// 兼容性矩阵
+#[derive(Debug, Serialize)]
+pub struct CompatibilityMatrix {
+    /// 报告生成时间（RFC 3339）
+    pub generated_at: String,
+    /// 按名称排序的目标列表（矩阵的列）
+    pub targets: Vec<String>,
+    /// 按名称排序的测试单元（矩阵的行）
+    pub units: Vec<MatrixUnit>,
+}
+
+/// 矩阵中的一行
+#[derive(Debug, Serialize)]
+pub struct MatrixUnit {
+    pub name: String,
+    /// 键为目标名称，未在该目标上测试的单元没有对应的键
+    pub cells: BTreeMap<String, MatrixCell>,
+}
+
+/// 单元在某个目标上的测试结果
+#[derive(Debug, Serialize)]
+pub struct MatrixCell {
+    /// 该单元在该目标上所有模板的汇总状态：任一模板失败即为 Fail
+    pub status: String,
+    /// 模板中声明的 `unit_version`，多个模板声明不同版本时以逗号分隔
+    pub unit_version: String,
+    /// 参与汇总的模板ID
+    pub templates: Vec<String>,
+}
+
+/// 汇总多个模板的状态：任一失败即失败，否则有通过即通过
+fn merge_status(current: &StepStatus, next: &StepStatus) -> StepStatus {
+    match (current, next) {
+        (StepStatus::Fail, _) | (_, StepStatus::Fail) => StepStatus::Fail,
+        (StepStatus::Pass, _) | (_, StepStatus::Pass) => StepStatus::Pass,
+        _ => current.clone(),
+    }
+}
+
+/// 单元格中显示的状态图标
+fn status_icon(status: &str) -> &'static str {
+    match status {
+        "Pass" => "✅ Pass",
+        "Fail" => "❌ Fail",
+        "Skipped" => "⚠️ Skipped",
+        "Blocked" => "❓ Blocked",
+        _ => "❓ Not Run",
+    }
+}
+
+/// 转义 Markdown 表格中的竖线
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+impl CompatibilityMatrix {
+    /// 由所有目标分组的执行结果构建矩阵
+    pub fn from_results<'a>(results: impl IntoIterator<Item = &'a ExecutionResult>) -> Self {
+        struct CellBuilder {
+            status: StepStatus,
+            versions: BTreeSet<String>,
+            templates: BTreeSet<String>,
+        }
+
+        let mut targets = BTreeSet::new();
+        let mut cells: BTreeMap<String, BTreeMap<String, CellBuilder>> = BTreeMap::new();
+        for result in results {
+            let metadata = &result.template.metadata;
+            targets.insert(result.target_name.clone());
+            let cell = cells
+                .entry(metadata.unit_name.clone())
+                .or_default()
+                .entry(result.target_name.clone())
+                .or_insert_with(|| CellBuilder {
+                    status: result.overall_status.clone(),
+                    versions: BTreeSet::new(),
+                    templates: BTreeSet::new(),
+                });
+            cell.status = merge_status(&cell.status, &result.overall_status);
+            if !metadata.unit_version.is_empty() {
+                cell.versions.insert(metadata.unit_version.clone());
+            }
+            cell.templates.insert(result.template.get_template_id());
+        }
+
+        Self {
+            generated_at: chrono::Local::now().to_rfc3339(),
+            targets: targets.into_iter().collect(),
+            units: cells
+                .into_iter()
+                .map(|(name, cells)| MatrixUnit {
+                    name,
+                    cells: cells
+                        .into_iter()
+                        .map(|(target, cell)| {
+                            (
+                                target,
+                                MatrixCell {
+                                    status: cell.status.as_str().to_string(),
+                                    unit_version: cell
+                                        .versions
+                                        .into_iter()
+                                        .collect::<Vec<_>>()
+                                        .join(", "),
+                                    templates: cell.templates.into_iter().collect(),
+                                },
+                            )
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// 渲染为带标题的 Markdown 文档
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "# Compatibility Matrix ({})\n\n{}",
+            self.generated_at,
+            self.to_markdown_table()
+        )
+    }
+
+    /// 渲染为 Markdown 表格
+    pub fn to_markdown_table(&self) -> String {
+        if self.units.is_empty() {
+            return "No test results.\n".to_string();
+        }
+
+        let mut content = String::from("| Unit |");
+        for target in &self.targets {
+            content.push_str(&format!(" {} |", escape_cell(target)));
+        }
+        content.push_str("\n|------|");
+        for _ in &self.targets {
+            content.push_str("------|");
+        }
+        content.push('\n');
+
+        for unit in &self.units {
+            content.push_str(&format!("| {} |", escape_cell(&unit.name)));
+            for target in &self.targets {
+                match unit.cells.get(target) {
+                    Some(cell) if cell.unit_version.is_empty() => {
+                        content.push_str(&format!(" {} |", status_icon(&cell.status)));
+                    }
+                    Some(cell) => content.push_str(&format!(
+                        " {} ({}) |",
+                        status_icon(&cell.status),
+                        escape_cell(&cell.unit_version)
+                    )),
+                    None => content.push_str(" - |"),
+                }
+            }
+            content.push('\n');
+        }
+        content
+    }
+
+    /// 写入 Markdown 和 JSON 两个文件
+    ///
+    /// `output` 以 `.json` 结尾时 JSON 写入该路径、Markdown 写入同名 `.md` 文件，
+    /// 否则 Markdown 写入该路径、JSON 写入同名 `.json` 文件。返回两个文件的路径。
+    pub fn write_to(&self, output: &Path) -> Result<(PathBuf, PathBuf)> {
+        let (markdown_path, json_path) = if output.extension().is_some_and(|ext| ext == "json") {
+            (output.with_extension("md"), output.to_path_buf())
+        } else {
+            (output.to_path_buf(), output.with_extension("json"))
+        };
+        if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Unable to create report directory: {}", parent.display())
+            })?; // 无法创建报告目录
+        }
+        fs::write(&markdown_path, self.to_markdown()).with_context(|| {
+            format!(
+                "Unable to write compatibility matrix: {}",
+                markdown_path.display()
+            )
+        })?; // 无法写入兼容性矩阵
+        fs::write(&json_path, serde_json::to_string_pretty(self)?).with_context(|| {
+            format!(
+                "Unable to write compatibility matrix: {}",
+                json_path.display()
+            )
+        })?; // 无法写入兼容性矩阵
+        info!(
+            "Compatibility matrix generated at: {} and {}",
+            markdown_path.display(),
+            json_path.display()
+        ); // 已生成兼容性矩阵
+        Ok((markdown_path, json_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::TestTemplate;
+    use crate::template::test_support::write_template;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    /// 在临时目录中创建模板并构造在指定目标上的执行结果
+    fn result(
+        dir: &Path,
+        file: &str,
+        unit: &str,
+        version: &str,
+        target: &str,
+        status: StepStatus,
+    ) -> ExecutionResult {
+        let path = write_template(
+            dir,
+            file,
+            &format!("title: \"{file}\"\nunit_name: \"{unit}\"\nunit_version: \"{version}\"\n"),
+            &format!("\n# {file}\n"),
+        );
+        let template = Arc::new(TestTemplate::from_file(path, dir).unwrap());
+        ExecutionResult {
+            template,
+            unit_name: unit.to_string(),
+            target_name: target.to_string(),
+            overall_status: status,
+            step_results: HashMap::new(),
+            variables: HashMap::new(),
+            report_path: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_compatibility_matrix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let results = [
+            result(
+                dir,
+                "curl_a.test.md",
+                "curl",
+                "8.5.0",
+                "riscv",
+                StepStatus::Pass,
+            ),
+            result(
+                dir,
+                "curl_b.test.md",
+                "curl",
+                "8.5.0",
+                "arm",
+                StepStatus::Pass,
+            ),
+            result(
+                dir,
+                "curl_c.test.md",
+                "curl",
+                "8.4.0",
+                "arm",
+                StepStatus::Fail,
+            ),
+            result(dir, "wget.test.md", "wget", "1.21", "arm", StepStatus::Pass),
+        ];
+
+        let matrix = CompatibilityMatrix::from_results(&results);
+        assert_eq!(matrix.targets, ["arm", "riscv"]);
+        let curl_arm = &matrix.units[0].cells["arm"];
+        assert_eq!(curl_arm.status, "Fail");
+        assert_eq!(curl_arm.unit_version, "8.4.0, 8.5.0");
+        assert_eq!(curl_arm.templates, ["curl_b", "curl_c"]);
+
+        let markdown = matrix.to_markdown();
+        assert!(markdown.contains("| Unit | arm | riscv |\n|------|------|------|\n"));
+        assert!(markdown.contains("| curl | ❌ Fail (8.4.0, 8.5.0) | ✅ Pass (8.5.0) |\n"));
+        assert!(markdown.contains("| wget | ✅ Pass (1.21) | - |\n"));
+
+        let (markdown_path, json_path) = matrix.write_to(&dir.join("out/matrix.json")).unwrap();
+        assert_eq!(markdown_path, dir.join("out/matrix.md"));
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(json_path).unwrap()).unwrap();
+        assert_eq!(json["units"][1]["cells"]["arm"]["unit_version"], "1.21");
+        assert!(json["units"][1]["cells"].get("riscv").is_none());
+    }
+}
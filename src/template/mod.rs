@@ -4,28 +4,49 @@
 //! 这些模板定义了针对特定单元在特定目标上的测试步骤和预期结果。
 
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 mod batch_executor;
 mod dependency;
 mod discovery;
+mod error;
 pub mod executor; // Changed to public
+pub mod history;
+mod html_report;
+mod json_report;
+mod junit_report;
+mod listing;
+mod matrix_report;
 mod parser;
 mod reporter;
+mod summary_report;
+mod tap_report;
+#[cfg(test)]
+mod test_support;
+mod validator;
 mod variable;
 
 // Re-export types from step.rs
 pub mod step;
-pub use step::ExecutionStep;
+pub use step::{ExecutionStep, SourceSpan};
 
+use crate::config::cli_args::ReportFormat;
 use crate::config::target_config::TargetConfig;
 use crate::utils;
 // Import ContentBlock from parser, and the new parsing function
 pub use batch_executor::BatchExecutor;
-pub use discovery::{TemplateFilter, discover_templates, filter_templates};
+pub use discovery::{LoadedTemplates, TemplateFilter, discover_templates, load_templates};
+pub use error::TemplateError;
 pub use executor::{ExecutionResult, ExecutorOptions};
+pub use json_report::JsonReport;
+pub use listing::TemplateListing;
+pub use matrix_report::CompatibilityMatrix;
 pub use parser::ContentBlock;
+pub use summary_report::write_summary_report;
+pub use tap_report::TapStream;
+pub use validator::{Diagnostic, validate_template};
 pub use variable::VariableManager; // Added StepDependencyManager
 
 /// Options for controlling batch execution
@@ -41,6 +62,13 @@ pub struct BatchOptions {
     /// Report should be generated report directory in flat structure
     /// or keep the original directory structure
     pub keep_template_directory_structure: bool,
+    /// Report formats to generate (Markdown is always generated)
+    pub report_formats: Vec<ReportFormat>,
+    /// TAP stream shared by all batches of one run (set for `--format tap`)
+    pub tap_stream: Option<Arc<TapStream>>,
+    /// Ask on the terminal for corrections when an assertion or extraction fails
+    /// (only when stdin is a terminal and a single target group runs at a time)
+    pub interactive: bool,
 }
 
 /// 外部模板引用
@@ -54,6 +82,13 @@ pub struct TemplateReference {
     pub namespace: String,
 }
 
+impl TemplateReference {
+    /// 被引用模板的模板ID
+    pub fn template_id(&self, tests_dir: &Path) -> String {
+        utils::get_template_id_from_path(tests_dir, Path::new(&self.template_path))
+    }
+}
+
 /// Markdown测试模板元数据（YAML前置数据）
 #[derive(Debug, Clone)]
 pub struct TemplateMetadata {
@@ -184,6 +219,50 @@ impl TestTemplate {
         utils::get_template_id_from_path(&self.tests_dir, &self.file_path)
     }
 
+    /// 获取本模板依赖的其他模板ID：`references` 中引用的模板，以及 `depends_on` 中跨模板依赖的模板
+    pub fn referenced_template_ids(&self) -> BTreeSet<String> {
+        let own_id = self.get_template_id();
+        let mut ids: BTreeSet<String> = self
+            .metadata
+            .references
+            .iter()
+            .map(|r| r.template_id(&self.tests_dir))
+            .collect();
+        for step in &self.steps {
+            for dep in &step.dependencies {
+                if let Some((template_id, _)) = dep.split_once("::") {
+                    ids.insert(template_id.to_string());
+                }
+            }
+        }
+        ids.remove(&own_id);
+        ids
+    }
+
+    /// 为加载失败的模板文件创建占位模板
+    ///
+    /// 占位模板没有步骤，标题和单元名称使用模板ID，目标为 `TargetConfig::unknown()`，
+    /// 只用于在汇总报告中显示该模板的失败结果
+    pub fn placeholder(path: &Path, tests_dir: &Path) -> Self {
+        let template_id = utils::get_template_id_from_path(tests_dir, path);
+        TestTemplate {
+            metadata: TemplateMetadata {
+                title: template_id.clone(),
+                target_config: TargetConfig::unknown(),
+                unit_name: template_id,
+                unit_version: String::new(),
+                tags: Vec::new(),
+                references: Vec::new(),
+                custom: HashMap::new(),
+            },
+            steps: Vec::new(),
+            file_path: path.to_path_buf(),
+            tests_dir: tests_dir.to_path_buf(),
+            raw_content: String::new(),
+            content_blocks: Vec::new(),
+        }
+    }
+
     /// 从模板文件路径创建测试模板
     pub fn from_file<P: AsRef<Path>>(path: P, tests_dir: &Path) -> Result<Self> {
         let path = path.as_ref();
@@ -10,10 +10,11 @@ use std::path::Path;
 
 use crate::config::target_config::TargetConfig;
 // Import the new ExecutionStep related types
-use crate::template::step::{ExecutionStep, GlobalStepId, StepType};
+use crate::template::step::{ExecutionStep, GlobalStepId, SourceSpan, StepType};
 // Import ParsedTestStep directly, ContentBlock is defined in this file
 use crate::template::{
-    AssertionType, DataExtraction, ParsedTestStep, TemplateMetadata, TemplateReference,
+    AssertionType, DataExtraction, ParsedTestStep, TemplateError, TemplateMetadata,
+    TemplateReference,
 };
 use crate::utils;
 
@@ -35,6 +36,8 @@ pub enum ContentBlock {
         text: String,
         /// 标题的原始属性（如 {id="..."}）
         attributes: HashMap<String, String>,
+        /// 标题在源文件中的位置
+        span: SourceSpan,
     },
 
     /// 结构化的代码块，包含id、语言、代码内容和属性。
@@ -47,11 +50,18 @@ pub enum ContentBlock {
         code: String,
         /// 代码块的原始属性（如 {id="...", depends_on="..."}）
         attributes: HashMap<String, String>,
+        /// 代码块在源文件中的位置
+        span: SourceSpan,
     },
 
     /// 代表一个步骤输出的占位符。
     /// 例如 ` ```output {ref="step_id"} ... ``` `。
-    OutputBlock { step_id: String, stream: String },
+    OutputBlock {
+        step_id: String,
+        stream: String,
+        /// 输出块在源文件中的位置
+        span: SourceSpan,
+    },
 
     /// 通用 Markdown 文本块。
     /// 这可以包含任何 Markdown内容，包括原始的步骤定义文本（如果它们不被特殊处理为其他类型的块）。
@@ -92,9 +102,21 @@ pub fn parse_template_into_content_blocks_and_steps(
     let template_id = utils::get_template_id_from_path(tests_dir, file_path);
     debug!("Generated template ID: {template_id}"); // 生成的模板 ID: {template_id}
 
+    // 正文是文件内容的后缀，据此把正文中的偏移换算为文件行号
+    let locator = SpanLocator {
+        file: file_path,
+        lines: LineIndex::new(content),
+        body_offset: content.len() - markdown_content.len(),
+    };
+
     // 同时解析步骤和内容块
-    let (execution_steps, md_content_blocks) =
-        parse_markdown_to_steps_and_content_blocks(markdown_content, &template_id, &metadata)?;
+    let (execution_steps, md_content_blocks) = parse_markdown_to_steps_and_content_blocks(
+        markdown_content,
+        &template_id,
+        &metadata,
+        tests_dir,
+        &locator,
+    )?;
     content_blocks.extend(md_content_blocks);
 
     info!(
@@ -122,11 +144,92 @@ pub fn parse_template_into_content_blocks_and_steps(
     Ok((metadata, execution_steps, content_blocks))
 }
 
+/// 字节偏移到行号的索引，由解析器和校验器共用
+pub(super) struct LineIndex {
+    /// 每一行在文件中的起始字节偏移
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(super) fn new(content: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    /// 字节偏移所在的行号（从 1 开始）
+    pub(super) fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+}
+
+/// 把正文中的字节区间换算为源文件中的位置
+struct SpanLocator<'a> {
+    file: &'a Path,
+    lines: LineIndex,
+    /// 正文在文件内容中的起始偏移
+    body_offset: usize,
+}
+
+impl SpanLocator<'_> {
+    fn span(&self, start: usize, end: usize) -> SourceSpan {
+        let last = end.saturating_sub(1).max(start);
+        SourceSpan {
+            file: self.file.to_path_buf(),
+            start_line: self.lines.line_of(self.body_offset + start),
+            end_line: self.lines.line_of(self.body_offset + last),
+        }
+    }
+}
+
+/// 模板正文中各类块的正则表达式，由解析器和校验器共用
+pub(super) struct BlockPatterns {
+    /// 标题行，捕获组：级别、文本、属性
+    pub heading: Regex,
+    /// bash 代码块，捕获组：语言、带花括号的属性、属性、代码
+    pub code_block: Regex,
+    /// 输出占位块，捕获组：属性
+    pub output_block: Regex,
+    /// 按标题、输出块、摘要表占位符、代码块的顺序组合的表达式，使用命名捕获组区分
+    pub combined: Regex,
+}
+
+impl BlockPatterns {
+    pub(super) fn new() -> Result<Self> {
+        let heading = Regex::new(r"(?m)^(#+)\s+(.*?)(?:\s+\{([^}]*)\}\s*|\s*)$")?;
+        let code_block = Regex::new(r"(?ms)```(bash)\s*(\{([^}]*)\})?\n(.*?)```")?;
+        let output_block = match Regex::new(r#"(?ms)^```output\s*\{([^\r\n}]*)\}.*?^```\s*$"#) {
+            Ok(re) => re,
+            Err(e) => {
+                error!("Failed to compile regex: {e}"); // 正则表达式编译失败: {e}
+                return Err(anyhow!("Failed to compile regex: {}", e)); // 正则表达式编译失败: {}
+            }
+        };
+        let summary_table = Regex::new(r#"(?im)^\s*<!--\s*LINTESOR_SUMMARY_TABLE\s*-->\s*$"#)?;
+        let combined = Regex::new(&format!(
+            "(?P<heading>{})|(?P<output_block>{})|(?P<summary_table>{})|(?P<code_block>{})",
+            heading.as_str(),
+            output_block.as_str(),
+            summary_table.as_str(),
+            code_block.as_str()
+        ))?;
+        Ok(Self {
+            heading,
+            code_block,
+            output_block,
+            combined,
+        })
+    }
+}
+
 /// 从Markdown内容中解析出 ExecutionSteps 和 ContentBlocks
 fn parse_markdown_to_steps_and_content_blocks(
     markdown: &str,
     template_id: &str,
     metadata: &TemplateMetadata,
+    tests_dir: &Path,
+    locator: &SpanLocator,
 ) -> Result<(Vec<ExecutionStep>, Vec<ContentBlock>)> {
     debug!(
         "Starting to parse Markdown content into ExecutionSteps and ContentBlocks (template_id: {template_id})"
@@ -135,32 +238,20 @@ fn parse_markdown_to_steps_and_content_blocks(
     let mut content_blocks = Vec::new();
     let mut all_local_ids: HashSet<String> = HashSet::new();
     let all_depends_refs: Vec<(String, String)> = Vec::new(); // (当前step global_id, depends_on的原始id)
-    let heading_re = Regex::new(r"(?m)^(#+)\s+(.*?)(?:\s+\{([^}]*)\}\s*|\s*)$")?;
-    let code_block_re = Regex::new(r"(?ms)```(bash)\s*(\{([^}]*)\})?\n(.*?)```")?;
-    let output_block_re = match Regex::new(r#"(?ms)^```output\s*\{([^\r\n}]*)\}.*?^```\s*$"#) {
-        Ok(re) => re,
-        Err(e) => {
-            error!("Failed to compile regex: {e}"); // 正则表达式编译失败: {e}
-            return Err(anyhow!("Failed to compile regex: {}", e)); // 正则表达式编译失败: {}
-        }
-    };
-    let summary_table_re = Regex::new(r#"(?im)^\s*<!--\s*LINTESOR_SUMMARY_TABLE\s*-->\s*$"#)?;
+    let patterns = BlockPatterns::new()?;
+    let heading_re = &patterns.heading;
+    let code_block_re = &patterns.code_block;
+    let output_block_re = &patterns.output_block;
+    let combined_re = &patterns.combined;
 
     let mut current_heading_stack: Vec<(GlobalStepId, u8, Vec<GlobalStepId>)> = Vec::new(); // (id, level, children)
     let mut local_id_counter = 0;
     let mut last_match_end = 0;
-    let combined_re_str = format!(
-        "(?P<heading>{})|(?P<output_block>{})|(?P<summary_table>{})|(?P<code_block>{})",
-        heading_re.as_str(),
-        output_block_re.as_str(),
-        summary_table_re.as_str(),
-        code_block_re.as_str()
-    );
-    let combined_re = Regex::new(&combined_re_str)?;
 
     for captures in combined_re.captures_iter(markdown) {
         let match_start = captures.get(0).unwrap().start();
         let match_end = captures.get(0).unwrap().end();
+        let span = locator.span(match_start, match_end);
         if match_start > last_match_end {
             let text_segment = &markdown[last_match_end..match_start];
             if !text_segment.trim().is_empty() {
@@ -174,7 +265,8 @@ fn parse_markdown_to_steps_and_content_blocks(
                 let level = caps.get(1).map_or(0, |m| m.as_str().len() as u8);
                 let text = caps.get(2).map_or("", |m| m.as_str()).trim().to_string();
                 let attributes_str = caps.get(3).map_or("", |m| m.as_str()).trim();
-                let attributes = parse_inline_attributes(attributes_str);
+                let attributes = parse_inline_attributes(attributes_str)
+                    .with_context(|| format!("Invalid attributes at {span}"))?; // 属性格式错误
                 let local_id = attributes.get("id").cloned().unwrap_or_else(|| {
                     local_id_counter += 1;
                     format!("heading_{local_id_counter}")
@@ -211,6 +303,7 @@ fn parse_markdown_to_steps_and_content_blocks(
                         &mut dependencies,
                         template_id,
                         &metadata.references,
+                        tests_dir,
                     );
                 }
                 content_blocks.push(ContentBlock::HeadingBlock {
@@ -218,6 +311,7 @@ fn parse_markdown_to_steps_and_content_blocks(
                     level,
                     text: text.clone(),
                     attributes: attributes.clone(),
+                    span: span.clone(),
                 });
                 execution_steps.push(ExecutionStep {
                     id: global_id.clone(),
@@ -230,6 +324,7 @@ fn parse_markdown_to_steps_and_content_blocks(
                     },
                     dependencies,
                     original_parsed_step: None,
+                    span: Some(span),
                 });
                 current_heading_stack.push((global_id, level, Vec::new())); // 新 heading 入栈，准备收集子节点
             }
@@ -237,10 +332,11 @@ fn parse_markdown_to_steps_and_content_blocks(
             if let Some(caps) = output_block_re.captures(output_match.as_str()) {
                 debug!("Found output_block: {}", output_match.as_str()); // 发现 output_block: {}
                 let attributes_str = caps.get(1).map_or("", |m| m.as_str());
-                let attributes = parse_inline_attributes(attributes_str);
+                let attributes = parse_inline_attributes(attributes_str)
+                    .with_context(|| format!("Invalid attributes at {span}"))?; // 属性格式错误
                 let ref_id_attr = attributes
                     .get("ref")
-                    .ok_or_else(|| anyhow!("output_block missing ref attribute"))?; // output_block 缺少 ref 属性
+                    .ok_or_else(|| anyhow!("output_block missing ref attribute at {span}"))?; // output_block 缺少 ref 属性
                 let stream = match attributes.get("stream") {
                     Some(stream) => stream.to_string(),
                     _ => "stdout".to_string(),
@@ -248,13 +344,18 @@ fn parse_markdown_to_steps_and_content_blocks(
                 content_blocks.push(ContentBlock::OutputBlock {
                     step_id: ref_id_attr.clone(),
                     stream,
+                    span: span.clone(),
                 });
                 let local_id = format!("{ref_id_attr}-outputplaceholder");
                 all_local_ids.insert(local_id.clone());
                 let global_id = format!("{template_id}::{local_id}");
                 let mut dependencies = HashSet::new();
-                let ref_global_id =
-                    resolve_dependency_ref(ref_id_attr, template_id, &metadata.references);
+                let ref_global_id = resolve_dependency_ref(
+                    ref_id_attr,
+                    template_id,
+                    &metadata.references,
+                    tests_dir,
+                );
                 dependencies.insert(ref_global_id.clone());
                 let parsed_step_info = ParsedTestStep {
                     id: local_id.clone(),
@@ -276,6 +377,7 @@ fn parse_markdown_to_steps_and_content_blocks(
                     step_type: StepType::OutputPlaceholder,
                     dependencies,
                     original_parsed_step: Some(parsed_step_info),
+                    span: Some(span),
                 });
                 // 只让父 heading 的 children 收集这个 output block
                 // 这样 heading 只依赖于自己直接的 output/code/heading 子节点
@@ -303,7 +405,8 @@ fn parse_markdown_to_steps_and_content_blocks(
                 let lang = caps.get(1).map_or("", |m| m.as_str()).to_string();
                 let attributes_str = caps.get(3).map_or("", |m| m.as_str());
                 let command = caps.get(4).map_or("", |m| m.as_str()).trim().to_string();
-                let attributes = parse_inline_attributes(attributes_str);
+                let attributes = parse_inline_attributes(attributes_str)
+                    .with_context(|| format!("Invalid attributes at {span}"))?; // 属性格式错误
                 let local_id = attributes.get("id").cloned().unwrap_or_else(|| {
                     local_id_counter += 1;
                     format!("codeblock_{local_id_counter}")
@@ -314,6 +417,7 @@ fn parse_markdown_to_steps_and_content_blocks(
                     lang: lang.clone(),
                     code: command.clone(),
                     attributes: attributes.clone(),
+                    span: span.clone(),
                 });
                 let global_id = format!("{template_id}::{local_id}");
                 let mut dependencies = HashSet::new();
@@ -324,6 +428,7 @@ fn parse_markdown_to_steps_and_content_blocks(
                         &mut dependencies,
                         template_id,
                         &metadata.references,
+                        tests_dir,
                     );
                 }
                 let parsed_step_info = ParsedTestStep {
@@ -360,6 +465,7 @@ fn parse_markdown_to_steps_and_content_blocks(
                     },
                     dependencies,
                     original_parsed_step: Some(parsed_step_info),
+                    span: Some(span),
                 });
                 // 只让父 heading 的 children 收集这个 codeblock
                 // 这样 heading 只依赖于自己直接的 codeblock/output/heading 子节点
@@ -384,6 +490,7 @@ fn parse_markdown_to_steps_and_content_blocks(
             content_blocks.push(ContentBlock::Text(remaining_text.to_string()));
         }
     }
+    add_variable_dependencies(&mut execution_steps, template_id)?;
     // 检查所有 depends_on 的 id 是否都存在
     for (from_id, dep_id) in &all_depends_refs {
         if !all_local_ids.contains(dep_id) {
@@ -399,7 +506,7 @@ fn parse_markdown_to_steps_and_content_blocks(
 }
 
 /// 从Markdown内容中提取YAML前置数据
-fn extract_front_matter(content: &str) -> Result<(String, &str)> {
+pub(super) fn extract_front_matter(content: &str) -> Result<(String, &str)> {
     debug!("Extracting YAML front matter from template content"); // 从模板内容中提取YAML前置数据
     let re = Regex::new(r"(?s)^---\s*\n(.*?)\n---\s*\n(.*)$")?;
 
@@ -417,6 +524,16 @@ fn extract_front_matter(content: &str) -> Result<(String, &str)> {
     }
 }
 
+/// 加载前置数据中 `target_config` 指向的目标配置
+///
+/// 相对路径相对于当前工作目录解析。加载模板和 `validate` 都通过这里加载，两者的结果因此一致。
+pub(super) fn load_target_config(target_config: &str) -> Result<TargetConfig, TemplateError> {
+    TargetConfig::from_file(target_config).map_err(|e| TemplateError::TargetConfig {
+        path: target_config.to_string(),
+        reason: e.to_string(),
+    })
+}
+
 /// 解析YAML元数据
 fn parse_metadata(yaml: &str) -> Result<TemplateMetadata> {
     debug!("Parsing YAML metadata"); // 解析YAML元数据
@@ -436,8 +553,7 @@ fn parse_metadata(yaml: &str) -> Result<TemplateMetadata> {
         .ok_or_else(|| anyhow!("Metadata missing 'target_config' field"))?; // 元数据缺少'target_config'字段
     debug!("Extracted target_config: {target_config_str}"); // 提取target_config: {target_config_str}
 
-    let target_config = TargetConfig::from_file(target_config_str)
-        .unwrap_or_else(|_| panic!("Unable to load target_config file: {target_config_str}")); // 无法加载 target_config 文件: {target_config_str}
+    let target_config = load_target_config(target_config_str)?;
 
     let unit_name = yaml_value["unit_name"]
         .as_str()
@@ -544,24 +660,18 @@ fn parse_metadata(yaml: &str) -> Result<TemplateMetadata> {
     })
 }
 
-/// 提取 depends_on 字符串中的单个依赖 id（去除 namespace，仅返回本地 id）
-/// 意思是暂时不考虑跨 namespace 的依赖
-fn extract_dep_id_from_dep_str(dep_str: &str) -> &str {
+/// 提取 depends_on 字符串中的单个依赖 id（去除空白和引号，保留 namespace 前缀）
+pub(super) fn extract_dep_id_from_dep_str(dep_str: &str) -> &str {
     // 依赖 id 的格式是 "namespace::local_id" 或者 "local_id"
-    let dep_str = dep_str.trim().trim_matches('"').trim_matches('\'');
-
-    if dep_str.contains("::") {
-        dep_str.split("::").last().unwrap_or("")
-    } else {
-        dep_str
-    }
+    dep_str.trim().trim_matches('"').trim_matches('\'')
 }
 
 /// Helper to parse inline attributes like id="foo" exec="true" assert.exit.code=0 extract.lintestor=/Lintestor/
 /// 这个函数解析类似于 id="foo" exec="true" assert.exit.code=0 extract.lintestor=/Lintestor/ 的内联属性
 /// 实现方式是使用有限状态机来解析键值对，内部状态似乎没什么复用的可能性所以 State 就不对外暴露了
 /// 注意我们在状态里没考虑 { 和 } 所以不许传入整个带 {} 的 attr_str
-fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
+/// 属性格式错误时返回错误，由调用方补充所在位置
+pub(super) fn parse_inline_attributes(input: &str) -> Result<HashMap<String, String>> {
     debug!("Parsing inline attributes: {input}"); // 解析内联属性: {input}
 
     let mut result = HashMap::new();
@@ -571,7 +681,7 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
 
     if input.is_empty() {
         // Early return if input is empty, because of state machine expect {} mustly
-        return result;
+        return Ok(result);
     }
 
     enum State {
@@ -620,20 +730,20 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                         state = State::ExpectEq;
                     } else {
                         if key.is_empty() {
-                            panic!(
+                            bail!(
                                 "Unexpected character '{ch}' at start of key or empty key before '='"
                             );
                         }
-                        panic!(
+                        bail!(
                             "Unexpected character '{ch}' after key '{key}', expected '=' or whitespace or '}}'"
                         );
                     }
                 } else {
                     if !key.is_empty() {
-                        panic!("Unexpected EOF after key: {key}");
+                        bail!("Unexpected EOF after key: {key}");
                     }
                     // EOF after attributes have started, but before '}'
-                    panic!("Unexpected EOF, expected attributes or '}}'");
+                    bail!("Unexpected EOF, expected attributes or '}}'");
                 }
             }
             State::ExpectEq => {
@@ -644,10 +754,10 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                     } else if ch.is_whitespace() {
                         chars.next();
                     } else {
-                        panic!("Expected '=' after key '{key}', found '{ch}'");
+                        bail!("Expected '=' after key '{key}', found '{ch}'");
                     }
                 } else {
-                    panic!("Unexpected EOF after key '{key}', expected '='");
+                    bail!("Unexpected EOF after key '{key}', expected '='");
                 }
             }
             State::ValueStart => {
@@ -678,7 +788,7 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                         }
                     }
                 } else {
-                    panic!("Unexpected EOF for key '{key}', expected a value.");
+                    bail!("Unexpected EOF for key '{key}', expected a value.");
                 }
             }
             State::StringValue => {
@@ -699,7 +809,7 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                         }
                     }
                 } else {
-                    panic!("Unexpected EOF in string value for key '{key}'");
+                    bail!("Unexpected EOF in string value for key '{key}'");
                 }
             }
             State::StringEscape => {
@@ -708,7 +818,7 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                     chars.next();
                     state = State::StringValue;
                 } else {
-                    panic!("Unexpected EOF after string escape for key '{key}'");
+                    bail!("Unexpected EOF after string escape for key '{key}'");
                 }
             }
             State::RegexValue => {
@@ -729,7 +839,7 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                         }
                     }
                 } else {
-                    panic!("Unexpected EOF in regex value for key '{key}'");
+                    bail!("Unexpected EOF in regex value for key '{key}'");
                 }
             }
             State::RegexEscape => {
@@ -739,7 +849,7 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                     chars.next();
                     state = State::RegexValue;
                 } else {
-                    panic!("Unexpected EOF after regex escape for key '{key}'");
+                    bail!("Unexpected EOF after regex escape for key '{key}'");
                 }
             }
             State::RegexFlags => {
@@ -822,7 +932,7 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
                         }
                     }
                 } else {
-                    panic!("Unexpected EOF in list value for key '{key}': missing closing ']'");
+                    bail!("Unexpected EOF in list value for key '{key}': missing closing ']'");
                 }
             }
             State::Bareword => {
@@ -849,10 +959,52 @@ fn parse_inline_attributes(input: &str) -> HashMap<String, String> {
 
     if chars.peek().is_some() {
         // If we reach here and there are still characters left, it means we didn't close the attributes properly
-        panic!("Unclosed attribute definition, possibly missing '}}'"); // 未闭合的属性定义，可能缺少 '}}'
+        bail!("Unclosed attribute definition, possibly missing '}}'"); // 未闭合的属性定义，可能缺少 '}}'
     }
 
-    result
+    Ok(result)
+}
+
+/// 把代码块通过 `{{ step::var }}` 或 `{{ 模板ID::step::var }}` 读取的本模板步骤加入它的依赖
+///
+/// 这样即使没有写 `depends_on`，读取变量的步骤也总在被读取的步骤之后执行，并行调度时也是如此。
+fn add_variable_dependencies(steps: &mut [ExecutionStep], template_id: &str) -> Result<()> {
+    let reference_re = Regex::new(r"\{\{\s*([A-Za-z0-9_.\-]+(?:::[A-Za-z0-9_.\-]+){1,2})\s*\}\}")?;
+    let local_ids: HashSet<String> = steps
+        .iter()
+        .filter(|step| matches!(step.step_type, StepType::CodeBlock { .. }))
+        .map(|step| step.local_id.clone())
+        .collect();
+    for step in steps.iter_mut() {
+        let (StepType::CodeBlock { .. }, Some(parsed)) =
+            (&step.step_type, &step.original_parsed_step)
+        else {
+            continue;
+        };
+        let mut referenced = HashSet::new();
+        for captures in reference_re.captures_iter(&parsed.raw_content) {
+            let parts: Vec<&str> = captures[1].split("::").collect();
+            match parts.as_slice() {
+                [scope, _] if local_ids.contains(*scope) => {
+                    referenced.insert(scope.to_string());
+                }
+                [scope, local_id, _] if *scope == template_id && local_ids.contains(*local_id) => {
+                    referenced.insert(local_id.to_string());
+                }
+                _ => {}
+            }
+        }
+        referenced.remove(&step.local_id);
+        for local_id in referenced {
+            debug!(
+                "Step {} reads variables of {local_id}, adding it as a dependency",
+                step.id
+            ); // 步骤读取了 {local_id} 的变量，将其加入依赖
+            step.dependencies
+                .insert(format!("{template_id}::{local_id}"));
+        }
+    }
+    Ok(())
 }
 
 /// Helper to parse "depends_on" string and populate dependencies set
@@ -861,6 +1013,7 @@ fn parse_depends_on_str(
     dependencies: &mut HashSet<GlobalStepId>,
     current_template_id: &str,
     references: &[TemplateReference],
+    tests_dir: &Path,
 ) {
     let deps_list_str = deps_str.trim_matches(|c| c == '[' || c == ']');
     for dep_item_str in deps_list_str.split(',') {
@@ -871,6 +1024,7 @@ fn parse_depends_on_str(
                 trimmed_dep,
                 current_template_id,
                 references,
+                tests_dir,
             ));
         }
     }
@@ -881,6 +1035,7 @@ fn resolve_dependency_ref(
     dep_ref: &str,
     current_template_id: &str,
     references: &[TemplateReference],
+    tests_dir: &Path,
 ) -> GlobalStepId {
     if dep_ref.contains("::") {
         let parts: Vec<&str> = dep_ref.splitn(2, "::").collect();
@@ -888,14 +1043,11 @@ fn resolve_dependency_ref(
             let namespace_or_template_id = parts[0];
             let local_step_id = parts[1];
 
+            // namespace 解析为被引用模板的模板ID，与 VariableManager 注册的命名空间保持一致
             for reference in references {
                 if reference.namespace == namespace_or_template_id {
-                    let referenced_template_file_name = Path::new(&reference.template_path)
-                        .file_stem()
-                        .unwrap_or_default()
-                        .to_str()
-                        .unwrap_or(namespace_or_template_id);
-                    return format!("{referenced_template_file_name}::{local_step_id}");
+                    let referenced_template_id = reference.template_id(tests_dir);
+                    return format!("{referenced_template_id}::{local_step_id}");
                 }
             }
             return dep_ref.to_string();
@@ -904,6 +1056,17 @@ fn resolve_dependency_ref(
     format!("{current_template_id}::{dep_ref}")
 }
 
+/// `assert.` 之后支持的断言类型
+pub(super) const ASSERTION_KEYS: &[&str] = &[
+    "exit_code",
+    "stdout_contains",
+    "stdout_not_contains",
+    "stdout_matches",
+    "stderr_contains",
+    "stderr_not_contains",
+    "stderr_matches",
+];
+
 /// Helper to parse assertions from a HashMap of attributes
 fn parse_assertions_from_attributes(attributes: &HashMap<String, String>) -> Vec<AssertionType> {
     let mut assertions = Vec::new();
@@ -934,7 +1097,9 @@ fn parse_assertions_from_attributes(attributes: &HashMap<String, String>) -> Vec
 }
 
 /// Helper to parse extractions from a HashMap of attributes
-fn parse_extractions_from_attributes(attributes: &HashMap<String, String>) -> Vec<DataExtraction> {
+pub(super) fn parse_extractions_from_attributes(
+    attributes: &HashMap<String, String>,
+) -> Vec<DataExtraction> {
     let mut extractions = Vec::new();
     for (key, value) in attributes {
         if key.starts_with("extract.") {
@@ -1015,4 +1180,32 @@ custom_field: "custom value"
             Some(&"custom value".to_string())
         );
     }
+
+    #[test]
+    fn test_source_spans() {
+        let content = "---\ntitle: \"Spans\"\ntarget_config: \"tests/test_files/local_target.toml\"\nunit_name: \"spans\"\nunit_version: \"1\"\n---\n\n# Spans {id=\"top\"}\n\n```bash {id=\"run\"}\necho one\necho two\n```\n\n```output {ref=\"run\"}\n```\n";
+        let file = Path::new("/tmp/spans.test.md");
+        let (_, steps, blocks) =
+            parse_template_into_content_blocks_and_steps(content, file, Path::new("/tmp")).unwrap();
+
+        let span_of = |local_id: &str| {
+            let step = steps.iter().find(|s| s.local_id == local_id).unwrap();
+            let span = step.span.as_ref().unwrap();
+            assert_eq!(span.file, file);
+            (span.start_line, span.end_line)
+        };
+        assert_eq!(span_of("top"), (8, 8));
+        assert_eq!(span_of("run"), (10, 13));
+        assert_eq!(span_of("run-outputplaceholder"), (15, 16));
+        assert!(blocks.iter().any(|block| matches!(
+            block,
+            ContentBlock::CodeBlock { id, span, .. } if id == "run" && span.to_string() == "/tmp/spans.test.md:10"
+        )));
+
+        // 属性格式错误时报告所在位置
+        let broken = content.replace("{id=\"run\"}", "{id=\"run}");
+        let error = parse_template_into_content_blocks_and_steps(&broken, file, Path::new("/tmp"))
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("/tmp/spans.test.md:10"));
+    }
 }
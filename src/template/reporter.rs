@@ -16,9 +16,32 @@ use std::sync::Arc;
 
 use crate::template::executor::ExecutionResult;
 use crate::template::variable::VariableManager;
-use crate::template::{ContentBlock, StepStatus, TestTemplate};
+use crate::template::{ContentBlock, StepStatus, TemplateError, TestTemplate};
 use crate::utils;
 
+/// 替换正文文本中的变量：先做模板级替换，再依次以每个步骤为上下文替换步骤级变量
+pub(crate) fn replace_text_variables(
+    text: &str,
+    template_id: &str,
+    result: &ExecutionResult,
+    var_manager: &VariableManager,
+) -> String {
+    // Global and step-specific variable replacement (ensure correct step_id context if needed)
+    let mut processed_text = var_manager.replace_variables(text, Some(template_id), None); // Broad pass
+    // More specific passes if text can contain step-scoped variables:
+    let mut sorted_step_ids: Vec<_> = result.step_results.keys().cloned().collect();
+    sorted_step_ids.sort();
+    for step_id_key in &sorted_step_ids {
+        let local_step_id_for_var_lookup = step_id_key.split("::").last().unwrap_or(step_id_key);
+        processed_text = var_manager.replace_variables(
+            &processed_text,
+            Some(template_id),
+            Some(local_step_id_for_var_lookup),
+        );
+    }
+    processed_text
+}
+
 /// 报告生成器
 /// 负责将执行结果转换为Markdown格式的测试报告
 pub struct Reporter {
@@ -35,12 +58,16 @@ impl Reporter {
     /// * `test_dir`: 当前处理的测试模板文件所在的目录。
     ///   用于解析模板中可能存在的相对路径引用，或作为报告中相对路径的基础。
     /// * `report_dir`: 所有生成的报告文件最终应存放的目录。
-    ///   如果为 `None`，可能会使用 `test_dir` 下的 "reports" 子目录或其他默认逻辑。
-    pub fn new(test_dir: Option<PathBuf>, report_dir: Option<PathBuf>) -> Self {
-        Self {
-            test_dir: test_dir.unwrap(),
-            report_dir: report_dir.unwrap(),
-        }
+    ///
+    /// 任一目录为 `None` 时返回 `TemplateError::MissingDirectory`。
+    pub fn new(
+        test_dir: Option<PathBuf>,
+        report_dir: Option<PathBuf>,
+    ) -> Result<Self, TemplateError> {
+        Ok(Self {
+            test_dir: test_dir.ok_or(TemplateError::MissingDirectory("test"))?,
+            report_dir: report_dir.ok_or(TemplateError::MissingDirectory("report"))?,
+        })
     }
 
     /// 生成单个测试模板的测试报告
@@ -81,10 +108,13 @@ impl Reporter {
         })?;
 
         // 构建报告文件的完整路径 (result 传过来的)
-        let report_path = match result.report_path {
-            Some(ref path) => path.clone(),
-            None => panic!("No report path provided in ExecutionResult, this should not happen"),
-        };
+        let report_path =
+            result
+                .report_path
+                .clone()
+                .ok_or_else(|| TemplateError::MissingReportPath {
+                    template_id: result.template_id(),
+                })?;
 
         // 生成报告的Markdown内容
         let report_content = self.generate_report_content(template, result, var_manager)?;
@@ -130,22 +160,8 @@ impl Reporter {
             match content_block {
                 ContentBlock::Metadata(_) => { /* Already handled or ignore if not first */ }
                 ContentBlock::Text(text_content) => {
-                    let mut processed_text = text_content.clone();
-                    // Global and step-specific variable replacement (ensure correct step_id context if needed)
-                    processed_text =
-                        var_manager.replace_variables(&processed_text, Some(&template_id), None); // Broad pass
-                    // More specific passes if text can contain step-scoped variables:
-                    let mut sorted_step_ids: Vec<_> = result.step_results.keys().cloned().collect();
-                    sorted_step_ids.sort();
-                    for step_id_key in &sorted_step_ids {
-                        let local_step_id_for_var_lookup =
-                            step_id_key.split("::").last().unwrap_or(step_id_key);
-                        processed_text = var_manager.replace_variables(
-                            &processed_text,
-                            Some(&template_id),
-                            Some(local_step_id_for_var_lookup),
-                        );
-                    }
+                    let mut processed_text =
+                        replace_text_variables(text_content, &template_id, result, var_manager);
                     processed_text.push('\n');
                     report_parts.push(processed_text);
                 }
@@ -154,6 +170,7 @@ impl Reporter {
                     level,
                     text,
                     attributes,
+                    ..
                 } => {
                     // 判断visible属性，默认true
                     let visible = attributes
@@ -182,6 +199,7 @@ impl Reporter {
                     lang,
                     code,
                     attributes,
+                    ..
                 } => {
                     // 判断visible属性
                     let visible = attributes
@@ -197,7 +215,9 @@ impl Reporter {
                         report_parts.push(self.clean_markdown_markup(&code_block_str)?);
                     }
                 }
-                ContentBlock::OutputBlock { step_id, stream } => {
+                ContentBlock::OutputBlock {
+                    step_id, stream, ..
+                } => {
                     // The step_id here is the *local* ID referenced in the template (e.g., {ref="local_step_id"})
                     // We need to find the corresponding StepResult using the global ID.
                     // 因为我们这边直接用 HashMap 通过 id 得到 results 了，不是原本那种 map 然后 id == id 的方式了
@@ -312,7 +332,20 @@ impl Reporter {
                 );
 
                 let stdout_summary = Self::summarize_output(&step_result.stdout, 50);
-                let stderr_summary = Self::summarize_output(&step_result.stderr, 30);
+                // 因依赖失败而跳过的步骤在错误信息一栏显示跳过原因
+                let mut stderr_summary = match &step_result.skip_reason {
+                    Some(reason) => reason.clone(),
+                    None => Self::summarize_output(&step_result.stderr, 30),
+                };
+                // 失败的步骤在错误信息前标出源文件位置，便于跳转
+                if step_result.status == StepStatus::Fail
+                    && let Some(span) = result.step_span(&global_step_id)
+                {
+                    stderr_summary = match stderr_summary.as_str() {
+                        "-" => span.to_string(),
+                        _ => format!("{span}: {stderr_summary}"),
+                    };
+                }
 
                 table.push_str(&format!(
                     "| {} | {} | {} | {} | {} | {} |\n",
@@ -408,7 +441,7 @@ mod tests {
     use crate::config::target_config::TargetConfig;
     use crate::template::executor::{ExecutionResult, StepResult};
     use crate::template::{
-        BatchOptions, ContentBlock, ExecutionStep, TemplateMetadata, TemplateReference,
+        BatchOptions, ContentBlock, ExecutionStep, SourceSpan, TemplateMetadata, TemplateReference,
     };
     use anyhow::Result;
     use std::collections::HashMap;
@@ -417,6 +450,14 @@ mod tests {
     use std::path::PathBuf;
     use std::sync::Arc;
 
+    fn span(start_line: usize, end_line: usize) -> SourceSpan {
+        SourceSpan {
+            file: PathBuf::from("/test/test_template.test.md"),
+            start_line,
+            end_line,
+        }
+    }
+
     fn create_dummy_template(
         id: &str,
         raw_content: &str,
@@ -478,6 +519,8 @@ mod tests {
                 exit_code: 0,
                 duration_ms: Some(100),
                 assertion_error: None,
+                skip_reason: None,
+                reconnects: Vec::new(),
             },
         );
         step_results.insert(
@@ -491,6 +534,8 @@ mod tests {
                 exit_code: 0,
                 duration_ms: Some(120),
                 assertion_error: None,
+                skip_reason: None,
+                reconnects: Vec::new(),
             },
         );
 
@@ -502,6 +547,7 @@ mod tests {
             step_results,
             variables: HashMap::new(),
             report_path: None,
+            error: None,
         }
     }
 
@@ -546,9 +592,10 @@ echo "Hello, {{ execution_time }}"
                 level: 1,
                 text: "{{ metadata.title }}".to_string(),
                 attributes: Default::default(),
+                span: span(6, 6),
             },
             ContentBlock::Text("This is a test report for {{ metadata.unit_name }} targeting {{ metadata.target_name }}.".to_string()),
-            ContentBlock::OutputBlock { step_id: "code1".to_string(), stream: "stdout".to_string() },
+            ContentBlock::OutputBlock { step_id: "code1".to_string(), stream: "stdout".to_string(), span: span(10, 12) },
             ContentBlock::CodeBlock {
                 id: "code1".to_string(),
                 lang: "bash".to_string(),
@@ -558,6 +605,7 @@ echo "Hello, {{ execution_time }}"
                 m.insert("id".to_string(), "code1".to_string());
                 m
                 },
+                span: span(14, 16),
             },
             ],
             vec![],
@@ -580,6 +628,8 @@ echo "Hello, {{ execution_time }}"
                     exit_code: 0,
                     duration_ms: Some(100),
                     assertion_error: None,
+                    skip_reason: None,
+                    reconnects: Vec::new(),
                 },
             )]),
             variables: HashMap::new(),
@@ -589,9 +639,13 @@ echo "Hello, {{ execution_time }}"
                     test_directory: Some(test_dir.clone()),
                     executor_options: Default::default(),
                     keep_template_directory_structure: true,
+                    report_formats: Vec::new(),
+                    tap_stream: None,
+                    interactive: false,
                 }),
                 &Arc::clone(&template),
             )?),
+            error: None,
         };
 
         // 创建变量管理器并设置变量
@@ -600,7 +654,7 @@ echo "Hello, {{ execution_time }}"
         var_manager.set_variable("GLOBAL", "GLOBAL", "var.global_var", "World")?;
 
         // 创建Reporter实例
-        let reporter = Reporter::new(Some(test_dir), Some(report_dir.clone()));
+        let reporter = Reporter::new(Some(test_dir), Some(report_dir.clone()))?;
 
         // 生成报告
         let report_path = reporter.generate_report(&template, &execution_result, &var_manager)?;
@@ -625,7 +679,7 @@ echo "Hello, {{ execution_time }}"
         let reporter = Reporter::new(
             Some(temp_dir.path().to_path_buf()),
             Some("./reports".into()),
-        );
+        )?;
 
         let input7 = "Start {id=\"id1\"} then {exec=true} finally {description=\"desc\"} end";
         assert_eq!(
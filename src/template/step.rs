@@ -6,6 +6,8 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
 
 use crate::template::ParsedTestStep; // Import the original ParsedTestStep from template module // 从 template 模块导入原始的 ParsedTestStep
 
@@ -18,6 +20,25 @@ use crate::template::ParsedTestStep; // Import the original ParsedTestStep from
 /// 或者为标题自动生成。
 pub type GlobalStepId = String;
 
+/// 步骤在模板源文件中的位置
+///
+/// 行号从 1 开始，`end_line` 为块最后一行（包含）。显示为 `file:line`，便于在编辑器中跳转。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    /// 模板文件路径
+    pub file: PathBuf,
+    /// 块的起始行
+    pub start_line: usize,
+    /// 块的结束行
+    pub end_line: usize,
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.start_line)
+    }
+}
+
 /// 定义一个执行步骤的具体类型
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)] // Removed Hash
 pub enum StepType {
@@ -65,11 +86,20 @@ pub struct ExecutionStep {
     /// `ParsedTestStep` 包含了如断言、变量提取规则、原始命令、是否可执行等详细信息。
     /// 对于标题类型的步骤，此字段为 `None`。
     pub original_parsed_step: Option<ParsedTestStep>,
-    // 未来可以添加更多信息，如步骤在源文件中的行号等，用于错误报告。
-    // pub line_number: Option<usize>,
+    /// 步骤在源文件中的位置，用于错误信息和报告。非解析得到的步骤为 `None`。
+    #[serde(default)]
+    pub span: Option<SourceSpan>,
 }
 
 impl ExecutionStep {
+    /// 用于日志和错误信息的步骤名称，已知源文件位置时附带位置，如 `tpl::build (tpl.test.md:12)`
+    pub fn display_name(&self) -> String {
+        match &self.span {
+            Some(span) => format!("{} ({span})", self.id),
+            None => self.id.clone(),
+        }
+    }
+
     /// 辅助函数，获取步骤的描述信息
     /// 对于代码块，尝试从其原始属性或 `ParsedTestStep` 中获取。
     /// 对于标题，描述就是其文本。
@@ -0,0 +1,339 @@
+//! 整次运行的汇总报告
+//!
+//! 合并所有目标分组（批次）的执行结果，生成一份包含总计、兼容性矩阵和按目标分节的模板结果表的
+//! Markdown 报告。报告在全部批次执行完毕后只写入一次，避免各批次互相覆盖。
+
+use anyhow::{Context, Result};
+use log::info;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::template::StepStatus;
+use crate::template::executor::ExecutionResult;
+use crate::template::history::RunComparison;
+use crate::template::matrix_report::CompatibilityMatrix;
+
+/// 按状态统计的模板数和步骤数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Totals {
+    templates: usize,
+    templates_passed: usize,
+    templates_failed: usize,
+    steps_passed: usize,
+    steps_failed: usize,
+    steps_skipped: usize,
+    steps_blocked: usize,
+    steps_not_run: usize,
+}
+
+impl Totals {
+    fn add(&mut self, result: &ExecutionResult) {
+        self.templates += 1;
+        match result.overall_status {
+            StepStatus::Pass => self.templates_passed += 1,
+            StepStatus::Fail => self.templates_failed += 1,
+            _ => {}
+        }
+        for step_result in result.step_results.values() {
+            match step_result.status {
+                StepStatus::Pass => self.steps_passed += 1,
+                StepStatus::Fail => self.steps_failed += 1,
+                StepStatus::Skipped => self.steps_skipped += 1,
+                StepStatus::Blocked => self.steps_blocked += 1,
+                StepStatus::NotRun => self.steps_not_run += 1,
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Totals) {
+        self.templates += other.templates;
+        self.templates_passed += other.templates_passed;
+        self.templates_failed += other.templates_failed;
+        self.steps_passed += other.steps_passed;
+        self.steps_failed += other.steps_failed;
+        self.steps_skipped += other.steps_skipped;
+        self.steps_blocked += other.steps_blocked;
+        self.steps_not_run += other.steps_not_run;
+    }
+
+    fn table_row(&self, label: &str) -> String {
+        format!(
+            "| {label} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            self.templates,
+            self.templates_passed,
+            self.templates_failed,
+            self.steps_passed,
+            self.steps_failed,
+            self.steps_skipped,
+            self.steps_blocked,
+            self.steps_not_run
+        )
+    }
+}
+
+/// 报告文件在汇总报告中的显示路径：位于汇总报告目录下时使用相对路径
+fn report_link(result: &ExecutionResult, summary_dir: Option<&Path>) -> String {
+    let Some(report_path) = result.report_path.as_ref() else {
+        return "N/A".to_string();
+    };
+    summary_dir
+        .and_then(|dir| report_path.strip_prefix(dir).ok())
+        .unwrap_or(report_path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 生成汇总报告内容
+///
+/// `summary_dir` 为汇总报告所在目录，用于把各模板报告的路径显示为相对路径；
+/// 指定 `comparison` 时在总计之后加入与历史运行的对比
+pub fn generate_summary_markdown<'a>(
+    results: impl IntoIterator<Item = &'a ExecutionResult>,
+    summary_dir: Option<&Path>,
+    comparison: Option<&RunComparison>,
+) -> String {
+    let results: Vec<&ExecutionResult> = results.into_iter().collect();
+    let mut by_target: BTreeMap<&str, Vec<&ExecutionResult>> = BTreeMap::new();
+    for result in &results {
+        by_target
+            .entry(result.target_name.as_str())
+            .or_default()
+            .push(result);
+    }
+
+    let mut content = format!(
+        "# Test Execution Summary ({})\n\n",
+        chrono::Local::now().to_rfc3339()
+    );
+
+    content.push_str("## Totals\n\n");
+    content.push_str("| Target | Templates | Passed | Failed | Steps Passed | Steps Failed | Steps Skipped | Steps Blocked | Steps Not Run |\n");
+    content.push_str("|--------|-----------|--------|--------|--------------|--------------|---------------|---------------|---------------|\n");
+    let mut overall = Totals::default();
+    for (target, target_results) in &by_target {
+        let mut totals = Totals::default();
+        for result in target_results {
+            totals.add(result);
+        }
+        content.push_str(&totals.table_row(target));
+        overall.merge(&totals);
+    }
+    content.push_str(&overall.table_row("**Total**"));
+
+    if let Some(comparison) = comparison {
+        content.push('\n');
+        content.push_str(comparison.to_markdown().trim_end());
+        content.push('\n');
+    }
+
+    content.push_str("\n## Compatibility Matrix\n\n");
+    content
+        .push_str(&CompatibilityMatrix::from_results(results.iter().copied()).to_markdown_table());
+
+    for (target, mut target_results) in by_target {
+        target_results.sort_by_key(|result| result.template.get_template_id());
+        content.push_str(&format!("\n## Target: {target}\n\n"));
+        content.push_str("| Template ID | Unit | Overall Status | Steps Passed | Steps Failed | Steps Skipped | Steps Blocked | Steps Not Run | Report File |\n");
+        content.push_str("|-------------|------|----------------|--------------|--------------|---------------|---------------|---------------|-------------|\n");
+        for result in &target_results {
+            let mut totals = Totals::default();
+            totals.add(result);
+            content.push_str(&format!(
+                "| {} | {} | {:?} | {} | {} | {} | {} | {} | {} |\n",
+                result.template.get_template_id(),
+                result.template.metadata.unit_name,
+                result.overall_status,
+                totals.steps_passed,
+                totals.steps_failed,
+                totals.steps_skipped,
+                totals.steps_blocked,
+                totals.steps_not_run,
+                report_link(result, summary_dir)
+            ));
+        }
+
+        // 列出未能执行的模板及错误信息
+        let errors: Vec<(String, &String)> = target_results
+            .iter()
+            .filter_map(|result| {
+                result
+                    .error
+                    .as_ref()
+                    .map(|error| (result.template.get_template_id(), error))
+            })
+            .collect();
+        if !errors.is_empty() {
+            content.push_str("\n### Template Errors\n\n");
+            content.push_str("| Template ID | Error |\n");
+            content.push_str("|-------------|-------|\n");
+            for (template_id, error) in errors {
+                content.push_str(&format!(
+                    "| {template_id} | {} |\n",
+                    error.replace('|', "\\|").replace('\n', "<br>")
+                ));
+            }
+        }
+
+        // 列出因依赖失败而跳过的步骤及原因
+        let mut skipped_with_reason: Vec<(String, &String, &String)> = target_results
+            .iter()
+            .flat_map(|result| {
+                result.step_results.values().filter_map(|step_result| {
+                    step_result
+                        .skip_reason
+                        .as_ref()
+                        .map(|reason| (result.template.get_template_id(), &step_result.id, reason))
+                })
+            })
+            .collect();
+        if !skipped_with_reason.is_empty() {
+            skipped_with_reason.sort();
+            content.push_str("\n### Skipped Steps\n\n");
+            content.push_str("| Template ID | Step ID | Reason |\n");
+            content.push_str("|-------------|---------|--------|\n");
+            for (template_id, step_id, reason) in skipped_with_reason {
+                content.push_str(&format!("| {template_id} | {step_id} | {reason} |\n"));
+            }
+        }
+    }
+    content
+}
+
+/// 将汇总报告写入文件
+pub fn write_summary_report<'a>(
+    path: &Path,
+    results: impl IntoIterator<Item = &'a ExecutionResult>,
+    comparison: Option<&RunComparison>,
+) -> Result<()> {
+    let summary_dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+    if let Some(parent) = summary_dir {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Unable to create report directory: {}", parent.display()))?; // 无法创建报告目录
+    }
+    fs::write(
+        path,
+        generate_summary_markdown(results, summary_dir, comparison),
+    )
+    .with_context(|| format!("Unable to write summary report: {}", path.display()))?; // 无法写入汇总报告
+    info!("Summary report generated at: {}", path.display()); // 已生成汇总报告
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::TestTemplate;
+    use crate::template::executor::StepResult;
+    use crate::template::test_support::write_template;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn result(
+        dir: &Path,
+        name: &str,
+        target: &str,
+        statuses: &[(&str, StepStatus)],
+    ) -> ExecutionResult {
+        let path = write_template(
+            dir,
+            &format!("{name}.test.md"),
+            &format!("title: \"{name}\"\nunit_name: \"{name}\"\nunit_version: \"1\"\n"),
+            &format!("\n# {name}\n"),
+        );
+        let template = Arc::new(TestTemplate::from_file(path, dir).unwrap());
+        let step_results = statuses
+            .iter()
+            .map(|(id, status)| {
+                (
+                    format!("{name}::{id}"),
+                    StepResult {
+                        id: id.to_string(),
+                        description: None,
+                        status: status.clone(),
+                        stdout: String::new(),
+                        stderr: String::new(),
+                        exit_code: 0,
+                        duration_ms: None,
+                        assertion_error: None,
+                        skip_reason: (*status == StepStatus::Skipped)
+                            .then(|| "dependency a failed".to_string()),
+                        reconnects: Vec::new(),
+                    },
+                )
+            })
+            .collect();
+        let overall_status = if statuses.iter().any(|(_, s)| *s == StepStatus::Fail) {
+            StepStatus::Fail
+        } else {
+            StepStatus::Pass
+        };
+        ExecutionResult {
+            template,
+            unit_name: name.to_string(),
+            target_name: target.to_string(),
+            overall_status,
+            step_results,
+            variables: HashMap::new(),
+            report_path: Some(dir.join(format!("reports/{name}.report.md"))),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_merges_targets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let results = [
+            result(dir, "curl", "riscv", &[("a", StepStatus::Pass)]),
+            result(
+                dir,
+                "wget",
+                "arm",
+                &[("a", StepStatus::Fail), ("b", StepStatus::Skipped)],
+            ),
+            result(dir, "curl_arm", "arm", &[("a", StepStatus::Pass)]),
+        ];
+
+        let summary = generate_summary_markdown(&results, Some(&dir.join("reports")), None);
+        assert!(summary.contains("| arm | 2 | 1 | 1 | 1 | 1 | 1 | 0 | 0 |\n"));
+        assert!(summary.contains("| riscv | 1 | 1 | 0 | 1 | 0 | 0 | 0 | 0 |\n"));
+        assert!(summary.contains("| **Total** | 3 | 2 | 1 | 2 | 1 | 1 | 0 | 0 |\n"));
+        assert!(summary.contains("## Compatibility Matrix\n\n| Unit | arm | riscv |"));
+
+        // 各目标分节按目标名称排序，节内按模板ID排序
+        let arm = summary.find("## Target: arm").unwrap();
+        let riscv = summary.find("## Target: riscv").unwrap();
+        assert!(arm < riscv);
+        let arm_section = &summary[arm..riscv];
+        assert!(
+            arm_section.find("| curl_arm | curl_arm | Pass").unwrap()
+                < arm_section.find("| wget | wget | Fail").unwrap()
+        );
+        assert!(arm_section.contains("| Fail | 0 | 1 | 1 | 0 | 0 | wget.report.md |"));
+        assert!(arm_section.contains("### Skipped Steps"));
+        assert!(arm_section.contains("| wget | b | dependency a failed |"));
+        assert!(!summary[riscv..].contains("Skipped Steps"));
+        assert!(!summary.contains("Template Errors"));
+    }
+
+    #[test]
+    fn test_summary_lists_template_errors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let broken = Arc::new(TestTemplate::placeholder(&dir.join("broken.test.md"), dir));
+        let results = [
+            result(dir, "curl", "riscv", &[("a", StepStatus::Pass)]),
+            ExecutionResult::failed(broken, "Unable to load target_config file | missing"),
+        ];
+
+        let summary = generate_summary_markdown(&results, None, None);
+        assert!(summary.contains("| **Total** | 2 | 1 | 1 | 1 | 0 | 0 | 0 | 0 |\n"));
+        let unknown = summary.find("## Target: unknown").unwrap();
+        let unknown_section = &summary[unknown..];
+        assert!(unknown_section.contains("| broken | broken | Fail |"));
+        assert!(unknown_section.contains(
+            "### Template Errors\n\n| Template ID | Error |\n|-------------|-------|\n| broken | Unable to load target_config file \\| missing |\n"
+        ));
+    }
+}
@@ -0,0 +1,297 @@
+//! TAP (Test Anything Protocol) 流式输出
+//!
+//! 每个可执行代码块步骤完成后立即输出一行 TAP 结果，供 prove 等 TAP 消费者实时读取。
+//! 跳过和未激活的步骤带有 `# SKIP` 指令，失败的步骤附带 YAML 诊断块。
+//! 执行结束后由调用方输出计划行 `1..N`。
+
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use crate::template::StepStatus;
+use crate::template::executor::StepResult;
+use crate::template::step::{ExecutionStep, StepType};
+
+/// 失败步骤的 YAML 诊断信息
+#[derive(Serialize)]
+struct Diagnostics<'a> {
+    exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assertion_error: Option<&'a str>,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    stdout: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    stderr: &'a str,
+}
+
+struct TapState {
+    writer: Box<dyn Write + Send>,
+    /// 已输出的测试点数量
+    count: usize,
+}
+
+/// TAP 输出流，可在多个并行执行的目标分组之间共享
+pub struct TapStream {
+    state: Mutex<TapState>,
+}
+
+impl fmt::Debug for TapStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TapStream")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+impl TapStream {
+    /// 创建输出到指定位置的 TAP 流，并写入版本行
+    pub fn new(mut writer: Box<dyn Write + Send>) -> Self {
+        let _ = writeln!(writer, "TAP version 13");
+        let _ = writer.flush();
+        Self {
+            state: Mutex::new(TapState { writer, count: 0 }),
+        }
+    }
+
+    /// 输出到标准输出的 TAP 流
+    pub fn stdout() -> Self {
+        Self::new(Box::new(io::stdout()))
+    }
+
+    /// 已输出的测试点数量
+    pub fn count(&self) -> usize {
+        self.state.lock().map_or(0, |state| state.count)
+    }
+
+    /// 步骤是否作为 TAP 测试点输出（只包括可执行的代码块）
+    pub fn is_test_step(step: &ExecutionStep) -> bool {
+        matches!(step.step_type, StepType::CodeBlock { .. })
+            && step
+                .original_parsed_step
+                .as_ref()
+                .is_some_and(|p| p.executable)
+    }
+
+    /// 输出一个步骤的结果，`result` 为 None 表示步骤因执行中止而没有运行
+    pub fn emit(&self, step: &ExecutionStep, result: Option<&StepResult>) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.count += 1;
+        let line = format_test_line(state.count, step, result);
+        let _ = state.writer.write_all(line.as_bytes());
+        let _ = state.writer.flush();
+    }
+
+    /// 输出计划行，在所有步骤执行完毕后调用
+    pub fn finish(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let plan = format!("1..{}\n", state.count);
+        let _ = state.writer.write_all(plan.as_bytes());
+        let _ = state.writer.flush();
+    }
+}
+
+/// 转义描述中的 `#` 和 `\`，并把换行替换为空格，避免被解析为 TAP 指令
+fn escape_description(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('\\', "\\\\")
+        .replace('#', "\\#")
+}
+
+/// 把跳过原因中的换行替换为空格
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 生成一个测试点的 TAP 文本（包括失败时的 YAML 诊断块）
+fn format_test_line(number: usize, step: &ExecutionStep, result: Option<&StepResult>) -> String {
+    let name = format!("{} - {}", step.id, escape_description(&step.description()));
+    let Some(result) = result else {
+        return format!("ok {number} - {name} # SKIP step was not executed\n");
+    };
+    match result.status {
+        StepStatus::Pass => format!("ok {number} - {name}\n"),
+        StepStatus::Fail => {
+            let diagnostics = Diagnostics {
+                exit_code: result.exit_code,
+                duration_ms: result.duration_ms,
+                assertion_error: result.assertion_error.as_deref(),
+                stdout: &result.stdout,
+                stderr: &result.stderr,
+            };
+            let mut text = format!("not ok {number} - {name}\n  ---\n");
+            let yaml = serde_yaml::to_string(&diagnostics).unwrap_or_default();
+            for line in yaml.lines() {
+                text.push_str("  ");
+                text.push_str(line);
+                text.push('\n');
+            }
+            text.push_str("  ...\n");
+            text
+        }
+        _ => {
+            let reason = result.skip_reason.as_deref().unwrap_or("step is inactive");
+            format!("ok {number} - {name} # SKIP {}\n", single_line(reason))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::test_support::{
+        continue_on_error_options, execute_template, write_template,
+    };
+    use crate::template::{BatchOptions, ParsedTestStep};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Arc;
+
+    /// 可在测试中读取内容的输出缓冲区
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn code_step(local_id: &str, command: &str) -> ExecutionStep {
+        ExecutionStep {
+            id: format!("sample::{local_id}"),
+            template_id: "sample".to_string(),
+            local_id: local_id.to_string(),
+            original_parsed_step: Some(ParsedTestStep {
+                id: local_id.to_string(),
+                description: None,
+                command: Some(command.to_string()),
+                depends_on: Vec::new(),
+                assertions: Vec::new(),
+                extractions: Vec::new(),
+                executable: true,
+                ref_command: None,
+                raw_content: String::new(),
+                active: None,
+                timeout_ms: None,
+            }),
+            step_type: StepType::CodeBlock {
+                lang: "bash".to_string(),
+                command: command.to_string(),
+                attributes: HashMap::new(),
+            },
+            dependencies: HashSet::new(),
+            span: None,
+        }
+    }
+
+    fn step_result(id: &str, status: StepStatus) -> StepResult {
+        StepResult {
+            id: id.to_string(),
+            description: None,
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms: Some(3),
+            assertion_error: None,
+            skip_reason: None,
+            reconnects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tap_stream_output() {
+        let buffer = SharedBuffer::default();
+        let tap = TapStream::new(Box::new(buffer.clone()));
+
+        tap.emit(
+            &code_step("ok", "echo ok"),
+            Some(&step_result("ok", StepStatus::Pass)),
+        );
+        tap.emit(
+            &code_step("broken", "grep '#' log\nexit 1"),
+            Some(&StepResult {
+                exit_code: 1,
+                stderr: "line 1\nline 2\n".to_string(),
+                assertion_error: Some("Expected exit code 0, got 1".to_string()),
+                ..step_result("broken", StepStatus::Fail)
+            }),
+        );
+        tap.emit(
+            &code_step("child", "echo child"),
+            Some(&StepResult {
+                skip_reason: Some("dependency broken failed".to_string()),
+                ..step_result("child", StepStatus::Skipped)
+            }),
+        );
+        tap.emit(
+            &code_step("inactive", "echo off"),
+            Some(&step_result("inactive", StepStatus::Skipped)),
+        );
+        tap.emit(&code_step("never", "echo never"), None);
+        tap.finish();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let expected = "TAP version 13\n\
+            ok 1 - sample::ok - echo ok\n\
+            not ok 2 - sample::broken - grep '\\#' log\n  \
+            ---\n  \
+            exit_code: 1\n  \
+            duration_ms: 3\n  \
+            assertion_error: Expected exit code 0, got 1\n  \
+            stderr: |\n    \
+            line 1\n    \
+            line 2\n  \
+            ...\n\
+            ok 3 - sample::child - echo child # SKIP dependency broken failed\n\
+            ok 4 - sample::inactive - echo off # SKIP step is inactive\n\
+            ok 5 - sample::never - echo never # SKIP step was not executed\n\
+            1..5\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_tap_stream_from_executed_template() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let path = write_template(
+            dir,
+            "sample.test.md",
+            "title: \"Sample\"\nunit_name: \"sample\"\nunit_version: \"1\"\n",
+            "\n# Sample\n\n```bash {id=\"greet\" exec=true assert.stdout_contains=\"bye\"}\necho hello\n```\n",
+        );
+        let buffer = SharedBuffer::default();
+        let tap = Arc::new(TapStream::new(Box::new(buffer.clone())));
+        let options = BatchOptions {
+            tap_stream: Some(tap.clone()),
+            ..continue_on_error_options()
+        };
+        execute_template(&path, dir, options);
+        tap.finish();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            output.contains("not ok 1 - sample::greet - echo hello\n  ---\n  exit_code: 0\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains(
+                "  assertion_error: 'Stdout does not contain expected pattern: ''bye'''\n"
+            ),
+            "{output}"
+        );
+        assert!(output.ends_with("1..1\n"));
+    }
+}
@@ -0,0 +1,65 @@
+//! 模板模块单元测试共用的辅助函数
+//!
+//! 目标配置写入测试的临时目录，模板中的 `target_config` 使用其绝对路径，
+//! 因此测试结果不依赖进程的工作目录。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::pool::ConnectionManagerPool;
+use crate::template::{
+    BatchExecutor, BatchOptions, ExecutionResult, ExecutorOptions, TestTemplate, VariableManager,
+};
+
+/// 在 `dir/targets/local/config.toml` 写入本地目标配置，返回其绝对路径
+pub fn write_local_target(dir: &Path) -> String {
+    let path = dir.join("targets/local/config.toml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        "testing_type = \"locally\"\nname = \"local\"\ndescription = \"local\"\n",
+    )
+    .unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+/// 在 `dir` 中写入使用本地目标的模板，`front_matter` 是除 `target_config` 外的前置数据
+pub fn write_template(dir: &Path, file_name: &str, front_matter: &str, body: &str) -> PathBuf {
+    let target_config = write_local_target(dir);
+    let path = dir.join(file_name);
+    fs::write(
+        &path,
+        format!("---\ntarget_config: \"{target_config}\"\n{front_matter}---\n{body}"),
+    )
+    .unwrap();
+    path
+}
+
+/// 断言失败后继续执行其余步骤的批量选项
+pub fn continue_on_error_options() -> BatchOptions {
+    BatchOptions {
+        executor_options: ExecutorOptions {
+            continue_on_error: true,
+            ..ExecutorOptions::default()
+        },
+        ..BatchOptions::default()
+    }
+}
+
+/// 通过 `BatchExecutor` 执行 `dir` 中的一个模板，返回执行结果
+pub fn execute_template(path: &Path, dir: &Path, options: BatchOptions) -> ExecutionResult {
+    let template = TestTemplate::from_file(path, dir).unwrap();
+    let template_id = template.get_template_id();
+    let options = BatchOptions {
+        test_directory: Some(dir.to_path_buf()),
+        ..options
+    };
+    let mut executor = BatchExecutor::new(
+        VariableManager::new(),
+        ConnectionManagerPool::new(),
+        Some(options),
+    );
+    executor.add_template(Arc::new(template)).unwrap();
+    executor.execute(&template_id).unwrap()
+}
@@ -0,0 +1,805 @@
+//! 模板静态校验
+//!
+//! `lintestor validate` 的实现：不加载目标配置之外的任何东西、不执行命令，直接扫描模板源文件，
+//! 一次性报告模板中的所有问题及其 `文件:行号`。这些问题在解析或执行时要么导致 panic，
+//! 要么只给出一条警告后被忽略。
+
+use regex::Regex;
+use serde_yaml::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::template::TemplateFilter;
+use crate::template::parser::{self, ASSERTION_KEYS, BlockPatterns, LineIndex};
+use crate::utils;
+
+/// 问题的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// 会导致模板无法解析或执行结果不可信，校验以非零状态退出
+    Error,
+    /// 可能是笔误，但不影响执行
+    Warning,
+}
+
+/// 校验发现的一个问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: PathBuf,
+    /// 从 1 开始的行号
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {severity}: {}",
+            self.file.display(),
+            self.line,
+            self.message
+        )
+    }
+}
+
+/// 一个模板的校验结果
+#[derive(Debug)]
+pub struct TemplateValidation {
+    /// 前置数据中的单元名称，无法读取时为 None
+    pub unit_name: Option<String>,
+    pub tags: Vec<String>,
+    /// 目标配置中的目标名称，目标配置无法加载时为 None
+    pub target_name: Option<String>,
+    /// 按行号排序的问题列表
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl TemplateValidation {
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+
+    /// 是否符合筛选条件，无法读取的字段视为符合，避免有问题的模板被筛掉
+    pub fn matches_filter(&self, filter: &TemplateFilter) -> bool {
+        let target_matches = match (&filter.target, &self.target_name) {
+            (Some(wanted), Some(target)) => wanted == target,
+            _ => true,
+        };
+        let unit_matches = match (&filter.unit, &self.unit_name) {
+            (Some(wanted), Some(unit)) => wanted == unit,
+            _ => true,
+        };
+        let tags_match = filter.tags.is_empty()
+            || self.unit_name.is_none()
+            || filter.tags.iter().any(|tag| self.tags.contains(tag));
+        target_matches && unit_matches && tags_match
+    }
+}
+
+/// 每个步骤执行后都会设置的内置变量
+const STEP_VARIABLES: &[&str] = &[
+    "stdout",
+    "stdout_summary",
+    "stderr",
+    "stderr_summary",
+    "exit_code",
+    "status.execution",
+    "status.assertion",
+];
+
+/// 所有模板都可以使用的内置变量
+const GLOBAL_VARIABLES: &[&str] = &[
+    "execution_date",
+    "execution_time",
+    "execution_datetime",
+    "execution_timestamp",
+    "metadata.title",
+    "metadata.unit_name",
+    "metadata.unit_version",
+    "metadata.target_name",
+    "metadata.target_description",
+];
+
+/// 前置数据中不属于自定义字段的键
+const STANDARD_METADATA_KEYS: &[&str] = &[
+    "title",
+    "target_config",
+    "unit_name",
+    "unit_version",
+    "tags",
+    "references",
+];
+
+fn is_step_variable(name: &str) -> bool {
+    STEP_VARIABLES.contains(&name)
+        || name
+            .strip_prefix("status.assertion.")
+            .is_some_and(|index| index.parse::<usize>().is_ok())
+}
+
+/// 把多行的错误信息合并为一行
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 前置数据中与正文校验相关的信息
+#[derive(Default)]
+struct FrontMatter {
+    /// 引用的命名空间到被引用模板文件的映射，文件不存在时为 None
+    namespaces: HashMap<String, Option<PathBuf>>,
+    /// 自定义字段对应的变量名（`metadata.<字段名>`）
+    custom_variables: HashSet<String>,
+}
+
+/// 正文中声明的步骤
+struct DeclaredStep {
+    local_id: String,
+    line: usize,
+}
+
+struct Validator<'a> {
+    file: &'a Path,
+    lines: LineIndex,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(file: &'a Path, content: &str) -> Self {
+        Self {
+            file,
+            lines: LineIndex::new(content),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// 字节偏移所在的行号
+    fn line_of(&self, offset: usize) -> usize {
+        self.lines.line_of(offset)
+    }
+
+    fn report(&mut self, severity: Severity, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: self.file.to_path_buf(),
+            line,
+            message,
+        });
+    }
+
+    fn error(&mut self, line: usize, message: impl Into<String>) {
+        self.report(Severity::Error, line, message.into());
+    }
+
+    fn warning(&mut self, line: usize, message: impl Into<String>) {
+        self.report(Severity::Warning, line, message.into());
+    }
+
+    /// 前置数据中顶层键所在的行号，找不到时返回第 1 行
+    fn key_line(content: &str, key: &str) -> usize {
+        content
+            .lines()
+            .enumerate()
+            .skip(1)
+            .take_while(|(_, line)| line.trim_end() != "---")
+            .find(|(_, line)| {
+                line.strip_prefix(key)
+                    .is_some_and(|rest| rest.trim_start().starts_with(':'))
+            })
+            .map_or(1, |(index, _)| index + 1)
+    }
+
+    fn check_front_matter(
+        &mut self,
+        content: &str,
+        yaml: &str,
+        tests_dir: &Path,
+        validation: &mut TemplateValidation,
+    ) -> FrontMatter {
+        let mut front_matter = FrontMatter::default();
+        let value: Value = match serde_yaml::from_str(yaml) {
+            Ok(value) => value,
+            Err(e) => {
+                // 前置数据从文件第 2 行开始
+                let line = e.location().map_or(1, |location| location.line() + 1);
+                self.error(
+                    line,
+                    format!(
+                        "Unable to parse YAML front matter: {}",
+                        single_line(&e.to_string())
+                    ),
+                );
+                return front_matter;
+            }
+        };
+
+        for key in ["title", "target_config", "unit_name", "unit_version"] {
+            if value[key].as_str().is_none() {
+                self.error(
+                    Self::key_line(content, key),
+                    format!("Metadata missing '{key}' field"),
+                );
+            }
+        }
+        validation.unit_name = value["unit_name"].as_str().map(str::to_string);
+        if let Value::Sequence(tags) = &value["tags"] {
+            validation.tags = tags
+                .iter()
+                .filter_map(|tag| tag.as_str().map(str::to_string))
+                .collect();
+        }
+
+        // 与加载模板时一样解析 target_config
+        if let Some(target_config) = value["target_config"].as_str() {
+            match parser::load_target_config(target_config) {
+                Ok(config) => validation.target_name = Some(config.get_name().to_string()),
+                Err(e) => self.error(
+                    Self::key_line(content, "target_config"),
+                    single_line(&e.to_string()),
+                ),
+            }
+        }
+
+        match &value["references"] {
+            Value::Sequence(references) => {
+                let line = Self::key_line(content, "references");
+                for reference in references {
+                    let template_path = reference["template"].as_str();
+                    let namespace = reference["as"].as_str();
+                    let (Some(template_path), Some(namespace)) = (template_path, namespace) else {
+                        self.error(
+                            line,
+                            "Item in references must have both 'template' and 'as' fields",
+                        );
+                        continue;
+                    };
+                    let path = tests_dir.join(template_path);
+                    let exists = path.is_file();
+                    if !exists {
+                        self.error(
+                            line,
+                            format!(
+                                "Referenced template '{template_path}' not found: {}",
+                                path.display()
+                            ),
+                        );
+                    }
+                    front_matter
+                        .namespaces
+                        .insert(namespace.to_string(), exists.then_some(path));
+                }
+            }
+            Value::Null => {}
+            _ => self.error(
+                Self::key_line(content, "references"),
+                "'references' must be a list",
+            ),
+        }
+
+        if let Value::Mapping(mapping) = &value {
+            front_matter.custom_variables = mapping
+                .iter()
+                .filter(|(_, value)| value.is_string())
+                .filter_map(|(key, _)| key.as_str())
+                .filter(|key| !STANDARD_METADATA_KEYS.contains(key))
+                .map(|key| format!("metadata.{key}"))
+                .collect();
+        }
+        front_matter
+    }
+
+    /// 解析属性，格式错误时报告并返回空属性
+    fn attributes(&mut self, line: usize, input: &str) -> HashMap<String, String> {
+        parser::parse_inline_attributes(input).unwrap_or_else(|e| {
+            self.error(line, format!("Invalid attributes: {e}"));
+            HashMap::new()
+        })
+    }
+
+    fn check_assertions(&mut self, line: usize, attributes: &HashMap<String, String>) {
+        let mut assertions: Vec<(&str, &String)> = attributes
+            .iter()
+            .filter_map(|(key, value)| key.strip_prefix("assert.").map(|name| (name, value)))
+            .collect();
+        assertions.sort();
+        for (name, value) in assertions {
+            if !ASSERTION_KEYS.contains(&name) {
+                self.error(
+                    line,
+                    format!(
+                        "Unknown assertion type 'assert.{name}' (supported: {})",
+                        ASSERTION_KEYS.join(", ")
+                    ),
+                );
+            } else if name == "exit_code" && value.parse::<i32>().is_err() {
+                self.error(
+                    line,
+                    format!("assert.exit_code must be an integer, got '{value}'"),
+                );
+            } else if name.ends_with("_matches")
+                && let Err(e) = Regex::new(value)
+            {
+                self.error(
+                    line,
+                    format!(
+                        "Invalid regex in assert.{name}: {}",
+                        single_line(&e.to_string())
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_body(
+        &mut self,
+        markdown: &str,
+        body_offset: usize,
+        template_id: &str,
+        front_matter: &FrontMatter,
+    ) {
+        let patterns = match BlockPatterns::new() {
+            Ok(patterns) => patterns,
+            Err(e) => {
+                self.error(1, e.to_string());
+                return;
+            }
+        };
+
+        let mut steps: Vec<DeclaredStep> = Vec::new();
+        // (行号, 描述, 引用的步骤)
+        let mut step_refs: Vec<(usize, String, String)> = Vec::new();
+        let mut extracted: HashMap<String, HashSet<String>> = HashMap::new();
+        let mut local_id_counter = 0;
+
+        for captures in patterns.combined.captures_iter(markdown) {
+            let Some(block) = captures.get(0) else {
+                continue;
+            };
+            let line = self.line_of(body_offset + block.start());
+            let (local_id, attributes) = if let Some(heading) = captures.name("heading") {
+                let Some(caps) = patterns.heading.captures(heading.as_str()) else {
+                    continue;
+                };
+                let attributes =
+                    self.attributes(line, caps.get(3).map_or("", |m| m.as_str()).trim());
+                let local_id = attributes.get("id").cloned().unwrap_or_else(|| {
+                    local_id_counter += 1;
+                    format!("heading_{local_id_counter}")
+                });
+                (local_id, attributes)
+            } else if let Some(output) = captures.name("output_block") {
+                let Some(caps) = patterns.output_block.captures(output.as_str()) else {
+                    continue;
+                };
+                let attributes = self.attributes(line, caps.get(1).map_or("", |m| m.as_str()));
+                match attributes.get("ref") {
+                    Some(ref_id) => {
+                        step_refs.push((line, "Output block".to_string(), ref_id.clone()))
+                    }
+                    None => self.error(line, "Output block is missing the 'ref' attribute"),
+                }
+                continue;
+            } else if let Some(code) = captures.name("code_block") {
+                let Some(caps) = patterns.code_block.captures(code.as_str()) else {
+                    continue;
+                };
+                let attributes = self.attributes(line, caps.get(3).map_or("", |m| m.as_str()));
+                let local_id = attributes.get("id").cloned().unwrap_or_else(|| {
+                    local_id_counter += 1;
+                    format!("codeblock_{local_id_counter}")
+                });
+                self.check_assertions(line, &attributes);
+                for extraction in parser::parse_extractions_from_attributes(&attributes) {
+                    if let Err(e) = Regex::new(&extraction.regex) {
+                        self.error(
+                            line,
+                            format!(
+                                "Invalid regex in extract.{}: {}",
+                                extraction.variable,
+                                single_line(&e.to_string())
+                            ),
+                        );
+                    }
+                    extracted
+                        .entry(local_id.clone())
+                        .or_default()
+                        .insert(extraction.variable);
+                }
+                (local_id, attributes)
+            } else {
+                continue;
+            };
+
+            if let Some(depends_on) = attributes.get("depends_on") {
+                for item in depends_on.trim_matches(|c| c == '[' || c == ']').split(',') {
+                    let dep = parser::extract_dep_id_from_dep_str(item);
+                    if !dep.is_empty() {
+                        step_refs.push((
+                            line,
+                            format!("Step '{local_id}' depends_on"),
+                            dep.to_string(),
+                        ));
+                    }
+                }
+            }
+            steps.push(DeclaredStep { local_id, line });
+        }
+
+        let mut first_seen: HashMap<&str, usize> = HashMap::new();
+        for step in &steps {
+            match first_seen.get(step.local_id.as_str()) {
+                Some(first_line) => {
+                    let message = format!(
+                        "Duplicate step id '{}' (first defined at line {first_line})",
+                        step.local_id
+                    );
+                    self.error(step.line, message);
+                }
+                None => {
+                    first_seen.insert(&step.local_id, step.line);
+                }
+            }
+        }
+        let ids: HashSet<&str> = first_seen.into_keys().collect();
+
+        let mut referenced_ids: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+        for (line, source, reference) in step_refs {
+            let (namespace, local_id) = match reference.split_once("::") {
+                Some((namespace, local_id)) if namespace != template_id => (namespace, local_id),
+                Some((_, local_id)) => ("", local_id),
+                None => ("", reference.as_str()),
+            };
+            if namespace.is_empty() {
+                if !ids.contains(local_id) {
+                    self.error(
+                        line,
+                        format!("{source} references unknown step '{reference}'"),
+                    );
+                }
+                continue;
+            }
+            match front_matter.namespaces.get(namespace) {
+                None => self.error(
+                    line,
+                    format!(
+                        "{source} references unknown namespace '{namespace}' in '{reference}' (declare it in 'references')"
+                    ),
+                ),
+                // 被引用的模板不存在，已在前置数据中报告
+                Some(None) => {}
+                Some(Some(path)) => {
+                    let declared = referenced_ids
+                        .entry(path.clone())
+                        .or_insert_with(|| declared_step_ids(path, &patterns));
+                    if !declared.contains(local_id) {
+                        self.error(
+                            line,
+                            format!(
+                                "{source} references unknown step '{reference}': no step '{local_id}' in {}",
+                                path.display()
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+
+        self.check_variables(
+            markdown,
+            body_offset,
+            template_id,
+            &ids,
+            &extracted,
+            front_matter,
+        );
+    }
+
+    /// 检查 `{{ ... }}` 中引用的变量是否有定义
+    ///
+    /// 其他模板中的变量在执行时才能确定，这里不做检查
+    fn check_variables(
+        &mut self,
+        markdown: &str,
+        body_offset: usize,
+        template_id: &str,
+        ids: &HashSet<&str>,
+        extracted: &HashMap<String, HashSet<String>>,
+        front_matter: &FrontMatter,
+    ) {
+        let (Ok(reference_re), Ok(name_re)) = (
+            Regex::new(r"\{\{\s*([^{}]*?)\s*\}\}"),
+            Regex::new(r"^[A-Za-z0-9_.\-]+(::[A-Za-z0-9_.\-]+){0,2}$"),
+        ) else {
+            return;
+        };
+        let is_global = |var: &str| {
+            GLOBAL_VARIABLES.contains(&var) || front_matter.custom_variables.contains(var)
+        };
+        let is_extracted_anywhere = |var: &str| extracted.values().any(|vars| vars.contains(var));
+        let is_step_var = |step: &str, var: &str| {
+            ids.contains(step)
+                && (is_step_variable(var) || extracted.get(step).is_some_and(|v| v.contains(var)))
+        };
+        let is_namespace = |scope: &str| front_matter.namespaces.contains_key(scope);
+
+        for captures in reference_re.captures_iter(markdown) {
+            let (Some(whole), Some(name)) = (captures.get(0), captures.get(1)) else {
+                continue;
+            };
+            let name = name.as_str();
+            // 表达式等无法静态判断的内容不检查
+            if !name_re.is_match(name) {
+                continue;
+            }
+            let parts: Vec<&str> = name.split("::").collect();
+            let defined = match parts.as_slice() {
+                [var] => is_global(var) || is_step_variable(var) || is_extracted_anywhere(var),
+                [scope, var] if ids.contains(scope) => is_step_var(scope, var),
+                [scope, var] if *scope == template_id || *scope == "GLOBAL" => is_global(var),
+                [scope, _] => is_namespace(scope),
+                [scope, "GLOBAL", var] if *scope == template_id || *scope == "GLOBAL" => {
+                    is_global(var)
+                }
+                [scope, step, var] if *scope == template_id => is_step_var(step, var),
+                [scope, _, _] => is_namespace(scope),
+                _ => true,
+            };
+            if !defined {
+                let line = self.line_of(body_offset + whole.start());
+                self.warning(
+                    line,
+                    format!("Variable '{name}' is referenced but never defined"),
+                );
+            }
+        }
+    }
+}
+
+/// 被引用模板中声明的步骤ID，文件无法读取时为空
+fn declared_step_ids(path: &Path, patterns: &BlockPatterns) -> HashSet<String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return HashSet::new();
+    };
+    let Ok((_, markdown)) = parser::extract_front_matter(&content) else {
+        return HashSet::new();
+    };
+    let mut ids = HashSet::new();
+    let mut local_id_counter = 0;
+    for captures in patterns.combined.captures_iter(markdown) {
+        let (attributes, prefix) = if let Some(heading) = captures.name("heading") {
+            let attributes = patterns
+                .heading
+                .captures(heading.as_str())
+                .and_then(|caps| caps.get(3).map(|m| m.as_str().trim().to_string()));
+            (attributes, "heading")
+        } else if let Some(code) = captures.name("code_block") {
+            let attributes = patterns
+                .code_block
+                .captures(code.as_str())
+                .and_then(|caps| caps.get(3).map(|m| m.as_str().to_string()));
+            (attributes, "codeblock")
+        } else {
+            continue;
+        };
+        let explicit_id = attributes
+            .and_then(|input| parser::parse_inline_attributes(&input).ok())
+            .and_then(|mut attributes| attributes.remove("id"));
+        ids.insert(explicit_id.unwrap_or_else(|| {
+            local_id_counter += 1;
+            format!("{prefix}_{local_id_counter}")
+        }));
+    }
+    ids
+}
+
+/// 校验一个模板文件
+pub fn validate_template(path: &Path, tests_dir: &Path) -> TemplateValidation {
+    let mut validation = TemplateValidation {
+        unit_name: None,
+        tags: Vec::new(),
+        target_name: None,
+        diagnostics: Vec::new(),
+    };
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            let mut validator = Validator::new(path, "");
+            validator.error(1, format!("Unable to read template file: {e}"));
+            validation.diagnostics = validator.diagnostics;
+            return validation;
+        }
+    };
+
+    let mut validator = Validator::new(path, &content);
+    match parser::extract_front_matter(&content) {
+        Ok((yaml, markdown)) => {
+            let front_matter =
+                validator.check_front_matter(&content, &yaml, tests_dir, &mut validation);
+            let template_id = utils::get_template_id_from_path(tests_dir, path);
+            // 正文是文件内容的后缀
+            let body_offset = content.len() - markdown.len();
+            validator.check_body(markdown, body_offset, &template_id, &front_matter);
+        }
+        Err(e) => validator.error(1, e.to_string()),
+    }
+
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by(|a, b| (a.line, &a.message).cmp(&(b.line, &b.message)));
+    validation.diagnostics = diagnostics;
+    validation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::TestTemplate;
+    use crate::template::test_support::write_local_target;
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let target_config = write_local_target(dir);
+        fs::write(
+            dir.join("base.test.md"),
+            format!("---\ntitle: \"Base\"\ntarget_config: \"{target_config}\"\nunit_name: \"base\"\nunit_version: \"1\"\n---\n\n```bash {{id=\"setup\"}}\necho base\n```\n"),
+        )
+        .unwrap();
+        let path = dir.join("broken.test.md");
+        fs::write(
+            &path,
+            [
+                "---",                                                               // 1
+                "title: \"Broken\"",                                                 // 2
+                "target_config: \"targets/missing/config.toml\"",                    // 3
+                "unit_name: \"broken\"",                                             // 4
+                "unit_version: \"1\"",                                               // 5
+                "owner: \"qa\"",                                                     // 6
+                "references:",                                                       // 7
+                "  - template: \"base.test.md\"",                                    // 8
+                "    as: base",                                                      // 9
+                "---",                                                               // 10
+                "",                                                                  // 11
+                "# Broken {id=\"top\"}",                                             // 12
+                "",                                                                  // 13
+                "```bash {id=\"a\" extract.ver=/(\\d+/ assert.stdout_matches=/x(/}", // 14
+                "echo {{ metadata.owner }} {{ ver }}",                               // 15
+                "```",                                                               // 16
+                "",                                                                  // 17
+                "```bash {id=\"a\" depends_on=[\"ghost\", \"base::setup\", \"base::nope\", \"other::x\"] assert.exit_cod=0}", // 18
+                "echo {{ a::stdout }} {{ a::missing }} {{ base::setup::anything }}", // 19
+                "```",                                                               // 20
+                "",                                                                  // 21
+                "```output {ref=\"nowhere\"}",                                       // 22
+                "```",                                                               // 23
+                "",                                                                  // 24
+                "```bash {id=\"b\" assert.exit_code=zero}",                          // 25
+                "echo {{ undefined_var }}",                                          // 26
+                "```",                                                               // 27
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let validation = validate_template(&path, dir);
+        let messages: Vec<(usize, Severity, &str)> = validation
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.severity, d.message.as_str()))
+            .collect();
+        let has = |line: usize, severity: Severity, fragment: &str| {
+            messages
+                .iter()
+                .any(|(l, s, m)| *l == line && *s == severity && m.contains(fragment))
+        };
+
+        assert!(has(3, Severity::Error, "Unable to load target_config file"));
+        assert!(has(14, Severity::Error, "Invalid regex in extract.ver"));
+        assert!(has(
+            14,
+            Severity::Error,
+            "Invalid regex in assert.stdout_matches"
+        ));
+        assert!(has(
+            18,
+            Severity::Error,
+            "Duplicate step id 'a' (first defined at line 14)"
+        ));
+        assert!(has(18, Severity::Error, "unknown step 'ghost'"));
+        assert!(has(18, Severity::Error, "unknown step 'base::nope'"));
+        assert!(has(18, Severity::Error, "unknown namespace 'other'"));
+        assert!(has(
+            18,
+            Severity::Error,
+            "Unknown assertion type 'assert.exit_cod'"
+        ));
+        assert!(has(19, Severity::Warning, "Variable 'a::missing'"));
+        assert!(has(
+            22,
+            Severity::Error,
+            "Output block references unknown step 'nowhere'"
+        ));
+        assert!(has(
+            25,
+            Severity::Error,
+            "assert.exit_code must be an integer"
+        ));
+        assert!(has(26, Severity::Warning, "Variable 'undefined_var'"));
+        // 已定义的变量和存在的跨模板步骤不报告
+        assert!(!messages.iter().any(|(_, _, m)| m.contains("base::setup'")
+            || m.contains("metadata.owner")
+            || m.contains("'ver'")
+            || m.contains("a::stdout")
+            || m.contains("base::setup::anything")));
+        assert_eq!(validation.error_count(), 10);
+        assert_eq!(validation.warning_count(), 2);
+        assert_eq!(validation.unit_name.as_deref(), Some("broken"));
+        assert!(validation.target_name.is_none());
+
+        let clean = validate_template(&dir.join("base.test.md"), dir);
+        assert!(clean.diagnostics.is_empty(), "{:?}", clean.diagnostics);
+        assert_eq!(clean.target_name.as_deref(), Some("local"));
+        assert!(validation.diagnostics[0].to_string().starts_with(&format!(
+            "{}:3: error: Unable to load target_config file targets/missing/config.toml",
+            path.display()
+        )));
+    }
+
+    #[test]
+    fn test_validate_resolves_target_config_like_loading() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let absolute = write_local_target(dir);
+        let only_in_tests_dir = "targets/only_in_tests_dir/config.toml";
+        fs::create_dir_all(dir.join("targets/only_in_tests_dir")).unwrap();
+        fs::copy(&absolute, dir.join(only_in_tests_dir)).unwrap();
+
+        // 绝对路径、只存在于测试目录下的相对路径、相对于当前目录（仓库根目录）的相对路径
+        for target_config in [
+            absolute.as_str(),
+            only_in_tests_dir,
+            "targets/local/config.toml",
+        ] {
+            let path = dir.join("sample.test.md");
+            fs::write(
+                &path,
+                format!("---\ntitle: \"Sample\"\ntarget_config: \"{target_config}\"\nunit_name: \"sample\"\nunit_version: \"1\"\n---\n"),
+            )
+            .unwrap();
+
+            let validation = validate_template(&path, dir);
+            let loaded = TestTemplate::from_file(&path, dir);
+            assert_eq!(
+                validation.error_count() == 0,
+                loaded.is_ok(),
+                "{target_config}: {:?}",
+                validation.diagnostics
+            );
+            assert_eq!(
+                validation.target_name,
+                loaded
+                    .ok()
+                    .map(|t| t.metadata.target_config.get_name().to_string()),
+                "{target_config}"
+            );
+        }
+    }
+}
@@ -8,11 +8,9 @@ use log::{debug, info, warn};
 use regex::Regex;
 use std::collections::HashMap;
 use std::error::Error;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use crate::utils;
-
 /// 变量管理器
 ///
 /// 负责变量的存储、查找和替换,提供统一的变量管理接口
@@ -124,11 +122,9 @@ impl VariableManager {
         self.register_namespace(&template_id, &template_id);
         // 其他在这里引用的命名空间（直接导入全局了）
         for item in template.metadata.references.iter() {
-            let template_path = item.template_path.clone();
             let as_namespace = item.namespace.clone();
             // 使用 template 自身的 tests_dir 来解析引用的模板ID
-            let item_template_id =
-                utils::get_template_id_from_path(&template.tests_dir, Path::new(&template_path));
+            let item_template_id = item.template_id(&template.tests_dir);
             self.register_namespace(&as_namespace, &item_template_id);
         }
 
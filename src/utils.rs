@@ -7,11 +7,27 @@ use crate::template::{self, BatchOptions};
 use log::{error, warn};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::{
+    cell::RefCell,
     error::Error,
     fs,
     path::{Path, PathBuf},
 };
 
+thread_local! {
+    /// 当前线程日志消息的前缀（并行执行时用于区分不同目标）
+    static LOG_PREFIX: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// 设置当前线程的日志前缀，传入 `None` 清除前缀
+pub fn set_log_prefix(prefix: Option<String>) {
+    LOG_PREFIX.with(|p| *p.borrow_mut() = prefix);
+}
+
+/// 获取当前线程的日志前缀
+pub fn log_prefix() -> Option<String> {
+    LOG_PREFIX.with(|p| p.borrow().clone())
+}
+
 /// 标准化模板ID
 ///
 /// 移除末尾的 `.test` 后缀，并确保ID不包含分隔符